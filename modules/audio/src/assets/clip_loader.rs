@@ -2,12 +2,17 @@ use std::io::Cursor;
 use std::sync::Arc;
 
 use crayon::errors::Result;
+use crayon::res::header::Format;
 use crayon::res::utils::prelude::ResourceLoader;
 use lewton::inside_ogg::OggStreamReader;
 
 use super::clip::*;
 
-pub const MAGIC: [u8; 8] = [b'C', b'S', b'F', b'X', b' ', 0, 0, 1];
+pub const FORMAT: Format = Format {
+    tag: *b"CSFX",
+    version: 1,
+    migrations: &[],
+};
 
 #[derive(Clone)]
 pub struct AudioClipLoader {}
@@ -24,11 +29,8 @@ impl ResourceLoader for AudioClipLoader {
     type Resource = Arc<AudioClip>;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if &bytes[0..8] != MAGIC {
-            bail!("[AudioClipLoader] MAGIC number not match.");
-        }
-
-        let cursor = Cursor::new(&bytes[8..]);
+        let payload = FORMAT.read(bytes)?;
+        let cursor = Cursor::new(&payload[..]);
         let mut stream_reader = OggStreamReader::new(cursor).unwrap();
 
         let mut clip = AudioClip {
@@ -57,4 +59,8 @@ impl ResourceLoader for AudioClipLoader {
     }
 
     fn delete(&self, _: Self::Handle, _: Self::Resource) {}
+
+    fn size(&self, clip: &Self::Resource) -> usize {
+        clip.pcm.len() * std::mem::size_of::<i16>()
+    }
 }
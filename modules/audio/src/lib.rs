@@ -29,7 +29,7 @@ pub use self::inside::{discard, setup};
 
 use crayon::errors::Result;
 use crayon::math::prelude::Vector3;
-use crayon::res::prelude::ResourceState;
+use crayon::res::utils::prelude::{GroupMember, ResourceState};
 use crayon::uuid::Uuid;
 
 use self::assets::prelude::AudioClipHandle;
@@ -62,12 +62,42 @@ pub fn clip_state(handle: AudioClipHandle) -> ResourceState {
     ctx().clip_state(handle)
 }
 
+/// Returns the error of clip if it's failed to load.
+#[inline]
+pub fn clip_error(handle: AudioClipHandle) -> Option<failure::Error> {
+    ctx().clip_error(handle)
+}
+
+/// Returns the loaded and total bytes of the clip file.
+#[inline]
+pub fn clip_bytes(handle: AudioClipHandle) -> (u64, u64) {
+    ctx().clip_bytes(handle)
+}
+
 /// Deletes a `AudioClip` resource from `AudioSystem`.
 #[inline]
 pub fn delete_clip(handle: AudioClipHandle) {
     ctx().delete_clip(handle);
 }
 
+impl GroupMember for AudioClipHandle {
+    fn state(&self) -> ResourceState {
+        clip_state(*self)
+    }
+
+    fn bytes(&self) -> (u64, u64) {
+        clip_bytes(*self)
+    }
+
+    fn error(&self) -> Option<failure::Error> {
+        clip_error(*self)
+    }
+
+    fn release(&self) {
+        delete_clip(*self)
+    }
+}
+
 /// Plays a audio source, returning a `AudioSourceHandle` for it.
 #[inline]
 pub fn play<T>(params: T) -> Result<AudioSourceHandle>
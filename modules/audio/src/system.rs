@@ -80,6 +80,19 @@ impl AudioSystem {
         self.clips.read().unwrap().state(handle)
     }
 
+    /// Returns the error of clip if it's failed to load.
+    #[inline]
+    pub fn clip_error(&self, handle: AudioClipHandle) -> Option<failure::Error> {
+        let clips = self.clips.read().unwrap();
+        clips.error(handle).map(|err| format_err!("{}", err))
+    }
+
+    /// Returns the loaded and total bytes of the clip file.
+    #[inline]
+    pub fn clip_bytes(&self, handle: AudioClipHandle) -> (u64, u64) {
+        self.clips.read().unwrap().bytes(handle)
+    }
+
     /// Deletes a `AudioClip` resource from `AudioSystem`.
     #[inline]
     pub fn delete_clip(&self, handle: AudioClipHandle) {
@@ -2,14 +2,17 @@ use std::io::Cursor;
 use std::sync::Arc;
 
 use crayon::errors::Result;
+use crayon::res::header::Format;
 use crayon::res::utils::prelude::ResourceLoader;
 use crayon::{bincode, video};
 
 use super::prefab::*;
 
-pub const MAGIC: [u8; 8] = [
-    'P' as u8, 'R' as u8, 'E' as u8, 'B' as u8, ' ' as u8, 0, 0, 1,
-];
+pub const FORMAT: Format = Format {
+    tag: *b"PREB",
+    version: 1,
+    migrations: &[],
+};
 
 #[derive(Clone)]
 pub struct PrefabLoader {}
@@ -26,11 +29,8 @@ impl ResourceLoader for PrefabLoader {
     type Resource = Arc<Prefab>;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if &bytes[0..8] != &MAGIC[..] {
-            bail!("[PrefabLoader] MAGIC number not match.");
-        }
-
-        let mut file = Cursor::new(&bytes[8..]);
+        let payload = FORMAT.read(bytes)?;
+        let mut file = Cursor::new(&payload[..]);
         let mut prefab: Prefab = bincode::deserialize_from(&mut file)?;
 
         for &v in &prefab.universe_meshes {
@@ -27,7 +27,7 @@ mod system;
 pub use self::inside::{discard, setup};
 pub use self::system::WorldDefaultResources;
 
-use crayon::res::utils::prelude::ResourceState;
+use crayon::res::utils::prelude::{GroupMember, ResourceState};
 use std::sync::Arc;
 
 use self::assets::prelude::{Prefab, PrefabHandle};
@@ -69,12 +69,42 @@ pub fn prefab_state(handle: PrefabHandle) -> ResourceState {
     ctx().prefab_state(handle)
 }
 
+/// Returns the error of prefab or its meshes if they are failed to load.
+#[inline]
+pub fn prefab_error(handle: PrefabHandle) -> Option<failure::Error> {
+    ctx().prefab_error(handle)
+}
+
+/// Returns the loaded and total bytes of the prefab file and its meshes.
+#[inline]
+pub fn prefab_bytes(handle: PrefabHandle) -> (u64, u64) {
+    ctx().prefab_bytes(handle)
+}
+
 /// Delete a prefab object from this world.
 #[inline]
 pub fn delete_prefab(handle: PrefabHandle) {
     ctx().delete_prefab(handle);
 }
 
+impl GroupMember for PrefabHandle {
+    fn state(&self) -> ResourceState {
+        prefab_state(*self)
+    }
+
+    fn bytes(&self) -> (u64, u64) {
+        prefab_bytes(*self)
+    }
+
+    fn error(&self) -> Option<failure::Error> {
+        prefab_error(*self)
+    }
+
+    fn release(&self) {
+        delete_prefab(*self)
+    }
+}
+
 /// Return the default resources in this world.
 #[inline]
 pub fn default() -> WorldDefaultResources {
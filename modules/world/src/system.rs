@@ -49,7 +49,9 @@ impl WorldSystem {
             quad: mesh_builder::quad()?,
         };
 
-        let prefabs = Arc::new(RwLock::new(ResourcePool::new(PrefabLoader::new())));
+        let mut prefabs = ResourcePool::new(PrefabLoader::new());
+        prefabs.set_load_dependencies(true);
+        let prefabs = Arc::new(RwLock::new(prefabs));
 
         let shared = WorldSystem {
             prefabs: prefabs.clone(),
@@ -82,10 +84,48 @@ impl WorldSystem {
         self.prefabs.read().unwrap().resource(handle).cloned()
     }
 
-    /// Query the resource state of specified prefab.
-    #[inline]
+    /// Query the resource state of specified prefab, which is ready only if all of its
+    /// meshes are ready as well.
     pub fn prefab_state(&self, handle: PrefabHandle) -> ResourceState {
-        self.prefabs.read().unwrap().state(handle)
+        let prefabs = self.prefabs.read().unwrap();
+        match prefabs.resource(handle) {
+            Some(prefab) => {
+                let meshes = prefab.meshes.iter().map(|&v| crayon::video::mesh_state(v));
+                ResourceState::combine(meshes)
+            }
+            None => prefabs.state(handle),
+        }
+    }
+
+    /// Returns the error of prefab if it's failed to load, or the error of its first failed
+    /// mesh once the prefab is loaded.
+    pub fn prefab_error(&self, handle: PrefabHandle) -> Option<Error> {
+        let prefabs = self.prefabs.read().unwrap();
+        match prefabs.resource(handle) {
+            Some(prefab) => prefab
+                .meshes
+                .iter()
+                .filter_map(|&v| crayon::video::mesh_error(v))
+                .next(),
+            None => prefabs.error(handle).map(|err| format_err!("{}", err)),
+        }
+    }
+
+    /// Returns the loaded and total bytes of the prefab file, and the files of its meshes
+    /// once the prefab is loaded.
+    pub fn prefab_bytes(&self, handle: PrefabHandle) -> (u64, u64) {
+        let prefabs = self.prefabs.read().unwrap();
+        let (mut loaded, mut total) = prefabs.bytes(handle);
+
+        if let Some(prefab) = prefabs.resource(handle) {
+            for &v in &prefab.meshes {
+                let (l, t) = crayon::video::mesh_bytes(v);
+                loaded += l;
+                total += t;
+            }
+        }
+
+        (loaded, total)
     }
 
     /// Delete a prefab object from this world.
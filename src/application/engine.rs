@@ -48,7 +48,7 @@ impl Drop for EngineSystem {
 
 impl EngineSystem {
     /// Setup engine with specified settings.
-    pub unsafe fn new(params: Params) -> Result<Self> {
+    pub unsafe fn new(mut params: Params) -> Result<Self> {
         #[cfg(not(target_arch = "wasm32"))]
         crate::sched::inside::setup(4, None, None);
         #[cfg(target_arch = "wasm32")]
@@ -57,6 +57,8 @@ impl EngineSystem {
         crate::window::inside::setup(params.window)?;
         crate::video::inside::setup()?;
         crate::input::inside::setup(params.input);
+
+        params.res.variants.extend(crate::video::texture_variants());
         crate::res::inside::setup(params.res)?;
 
         let state = Arc::new(EngineState {
@@ -72,7 +74,7 @@ impl EngineSystem {
         Ok(sys)
     }
 
-    pub unsafe fn new_headless(params: Params) -> Result<Self> {
+    pub unsafe fn new_headless(mut params: Params) -> Result<Self> {
         #[cfg(not(target_arch = "wasm32"))]
         crate::sched::inside::setup(4, None, None);
         #[cfg(target_arch = "wasm32")]
@@ -81,6 +83,8 @@ impl EngineSystem {
         crate::window::inside::headless();
         crate::video::inside::headless();
         crate::input::inside::setup(params.input);
+
+        params.res.variants.extend(crate::video::texture_variants());
         crate::res::inside::setup(params.res)?;
 
         let state = Arc::new(EngineState {
@@ -50,6 +50,7 @@ use self::time::TimeSystem;
 
 use crate::input::InputParams;
 use crate::res::ResourceParams;
+use crate::sched::prelude::LatchProbe;
 use crate::window::WindowParams;
 
 /// A structure containing configuration data for the game engine, which are
@@ -133,20 +134,39 @@ where
 
 #[doc(hidden)]
 pub fn oneshot() -> Result<()> {
+    oneshot_with(Params::default())
+}
+
+/// Setup the core system headlessly with `params`, and runs frames until the manifests of
+/// `params.res.dirs` are loaded. It's used by tests that load resources.
+#[doc(hidden)]
+pub fn oneshot_with(params: Params) -> Result<()> {
     unsafe {
         debug_assert!(LIFECYCLE_CTX.is_null(), "duplicated setup of crayon.");
 
-        let params = Params::default();
-
         sys::init();
+
+        let dirs = params.res.dirs.clone();
         LIFECYCLE_CTX = Box::into_raw(Box::new(LifecycleSystem::new()));
         TIME_CTX = Box::into_raw(Box::new(TimeSystem::new(&params)));
         CTX = Box::into_raw(Box::new(EngineSystem::new_headless(params)?));
 
-        ctx().run_oneshot()
+        let latch = crate::res::inside::load_manifests(dirs)?;
+        ctx().run_oneshot()?;
+        while !latch.is_set() {
+            ctx().run_oneshot()?;
+        }
+
+        Ok(())
     }
 }
 
+/// Runs one more frame of the system that is set up by `oneshot`.
+#[doc(hidden)]
+pub fn step() -> Result<()> {
+    ctx().run_oneshot()
+}
+
 /// Discard the core system.
 #[inline]
 pub fn discard() {
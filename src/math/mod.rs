@@ -15,4 +15,5 @@ pub mod prelude {
     pub use cgmath::prelude::{One, Zero};
     pub use cgmath::{Angle, Deg, Euler, Quaternion, Rad, Rotation};
     pub use cgmath::{Matrix, Matrix2, Matrix3, Matrix4, SquareMatrix, Vector2, Vector3, Vector4};
+    pub use cgmath::{Point2, Point3};
 }
@@ -0,0 +1,335 @@
+//! Sends loading requests to VFS in the order of priority, with a limit of in-flight
+//! requests for every schema.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use crate::sched::prelude::LockLatch;
+use crate::utils::hash::FastHashMap;
+
+use super::request::{CancelToken, Priority, Progress, Response};
+use super::url::Url;
+use super::vfs::VFS;
+
+pub struct Job {
+    pub vfs: Arc<dyn VFS>,
+    pub url: Url,
+    /// Reads the byte range of file instead of the whole if specified.
+    pub range: Option<Range<u64>>,
+    /// The received bytes of the whole file, which are not reported for ranges.
+    pub progress: Progress,
+    pub state: Arc<LockLatch<Response>>,
+    pub token: CancelToken,
+    pub priority: Priority,
+}
+
+struct Pending {
+    job: Job,
+    seq: u64,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.cmp(rhs) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl Ord for Pending {
+    // The max-heap pops the highest priority first, and the earliest one among them.
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        self.job
+            .priority
+            .cmp(&rhs.job.priority)
+            .then_with(|| rhs.seq.cmp(&self.seq))
+    }
+}
+
+#[derive(Default)]
+struct Lane {
+    running: usize,
+    pending: BinaryHeap<Pending>,
+}
+
+#[derive(Default)]
+struct Lanes {
+    seq: u64,
+    lanes: FastHashMap<String, Lane>,
+    /// The jobs that have been popped from lanes, but not sent to VFS yet.
+    ready: VecDeque<(String, Job, usize)>,
+    /// If there is a thread that is sending the ready jobs.
+    draining: bool,
+}
+
+impl Lanes {
+    fn pop(&mut self, schema: &str, limit: usize) {
+        let lane = match self.lanes.get_mut(schema) {
+            Some(lane) => lane,
+            None => return,
+        };
+
+        while lane.running < limit.max(1) {
+            match lane.pending.pop() {
+                Some(v) if v.job.token.is_canceled() => continue,
+                Some(v) => {
+                    lane.running += 1;
+                    self.ready.push_back((schema.to_owned(), v.job, limit));
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Dispatcher {
+    lanes: Mutex<Lanes>,
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Queues the job, which is sent to VFS once there are less than `limit` in-flight
+    /// requests of its schema.
+    pub fn submit(self: &Arc<Self>, job: Job, limit: usize) {
+        let schema = job.url.schema().to_owned();
+
+        {
+            let mut lanes = self.lanes.lock().unwrap();
+            lanes.seq += 1;
+
+            let seq = lanes.seq;
+            let lane = lanes.lanes.entry(schema.clone()).or_default();
+            lane.pending.push(Pending { job, seq });
+            lanes.pop(&schema, limit);
+        }
+
+        self.drain();
+    }
+
+    // Sends the ready jobs in a loop. VFS might respond synchronously, which pops the next
+    // jobs while the previous ones are being sent, so only one thread drains the queue at
+    // a time instead of recursing into it.
+    fn drain(self: &Arc<Self>) {
+        {
+            let mut lanes = self.lanes.lock().unwrap();
+            if lanes.draining {
+                return;
+            }
+
+            lanes.draining = true;
+        }
+
+        loop {
+            let (schema, job, limit) = {
+                let mut lanes = self.lanes.lock().unwrap();
+                match lanes.ready.pop_front() {
+                    Some(v) => v,
+                    None => {
+                        lanes.draining = false;
+                        return;
+                    }
+                }
+            };
+
+            self.start(schema, job, limit);
+        }
+    }
+
+    fn start(self: &Arc<Self>, schema: String, job: Job, limit: usize) {
+        let mut running = Running {
+            dispatcher: self.clone(),
+            schema,
+            limit,
+            url: job.url.clone(),
+            state: Some(job.state),
+            token: job.token,
+        };
+
+        let latch = LockLatch::with_callback(move |rsp: Response| running.set(rsp));
+        let (vfs, url, range, progress) = (job.vfs, job.url, job.range, job.progress);
+        let func = move || match range {
+            Some(range) => vfs.request_range(&url, range, Arc::new(latch)),
+            None => vfs.request_with_progress(&url, progress, Arc::new(latch)),
+        };
+
+        if crate::sched::valid() {
+            crate::sched::spawn(func);
+        } else {
+            func();
+        }
+    }
+
+    fn finish(self: &Arc<Self>, schema: &str, limit: usize) {
+        {
+            let mut lanes = self.lanes.lock().unwrap();
+            if let Some(lane) = lanes.lanes.get_mut(schema) {
+                lane.running -= 1;
+            }
+
+            lanes.pop(schema, limit);
+        }
+
+        self.drain();
+    }
+}
+
+/// A in-flight request. It frees its slot of lane once it's responded, or dropped by VFS
+/// without any response, in which case the request is failed, so it never hangs.
+struct Running {
+    dispatcher: Arc<Dispatcher>,
+    schema: String,
+    limit: usize,
+    url: Url,
+    state: Option<Arc<LockLatch<Response>>>,
+    token: CancelToken,
+}
+
+impl Running {
+    fn set(&mut self, rsp: Response) {
+        if let Some(state) = self.state.take() {
+            if !self.token.is_canceled() {
+                state.set(rsp);
+            }
+        }
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if self.state.is_some() {
+            let err = format_err!("[Dispatcher] {} is dropped without response.", self.url);
+            self.set(Err(err));
+        }
+
+        self.dispatcher.finish(&self.schema, self.limit);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{self, AtomicBool};
+
+    use super::*;
+
+    /// A VFS that holds the requests until they are released manually, or responds them
+    /// synchronously.
+    #[derive(Default)]
+    struct Manual {
+        requests: Mutex<Vec<(String, Arc<LockLatch<Response>>)>>,
+        sync: AtomicBool,
+    }
+
+    impl std::fmt::Debug for Manual {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "Manual")
+        }
+    }
+
+    impl VFS for Manual {
+        fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+            if self.sync.load(atomic::Ordering::SeqCst) {
+                return state.set(Ok(Vec::new().into_boxed_slice()));
+            }
+
+            self.requests
+                .lock()
+                .unwrap()
+                .push((url.path().to_owned(), state));
+        }
+    }
+
+    impl Manual {
+        fn release(&self) -> String {
+            let (path, state) = self.requests.lock().unwrap().remove(0);
+            state.set(Ok(Vec::new().into_boxed_slice()));
+            path
+        }
+    }
+
+    fn submit(
+        dispatcher: &Arc<Dispatcher>,
+        vfs: &Arc<Manual>,
+        path: &str,
+        priority: Priority,
+    ) -> (Arc<LockLatch<Response>>, CancelToken) {
+        let job = Job {
+            vfs: vfs.clone(),
+            url: Url::new(format!("mem://{}", path)).unwrap(),
+            range: None,
+            progress: Progress::new(),
+            state: Arc::new(LockLatch::new()),
+            token: CancelToken::new(),
+            priority,
+        };
+
+        let v = (job.state.clone(), job.token.clone());
+        dispatcher.submit(job, 1);
+        v
+    }
+
+    #[test]
+    fn dispatch() {
+        let vfs = Arc::new(Manual::default());
+        let dispatcher = Arc::new(Dispatcher::new());
+        let submit = |path: &str, priority: Priority| submit(&dispatcher, &vfs, path, priority).1;
+
+        submit("/a", Priority::Low);
+        submit("/b", Priority::Low);
+        submit("/c", Priority::High);
+        submit("/d", Priority::Normal);
+        submit("/e", Priority::High).cancel();
+
+        assert_eq!(vfs.requests.lock().unwrap().len(), 1);
+        assert_eq!(vfs.release(), "/a");
+        assert_eq!(vfs.release(), "/c");
+        assert_eq!(vfs.release(), "/d");
+        assert_eq!(vfs.release(), "/b");
+        assert!(vfs.requests.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn dropped() {
+        let vfs = Arc::new(Manual::default());
+        let dispatcher = Arc::new(Dispatcher::new());
+
+        let (a, _) = submit(&dispatcher, &vfs, "/a", Priority::Normal);
+        let (b, _) = submit(&dispatcher, &vfs, "/b", Priority::Normal);
+
+        // The request that is dropped by VFS fails, and frees its slot.
+        let request = vfs.requests.lock().unwrap().remove(0);
+        drop(request);
+        assert!(a.take().is_err());
+        assert_eq!(vfs.release(), "/b");
+        assert!(b.take().is_ok());
+    }
+
+    #[test]
+    fn synchronous() {
+        let vfs = Arc::new(Manual::default());
+        let dispatcher = Arc::new(Dispatcher::new());
+
+        let states: Vec<_> = (0..10_000)
+            .map(|i| submit(&dispatcher, &vfs, &format!("/{}", i), Priority::Normal).0)
+            .collect();
+
+        // The pending requests are sent in a loop, instead of recursion.
+        vfs.sync.store(true, atomic::Ordering::SeqCst);
+        vfs.release();
+
+        for v in &states[1..] {
+            assert!(v.take().is_ok());
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! The common header of resource files.
+//!
+//! Every resource file starts with a 20-bytes header, which is followed by the payload:
+//!
+//! ```text
+//! [0..4]   The tag of format, e.g. `VTEX`.
+//! [4..8]   The marker `[b'#', 0, 0, 0]`.
+//! [8..12]  The version of payload format in little-endian.
+//! [12..16] The length of payload in little-endian.
+//! [16..20] The CRC32 checksum of payload in little-endian.
+//! ```
+//!
+//! Files that are built by older tools starts with a 8-bytes MAGIC number like
+//! `[b'V', b'T', b'E', b'X', b' ', 0, 0, 1]` instead, whose last byte is the version. They
+//! are still readable, but without the length and checksum validations.
+//!
+//! # Migration
+//!
+//! The payload of older versions are upgraded to the current one with the `migrations` of
+//! `Format` step by step, so loaders only have to deserialize the latest version.
+
+use std::borrow::Cow;
+
+use crate::errors::*;
+
+/// The length of header in bytes.
+pub const LEN: usize = 20;
+
+const MARKER: [u8; 4] = [b'#', 0, 0, 0];
+const LEGACY_MARKER: [u8; 3] = [b' ', 0, 0];
+
+/// Upgrades the payload of version `N` into version `N + 1`.
+pub type Migration = fn(&[u8]) -> Result<Vec<u8>>;
+
+/// The description of a resource file format.
+#[derive(Clone, Copy)]
+pub struct Format {
+    /// The tag of format, which identifies the kind of resource.
+    pub tag: [u8; 4],
+    /// The current version of payload.
+    pub version: u32,
+    /// The migrations from older versions, the `migrations[i]` upgrades the payload of
+    /// version `i + 1` into version `i + 2`.
+    pub migrations: &'static [Migration],
+}
+
+impl Format {
+    /// Prepends the header of current version to the payload.
+    pub fn write(&self, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(LEN + payload.len());
+        bytes.extend_from_slice(&self.tag);
+        bytes.extend_from_slice(&MARKER);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&crc32(payload).to_le_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// Validates the header, and returns the payload which is migrated into the current
+    /// version if necessary.
+    pub fn read<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let tag = String::from_utf8_lossy(&self.tag);
+
+        if bytes.len() < 8 || bytes[0..4] != self.tag {
+            bail!("[{}] MAGIC number not match.", tag);
+        }
+
+        let (version, payload) = if bytes[4..7] == LEGACY_MARKER {
+            (u32::from(bytes[7]), &bytes[8..])
+        } else if bytes[4..8] == MARKER && bytes.len() >= LEN {
+            let version = le32(&bytes[8..]);
+            let len = le32(&bytes[12..]) as usize;
+            let checksum = le32(&bytes[16..]);
+            let payload = &bytes[LEN..];
+
+            if payload.len() != len {
+                bail!(
+                    "[{}] The length of payload is {} bytes, expects {} bytes.",
+                    tag,
+                    payload.len(),
+                    len
+                );
+            }
+
+            if crc32(payload) != checksum {
+                bail!("[{}] Checksum not match, the file is corrupted.", tag);
+            }
+
+            (version, payload)
+        } else {
+            bail!("[{}] Header is invalid.", tag);
+        };
+
+        if version == 0 || version > self.version {
+            bail!(
+                "[{}] Version {} is not supported, the latest version is {}.",
+                tag,
+                version,
+                self.version
+            );
+        }
+
+        let mut payload = Cow::Borrowed(payload);
+        for v in version..self.version {
+            let migration = self
+                .migrations
+                .get(v as usize - 1)
+                .ok_or_else(|| format_err!("[{}] Migration from version {} is missing.", tag, v))?;
+
+            payload = Cow::Owned(migration(&payload).map_err(|err| {
+                format_err!("[{}] Failed to migrate from version {}. {}", tag, v, err)
+            })?);
+        }
+
+        Ok(payload)
+    }
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Computes the CRC32 (IEEE) checksum.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &v in bytes {
+        crc = CRC32_TABLE[((crc ^ u32::from(v)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const V1: Format = Format {
+        tag: *b"TEST",
+        version: 1,
+        migrations: &[],
+    };
+
+    const V2: Format = Format {
+        tag: *b"TEST",
+        version: 2,
+        migrations: &[migrate],
+    };
+
+    fn migrate(payload: &[u8]) -> Result<Vec<u8>> {
+        Ok(payload.iter().map(|v| v * 2).collect())
+    }
+
+    #[test]
+    fn read() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let bytes = V2.write(&[1, 2, 3]);
+        assert_eq!(bytes.len(), LEN + 3);
+        assert_eq!(&V2.read(&bytes).unwrap()[..], &[1, 2, 3]);
+        assert!(V1.read(&bytes).is_err());
+
+        let bytes = V1.write(&[1, 2, 3]);
+        assert_eq!(&V2.read(&bytes).unwrap()[..], &[2, 4, 6]);
+
+        let legacy = [b'T', b'E', b'S', b'T', b' ', 0, 0, 1, 1, 2];
+        assert_eq!(&V1.read(&legacy).unwrap()[..], &[1, 2]);
+        assert_eq!(&V2.read(&legacy).unwrap()[..], &[2, 4]);
+
+        let mut corrupted = V2.write(&[1, 2, 3]);
+        corrupted[LEN] = 0;
+        assert!(V2.read(&corrupted).is_err());
+        assert!(V2.read(&corrupted[..LEN + 2]).is_err());
+        assert!(V2.read(b"TES").is_err());
+        assert!(V2.read(b"VTEX # \0\0").is_err());
+    }
+}
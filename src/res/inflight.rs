@@ -0,0 +1,203 @@
+//! Shares the in-flight loading of the same resource, so it's fetched from VFS only once
+//! even if several callers request it at the same time.
+
+use std::sync::{Arc, Mutex, Weak};
+
+use uuid::Uuid;
+
+use crate::sched::prelude::LockLatch;
+use crate::utils::hash::FastHashMap;
+
+use super::request::{CancelToken, Progress, Response};
+
+type Waiter = (Arc<LockLatch<Response>>, CancelToken);
+
+/// The shared loading of one resource. The response is kept as long as the entry is
+/// alive, so the callers that join after the loading is completed receive it as well.
+pub struct Entry {
+    token: CancelToken,
+    progress: Progress,
+    state: Mutex<EntryState>,
+}
+
+#[derive(Default)]
+struct EntryState {
+    waiters: Vec<Waiter>,
+    done: Option<Result<Box<[u8]>, String>>,
+}
+
+impl Entry {
+    fn wait(&self, latch: Arc<LockLatch<Response>>, token: CancelToken) {
+        let rsp = {
+            let mut state = self.state.lock().unwrap();
+            match state.done {
+                Some(ref done) => restore(done),
+                None => {
+                    state.waiters.push((latch, token));
+                    return;
+                }
+            }
+        };
+
+        if !token.is_canceled() {
+            latch.set(rsp);
+        }
+    }
+
+    fn finish(&self, rsp: Response) {
+        let done = match rsp {
+            Ok(bytes) => Ok(bytes),
+            Err(err) => Err(err.to_string()),
+        };
+
+        let waiters = {
+            let mut state = self.state.lock().unwrap();
+            state.done = Some(done.clone());
+            std::mem::take(&mut state.waiters)
+        };
+
+        for (latch, token) in waiters {
+            if !token.is_canceled() {
+                latch.set(restore(&done));
+            }
+        }
+    }
+}
+
+fn restore(done: &Result<Box<[u8]>, String>) -> Response {
+    match *done {
+        Ok(ref bytes) => Ok(bytes.clone()),
+        Err(ref err) => Err(format_err!("{}", err)),
+    }
+}
+
+#[derive(Default)]
+pub struct InFlight {
+    entries: Mutex<FastHashMap<Uuid, Weak<Entry>>>,
+}
+
+impl InFlight {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Joins the loading of `uuid`, or starts a new one with `start` if there is none.
+    /// The response is set to `latch` unless `token` is canceled. The loading is canceled
+    /// only after all the tokens of its callers are canceled.
+    pub fn join<F>(
+        &self,
+        uuid: Uuid,
+        latch: Arc<LockLatch<Response>>,
+        token: &CancelToken,
+        start: F,
+    ) -> Arc<Entry>
+    where
+        F: FnOnce(Arc<LockLatch<Response>>, CancelToken, Progress),
+    {
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(entry) = entries.get(&uuid).and_then(Weak::upgrade) {
+            if entry.token.attach(token) {
+                drop(entries);
+                entry.wait(latch, token.clone());
+                return entry;
+            }
+        }
+
+        entries.retain(|_, v| v.strong_count() > 0);
+
+        let entry = Arc::new(Entry {
+            token: CancelToken::new(),
+            progress: Progress::new(),
+            state: Mutex::new(EntryState::default()),
+        });
+
+        if !entry.token.attach(token) {
+            entry.token.cancel();
+        }
+
+        entry.wait(latch, token.clone());
+        entries.insert(uuid, Arc::downgrade(&entry));
+        drop(entries);
+
+        let clone = entry.clone();
+        let state = LockLatch::with_callback(move |rsp| clone.finish(rsp));
+        start(Arc::new(state), entry.token.clone(), entry.progress.clone());
+        entry
+    }
+
+    /// Returns the received bytes of `uuid` if it's still being loaded.
+    pub fn progress(&self, uuid: Uuid) -> Option<u64> {
+        let entry = self
+            .entries
+            .lock()
+            .unwrap()
+            .get(&uuid)
+            .and_then(Weak::upgrade)?;
+        let done = entry.state.lock().unwrap().done.is_some();
+
+        if done {
+            None
+        } else {
+            Some(entry.progress.get())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sched::prelude::LatchProbe;
+
+    #[test]
+    fn join() {
+        let inflight = InFlight::new();
+        let uuid = Uuid::from_bytes([1; 16]);
+        let starts = Mutex::new(Vec::new());
+
+        let join = |token: &CancelToken| {
+            let latch = Arc::new(LockLatch::new());
+            let entry = inflight.join(uuid, latch.clone(), token, |state, token, progress| {
+                progress.add(1);
+                starts.lock().unwrap().push((state, token))
+            });
+
+            (entry, latch)
+        };
+
+        let (a, b) = (CancelToken::new(), CancelToken::new());
+        let (entry, la) = join(&a);
+        let (_, lb) = join(&b);
+        assert_eq!(starts.lock().unwrap().len(), 1);
+        assert_eq!(inflight.progress(uuid), Some(1));
+
+        // The loading keeps going until all the callers cancel it.
+        a.cancel();
+        assert!(!starts.lock().unwrap()[0].1.is_canceled());
+
+        let (state, _) = starts.lock().unwrap().remove(0);
+        state.set(Ok(vec![1, 2].into_boxed_slice()));
+        assert!(!la.is_set());
+        assert_eq!(&lb.take().unwrap()[..], &[1, 2]);
+        assert_eq!(inflight.progress(uuid), None);
+
+        // The response is kept for later callers while the entry is alive.
+        let (_, lc) = join(&CancelToken::new());
+        assert_eq!(&lc.take().unwrap()[..], &[1, 2]);
+        assert!(starts.lock().unwrap().is_empty());
+
+        drop(entry);
+        drop(state);
+
+        // A new loading is started if the previous one has been canceled.
+        let c = CancelToken::new();
+        join(&c);
+        assert_eq!(starts.lock().unwrap().len(), 1);
+
+        c.cancel();
+        assert!(starts.lock().unwrap()[0].1.is_canceled());
+
+        join(&CancelToken::new());
+        assert_eq!(starts.lock().unwrap().len(), 2);
+    }
+}
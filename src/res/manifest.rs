@@ -9,8 +9,17 @@ use uuid::Uuid;
 use crate::errors::*;
 use crate::utils::prelude::{DataBuffer, DataBufferPtr, FastHashMap, HashValue};
 
+use super::header::Format;
+
 pub const NAME: &str = "MANIFEST";
-pub const MAGIC: [u8; 8] = [b'M', b'N', b'F', b'T', b' ', 0, 0, 1];
+
+/// The format of `MANIFEST` file. The manifests of older versions, which start with the
+/// legacy MAGIC numbers, are migrated into the current one.
+pub const FORMAT: Format = Format {
+    tag: *b"MNFT",
+    version: 4,
+    migrations: &[v1, v2, v3],
+};
 
 /// A manifest item in the build.
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
@@ -18,6 +27,84 @@ pub struct ManifestItem {
     pub filename: DataBufferPtr<str>,
     pub dependencies: DataBufferPtr<[usize]>,
     pub uuid: Uuid,
+    /// The CRC32 checksum of resource file, which is unknown in manifests of older versions.
+    pub hash: Option<u32>,
+    /// The size of resource file in bytes, which is unknown in manifests of older versions.
+    pub size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestItemV1 {
+    filename: DataBufferPtr<str>,
+    dependencies: DataBufferPtr<[usize]>,
+    uuid: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestItemV3 {
+    filename: DataBufferPtr<str>,
+    dependencies: DataBufferPtr<[usize]>,
+    uuid: Uuid,
+    hash: Option<u32>,
+}
+
+// The version 2 adds the variants of resources.
+fn v1(payload: &[u8]) -> Result<Vec<u8>> {
+    let (items, buf): (Vec<ManifestItemV1>, DataBuffer) = bincode::deserialize(payload)?;
+    let variants: Vec<ManifestVariant> = Vec::new();
+    Ok(bincode::serialize(&(items, buf, variants))?)
+}
+
+// The version 3 adds the checksums of items.
+fn v2(payload: &[u8]) -> Result<Vec<u8>> {
+    let (items, buf, variants): (Vec<ManifestItemV1>, DataBuffer, Vec<ManifestVariant>) =
+        bincode::deserialize(payload)?;
+
+    let items: Vec<_> = items
+        .into_iter()
+        .map(|v| ManifestItemV3 {
+            filename: v.filename,
+            dependencies: v.dependencies,
+            uuid: v.uuid,
+            hash: None,
+        })
+        .collect();
+
+    Ok(bincode::serialize(&(items, buf, variants))?)
+}
+
+// The version 4 adds the sizes of items.
+fn v3(payload: &[u8]) -> Result<Vec<u8>> {
+    let (items, buf, variants): (Vec<ManifestItemV3>, DataBuffer, Vec<ManifestVariant>) =
+        bincode::deserialize(payload)?;
+
+    let items = items
+        .into_iter()
+        .map(|v| ManifestItem {
+            filename: v.filename,
+            dependencies: v.dependencies,
+            uuid: v.uuid,
+            hash: v.hash,
+            size: None,
+        })
+        .collect();
+
+    let manifest = Manifest {
+        items,
+        buf,
+        variants,
+    };
+
+    Ok(bincode::serialize(&manifest)?)
+}
+
+/// An alternative of resource in the build, e.g. the same texture compressed in
+/// different formats. The variant is picked by its tag when resolving the resource.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ManifestVariant {
+    pub uuid: Uuid,
+    pub variant: Uuid,
+    pub tag: DataBufferPtr<str>,
 }
 
 /// Manifest for all the resources in the build.
@@ -25,6 +112,7 @@ pub struct ManifestItem {
 pub struct Manifest {
     pub items: Vec<ManifestItem>,
     pub buf: DataBuffer,
+    pub variants: Vec<ManifestVariant>,
 }
 
 impl Manifest {
@@ -32,57 +120,242 @@ impl Manifest {
         Default::default()
     }
 
-    pub fn load_from(mut file: &mut dyn Read) -> Result<Manifest> {
-        let mut buf = [0; 16];
-        file.read_exact(&mut buf[0..8])?;
+    pub fn load_from(file: &mut dyn Read) -> Result<Manifest> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let payload = FORMAT.read(&bytes)?;
+        Ok(bincode::deserialize(&payload)?)
+    }
+
+    /// Serializes the manifest into the bytes of `MANIFEST` file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(FORMAT.write(&bincode::serialize(self)?))
+    }
+}
+
+/// Builds a `Manifest` in memory, which is useful to test resources without the build
+/// process of `crayon-cli`, e.g. with the `Memory` VFS.
+#[derive(Debug, Default, Clone)]
+pub struct ManifestBuilder {
+    items: Vec<BuilderItem>,
+}
+
+#[derive(Debug, Clone)]
+struct BuilderItem {
+    filename: String,
+    uuid: Uuid,
+    dependencies: Vec<Uuid>,
+    hash: u32,
+    size: u64,
+}
+
+impl ManifestBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a resource file with its filename and UUID.
+    #[inline]
+    pub fn add<T: Into<String>>(&mut self, filename: T, uuid: Uuid, bytes: &[u8]) -> &mut Self {
+        self.add_with_dependencies(filename, uuid, bytes, &[])
+    }
+
+    /// Adds a resource file which depends on others, the dependencies could be added later.
+    pub fn add_with_dependencies<T: Into<String>>(
+        &mut self,
+        filename: T,
+        uuid: Uuid,
+        bytes: &[u8],
+        dependencies: &[Uuid],
+    ) -> &mut Self {
+        self.items.push(BuilderItem {
+            filename: filename.into(),
+            uuid,
+            dependencies: dependencies.to_vec(),
+            hash: super::header::crc32(bytes),
+            size: bytes.len() as u64,
+        });
+
+        self
+    }
+
+    /// Builds the manifest, and returns an error if any dependency is not found.
+    pub fn build(&self) -> Result<Manifest> {
+        let mut manifest = Manifest::new();
+
+        for item in &self.items {
+            let mut indices = Vec::new();
+            for v in &item.dependencies {
+                match self.items.iter().position(|dep| dep.uuid == *v) {
+                    Some(index) => indices.push(index),
+                    None => bail!(
+                        "[ManifestBuilder] Could not found dependency {} of {}.",
+                        v,
+                        item.filename
+                    ),
+                }
+            }
 
-        // MAGIC: [u8; 8]
-        if buf[0..8] != MAGIC[..] {
-            bail!("[ManifestLoader] MAGIC number not match.");
+            manifest.items.push(ManifestItem {
+                filename: manifest.buf.extend_from_str(item.filename.as_str()),
+                dependencies: manifest.buf.extend_from_slice(&indices),
+                uuid: item.uuid,
+                hash: Some(item.hash),
+                size: Some(item.size),
+            });
         }
 
-        Ok(bincode::deserialize_from(&mut file)?)
+        Ok(manifest)
+    }
+
+    /// Builds the manifest, and serializes it into the bytes of `MANIFEST` file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.build()?.to_bytes()
     }
 }
 
+/// A manifest mounted into `ManfiestResolver`. The resource files are located under
+/// `location`, and their filenames are resolved under `target`, which is the same as
+/// `location` unless the layer is mounted as an overlay of others.
+#[derive(Debug, Clone)]
+struct Layer {
+    location: InlinableString,
+    target: InlinableString,
+    manifest: Manifest,
+}
+
+/// Resolves resources from a stack of manifest layers. Layers mounted later have higher
+/// precedence, so a patch or mod could override the UUIDs or filenames of base layers.
 #[derive(Debug, Default, Clone)]
 pub struct ManfiestResolver {
-    manifests: Vec<Manifest>,
-    manifest_prefixs: Vec<InlinableString>,
+    layers: Vec<Layer>,
     uuids: FastHashMap<Uuid, (usize, usize)>,
     filenames: FastHashMap<HashValue<str>, Uuid>,
+    variants: FastHashMap<Uuid, Vec<(InlinableString, Uuid)>>,
+    preferences: Vec<String>,
 }
 
 impl ManfiestResolver {
     pub fn new() -> Self {
         ManfiestResolver {
-            manifests: Vec::new(),
-            manifest_prefixs: Vec::new(),
+            layers: Vec::new(),
             uuids: FastHashMap::default(),
             filenames: FastHashMap::default(),
+            variants: FastHashMap::default(),
+            preferences: Vec::new(),
         }
     }
 
+    /// Sets the preferred tags of variants, with the highest priority first.
+    pub fn set_preferences<T: Into<String>>(&mut self, tags: Vec<T>) {
+        self.preferences = tags.into_iter().map(|v| v.into()).collect();
+    }
+
+    /// Mounts the manifest of resources under `prefix` as the top layer.
+    #[inline]
     pub fn add<T: Into<InlinableString>>(&mut self, prefix: T, file: &mut dyn Read) -> Result<()> {
+        let prefix = prefix.into();
+        self.add_overlay(prefix.clone(), prefix, file)
+    }
+
+    /// Mounts the manifest of resources under `location` as the top layer, whose filenames
+    /// are resolved under `target` instead. The resources of this layer override the ones
+    /// with the same UUIDs or filenames in the layers mounted before. If there is a layer
+    /// at `location` already, its replaced.
+    pub fn add_overlay<T1, T2>(
+        &mut self,
+        location: T1,
+        target: T2,
+        file: &mut dyn Read,
+    ) -> Result<()>
+    where
+        T1: Into<InlinableString>,
+        T2: Into<InlinableString>,
+    {
         let manifest = Manifest::load_from(file)?;
+        let location = normalize(location.into());
+        let target = normalize(target.into());
+
+        if let Some(index) = self.position(&location) {
+            self.layers.remove(index);
+            self.rebuild();
+        }
+
+        self.layers.push(Layer {
+            location,
+            target,
+            manifest,
+        });
+
+        self.index(self.layers.len() - 1);
+        Ok(())
+    }
+
+    /// Unmounts the layer at `location`, and returns false if there is no such layer.
+    pub fn unmount<T: AsRef<str>>(&mut self, location: T) -> bool {
+        let location = normalize(location.as_ref().into());
+        match self.position(&location) {
+            Some(index) => {
+                self.layers.remove(index);
+                self.rebuild();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the locations of all the layers, from the lowest precedence to the highest.
+    pub fn layers(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|v| v.location.as_ref())
+    }
+
+    /// Returns the location of layer that supplies the resource, or None if not exists.
+    pub fn layer(&self, uuid: Uuid) -> Option<&str> {
+        self.uuids
+            .get(&uuid)
+            .map(|&(index, _)| self.layers[index].location.as_ref())
+    }
 
-        let mut prefix = prefix.into();
-        if !prefix.ends_with('/') {
-            prefix.push('/');
+    fn position(&self, location: &str) -> Option<usize> {
+        self.layers
+            .iter()
+            .position(|v| v.location.as_ref() == location)
+    }
+
+    fn rebuild(&mut self) {
+        self.uuids.clear();
+        self.filenames.clear();
+        self.variants.clear();
+
+        for index in 0..self.layers.len() {
+            self.index(index);
         }
+    }
+
+    // Indexes the resources of layer, which overrides the ones of layers before it. The
+    // variants of overridden resources are dropped, so the stale variants of lower layers
+    // are never picked over the new resources.
+    fn index(&mut self, index: usize) {
+        let layer = &self.layers[index];
+        let manifest = &layer.manifest;
 
-        let index = self.manifests.len();
         for (sub_index, v) in manifest.items.iter().enumerate() {
             let filename = manifest.buf.as_str(v.filename);
-            let fullname = format!("{}{}", prefix, filename);
+            let fullname = format!("{}{}", layer.target, filename);
 
             self.uuids.insert(v.uuid, (index, sub_index));
             self.filenames.insert(fullname.into(), v.uuid);
+            self.variants.remove(&v.uuid);
         }
 
-        self.manifests.push(manifest);
-        self.manifest_prefixs.push(prefix);
-        Ok(())
+        let mut variants: FastHashMap<Uuid, Vec<_>> = FastHashMap::default();
+        for v in &manifest.variants {
+            let tag = manifest.buf.as_str(v.tag).into();
+            variants.entry(v.uuid).or_default().push((tag, v.variant));
+        }
+
+        self.variants.extend(variants);
     }
 
     /// Checks if the uuid exists in this registry.
@@ -98,20 +371,47 @@ impl ManfiestResolver {
         self.filenames.get(&fullname).cloned()
     }
 
-    /// Resolve the UUID to full path of corresponding resource.
+    /// Returns the UUID of the most preferred variant of resource, or the resource
+    /// itself if none of its variants matches the preferences.
+    pub fn variant(&self, uuid: Uuid) -> Uuid {
+        if let Some(variants) = self.variants.get(&uuid) {
+            for tag in &self.preferences {
+                for &(ref v, variant) in variants {
+                    if v.as_ref() == tag.as_str() && self.uuids.contains_key(&variant) {
+                        return variant;
+                    }
+                }
+            }
+        }
+
+        uuid
+    }
+
+    /// Resolve the UUID to full path of corresponding resource, the most preferred
+    /// variant is picked if there are any.
     #[inline]
     pub fn resolve(&self, uuid: Uuid) -> Option<String> {
+        let uuid = self.variant(uuid);
+        self.uuids
+            .get(&uuid)
+            .map(|&(index, _)| format!("{}/{:X}", self.layers[index].location, uuid.to_simple()))
+    }
+
+    /// Returns the size of resource file in bytes if its recorded in manifest, the most
+    /// preferred variant is picked if there are any.
+    pub fn size(&self, uuid: Uuid) -> Option<u64> {
+        let uuid = self.variant(uuid);
         self.uuids
             .get(&uuid)
-            .and_then(|&(index, _)| self.manifest_prefixs.get(index))
-            .map(|prefix| format!("{}/{:X}", prefix, uuid.to_simple()))
+            .and_then(|&(index, sub_index)| self.layers[index].manifest.items[sub_index].size)
     }
 
     /// Return the iterator over all the dependencies of specified resource if exists.
     #[inline]
     pub fn dependencies(&self, uuid: Uuid) -> Option<Dependencies> {
         self.uuids.get(&uuid).and_then(|&(index, sub_index)| {
-            self.manifests.get(index).map(|manifest| {
+            self.layers.get(index).map(|layer| {
+                let manifest = &layer.manifest;
                 let dependencies = manifest.items[sub_index].dependencies;
                 Dependencies {
                     index: 0,
@@ -121,6 +421,53 @@ impl ManfiestResolver {
             })
         })
     }
+
+    /// Returns the resource and all of its dependencies recursively, ordered so that every
+    /// resource comes after its dependencies. Its an error if there are cyclic dependencies.
+    pub fn closure(&self, uuid: Uuid) -> Result<Vec<Uuid>> {
+        if !self.contains(uuid) {
+            bail!("[ManfiestResolver] Could not found resource {}.", uuid);
+        }
+
+        let mut closure = Vec::new();
+        let mut visiting = Vec::new();
+        self.visit(uuid, &mut visiting, &mut closure)?;
+        Ok(closure)
+    }
+
+    fn visit(&self, uuid: Uuid, visiting: &mut Vec<Uuid>, closure: &mut Vec<Uuid>) -> Result<()> {
+        if closure.contains(&uuid) {
+            return Ok(());
+        }
+
+        if let Some(i) = visiting.iter().position(|&v| v == uuid) {
+            let path: Vec<_> = visiting[i..].iter().map(|v| v.to_string()).collect();
+            bail!(
+                "[ManfiestResolver] Cyclic dependencies found: {} -> {}.",
+                path.join(" -> "),
+                uuid
+            );
+        }
+
+        visiting.push(uuid);
+        if let Some(dependencies) = self.dependencies(uuid) {
+            for v in dependencies {
+                self.visit(v, visiting, closure)?;
+            }
+        }
+
+        visiting.pop();
+        closure.push(uuid);
+        Ok(())
+    }
+}
+
+fn normalize(mut prefix: InlinableString) -> InlinableString {
+    if !prefix.ends_with('/') {
+        prefix.push('/');
+    }
+
+    prefix
 }
 
 /// An iterator visiting all the dependencies of specified resource.
@@ -136,9 +483,250 @@ impl<'a> Iterator for Dependencies<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         if self.index < self.dependencies.len() {
             self.index += 1;
-            Some(self.items[self.index - 1].uuid)
+            Some(self.items[self.dependencies[self.index - 1]].uuid)
         } else {
             None
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn manifest(variants: &[(&str, Uuid)]) -> (Uuid, Vec<u8>) {
+        let mut manifest = Manifest::new();
+        let uuid = Uuid::from_bytes([1; 16]);
+
+        manifest.items.push(ManifestItem {
+            filename: manifest.buf.extend_from_str("texture.png"),
+            dependencies: manifest.buf.extend_from_slice(&[]),
+            uuid,
+            hash: Some(1),
+            size: Some(16),
+        });
+
+        for &(tag, variant) in variants {
+            manifest.items.push(ManifestItem {
+                filename: manifest.buf.extend_from_str(format!("texture.{}", tag)),
+                dependencies: manifest.buf.extend_from_slice(&[]),
+                uuid: variant,
+                hash: Some(2),
+                size: Some(8),
+            });
+
+            manifest.variants.push(ManifestVariant {
+                uuid,
+                variant,
+                tag: manifest.buf.extend_from_str(tag),
+            });
+        }
+
+        (uuid, manifest.to_bytes().unwrap())
+    }
+
+    #[test]
+    fn variants() {
+        let (etc2, s3tc) = (Uuid::from_bytes([2; 16]), Uuid::from_bytes([3; 16]));
+        let (uuid, bytes) = manifest(&[("etc2", etc2), ("s3tc", s3tc)]);
+
+        let mut resolver = ManfiestResolver::new();
+        resolver.add("res:", &mut bytes.as_slice()).unwrap();
+        assert_eq!(resolver.variant(uuid), uuid);
+
+        assert_eq!(resolver.size(uuid), Some(16));
+
+        resolver.set_preferences(vec!["pvrtc", "s3tc", "etc2"]);
+        assert_eq!(resolver.variant(uuid), s3tc);
+        assert_eq!(resolver.size(uuid), Some(8));
+        assert_eq!(
+            resolver.resolve(uuid).unwrap(),
+            format!("res://{:X}", s3tc.to_simple())
+        );
+
+        resolver.set_preferences(vec!["pvrtc"]);
+        assert_eq!(resolver.variant(uuid), uuid);
+    }
+
+    #[test]
+    fn closure() {
+        let mut manifest = Manifest::new();
+        let uuids: Vec<_> = (0..4).map(|i| Uuid::from_bytes([i + 1; 16])).collect();
+
+        // 0 -> (1, 2), 1 -> 2, 2 -> (), 3 -> 3
+        let dependencies: [&[usize]; 4] = [&[1, 2], &[2], &[], &[3]];
+        for (i, &uuid) in uuids.iter().enumerate() {
+            manifest.items.push(ManifestItem {
+                filename: manifest.buf.extend_from_str(format!("{}.prefab", i)),
+                dependencies: manifest.buf.extend_from_slice(dependencies[i]),
+                uuid,
+                hash: None,
+                size: None,
+            });
+        }
+
+        let bytes = manifest.to_bytes().unwrap();
+
+        let mut resolver = ManfiestResolver::new();
+        resolver.add("res:", &mut bytes.as_slice()).unwrap();
+
+        let deps: Vec<_> = resolver.dependencies(uuids[0]).unwrap().collect();
+        assert_eq!(deps, vec![uuids[1], uuids[2]]);
+
+        let closure = resolver.closure(uuids[0]).unwrap();
+        assert_eq!(closure, vec![uuids[2], uuids[1], uuids[0]]);
+        assert_eq!(resolver.closure(uuids[2]).unwrap(), vec![uuids[2]]);
+        assert!(resolver.closure(uuids[3]).is_err());
+        assert!(resolver.closure(Uuid::nil()).is_err());
+    }
+
+    fn layer(items: &[(&str, Uuid)]) -> Vec<u8> {
+        let mut manifest = Manifest::new();
+        for &(filename, uuid) in items {
+            manifest.items.push(ManifestItem {
+                filename: manifest.buf.extend_from_str(filename),
+                dependencies: manifest.buf.extend_from_slice::<usize>(&[]),
+                uuid,
+                hash: None,
+                size: None,
+            });
+        }
+
+        manifest.to_bytes().unwrap()
+    }
+
+    #[test]
+    fn builder() {
+        let (a, b) = (Uuid::from_bytes([1; 16]), Uuid::from_bytes([2; 16]));
+
+        let mut builder = ManifestBuilder::new();
+        builder.add_with_dependencies("a.prefab", a, b"prefab", &[b]);
+        builder.add("b.mesh", b, b"mesh");
+
+        let mut resolver = ManfiestResolver::new();
+        let bytes = builder.to_bytes().unwrap();
+        resolver.add("mem://res/", &mut bytes.as_slice()).unwrap();
+
+        assert_eq!(resolver.find("mem://res/a.prefab"), Some(a));
+        assert_eq!(resolver.size(b), Some(4));
+        assert_eq!(resolver.closure(a).unwrap(), vec![b, a]);
+
+        builder.add_with_dependencies("c", Uuid::from_bytes([3; 16]), b"", &[Uuid::nil()]);
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn overlay() {
+        let (a, b, c) = (
+            Uuid::from_bytes([1; 16]),
+            Uuid::from_bytes([2; 16]),
+            Uuid::from_bytes([3; 16]),
+        );
+
+        let mut resolver = ManfiestResolver::new();
+        let base = layer(&[("a.png", a), ("b.png", b)]);
+        resolver.add("res:", &mut base.as_slice()).unwrap();
+
+        // The patch overrides `a` by UUID, and `b.png` by filename.
+        let patch = layer(&[("a.png", a), ("b.png", c)]);
+        resolver
+            .add_overlay("patch:", "res:", &mut patch.as_slice())
+            .unwrap();
+
+        assert_eq!(
+            resolver.layers().collect::<Vec<_>>(),
+            vec!["res:/", "patch:/"]
+        );
+        assert_eq!(resolver.layer(a), Some("patch:/"));
+        assert_eq!(resolver.layer(b), Some("res:/"));
+        assert_eq!(resolver.find("res:/b.png"), Some(c));
+        assert_eq!(resolver.find("patch:/b.png"), None);
+        assert_eq!(
+            resolver.resolve(a).unwrap(),
+            format!("patch://{:X}", a.to_simple())
+        );
+
+        assert!(resolver.unmount("patch:"));
+        assert!(!resolver.unmount("patch:"));
+        assert_eq!(resolver.layer(a), Some("res:/"));
+        assert_eq!(resolver.find("res:/b.png"), Some(b));
+        assert!(!resolver.contains(c));
+    }
+
+    #[test]
+    fn overlay_variants() {
+        let (etc2, s3tc) = (Uuid::from_bytes([2; 16]), Uuid::from_bytes([3; 16]));
+        let (uuid, bytes) = manifest(&[("etc2", etc2), ("s3tc", s3tc)]);
+
+        let mut resolver = ManfiestResolver::new();
+        resolver.set_preferences(vec!["s3tc"]);
+        resolver.add("res:", &mut bytes.as_slice()).unwrap();
+        assert_eq!(resolver.variant(uuid), s3tc);
+
+        // The patch overrides the resource without variants, so the stale ones of base
+        // layer are not used any more.
+        let patch = layer(&[("texture.png", uuid)]);
+        resolver
+            .add_overlay("patch:", "res:", &mut patch.as_slice())
+            .unwrap();
+
+        assert_eq!(resolver.variant(uuid), uuid);
+        assert_eq!(
+            resolver.resolve(uuid).unwrap(),
+            format!("patch://{:X}", uuid.to_simple())
+        );
+
+        // The patch with its own variants.
+        let (_, bytes) = manifest(&[("s3tc", etc2)]);
+        resolver
+            .add_overlay("patch:", "res:", &mut bytes.as_slice())
+            .unwrap();
+        assert_eq!(resolver.variant(uuid), etc2);
+        assert_eq!(resolver.layer(etc2), Some("patch:/"));
+
+        assert!(resolver.unmount("patch:"));
+        assert_eq!(resolver.variant(uuid), s3tc);
+    }
+
+    #[test]
+    fn migrate() {
+        let legacy = |version: u8| vec![b'M', b'N', b'F', b'T', b' ', 0, 0, version];
+
+        let mut manifest = Manifest::new();
+        let uuid = Uuid::from_bytes([1; 16]);
+        let filename = manifest.buf.extend_from_str("texture.png");
+        let dependencies = manifest.buf.extend_from_slice::<usize>(&[]);
+        let items = vec![(filename, dependencies, uuid)];
+
+        let mut bytes = legacy(1);
+        bytes.extend(bincode::serialize(&(&items, &manifest.buf)).unwrap());
+
+        let mut resolver = ManfiestResolver::new();
+        resolver.add("res:", &mut bytes.as_slice()).unwrap();
+        assert_eq!(resolver.find("res:/texture.png"), Some(uuid));
+
+        let mut bytes = legacy(2);
+        let variants: Vec<ManifestVariant> = Vec::new();
+        bytes.extend(bincode::serialize(&(&items, &manifest.buf, &variants)).unwrap());
+
+        let manifest = Manifest::load_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(manifest.items[0].uuid, uuid);
+        assert_eq!(manifest.items[0].hash, None);
+        assert_eq!(manifest.items[0].size, None);
+
+        let mut bytes = legacy(3);
+        let items = vec![(filename, dependencies, uuid, Some(7u32))];
+        bytes.extend(bincode::serialize(&(&items, &manifest.buf, &variants)).unwrap());
+
+        let manifest = Manifest::load_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(manifest.items[0].hash, Some(7));
+        assert_eq!(manifest.items[0].size, None);
+
+        // The current version is written with the resource header.
+        let bytes = manifest.to_bytes().unwrap();
+        assert_eq!(&bytes[0..8], b"MNFT#\0\0\0");
+        let manifest = Manifest::load_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(manifest.items[0].hash, Some(7));
+        assert!(Manifest::load_from(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -15,8 +15,8 @@
 //! # Resource
 //!
 //! A _resource_ is a abstraction of some `piece of data` that are fully prepared for using at runtime.
-//! We are providing a command line tool [crayon-cli](https://github.com/shawnscode/crayon-tools) that
-//! automatically compiles assets into resources for runtime.
+//! We are providing a command line tool `crayon-cli`, which is placed under `tools/crayon-tools`,
+//! that automatically compiles assets into resources for runtime.
 //!
 //! ## UUID
 //!
@@ -32,8 +32,32 @@
 //! filesystem, or other places if extended by pluggable `VFS`.
 //!
 //! The `VFS` trait has a pretty simple interface, since it should focus on games that load
-//! data asynchronously. A trival `Directory` is provided to supports local host filesystem.
-//! And it should be easy to add features like compression and encrpytion.
+//! data asynchronously. A trival `Directory` is provided to supports local host filesystem,
+//! `Http` fetches files from web servers with the `http` and `https` schemas, and `Pack`
+//! loads resources from one archive file which is built by `vfs::pack::build`. `Memory`
+//! serves files from memory, which is useful for tests with `manifest::ManifestBuilder`, or
+//! to embed a whole directory of resources into the binary with `crayon_tools::embed`.
+//! Features like compression are added by wrapping adapters, e.g. `Compressed` decompresses
+//! the files of any VFS transparently, `Encrypted` decrypts and authenticates them with
+//! the key of `ResourceParams`, and `Cache` keeps the files of remote VFS on local disk.
+//!
+//! Most of VFS are read-only. The files of user, e.g. save games and settings, could be
+//! written, listed and deleted under the `user` schema, see `vfs::user`.
+//!
+//! Large files could be read partially with `load_range`, or in chunks with `stream`, which
+//! are efficient with VFS that supports ranged reads, e.g. `Dir`, `Pack` and `Http`. Files
+//! that are encrypted or compressed are fetched as a whole for every range.
+//!
+//! ## Request
+//!
+//! Loading requests are sent to VFS in the order of `Priority`, and there are at most
+//! `SchemaResolver::concurrency` in-flight requests of every schema, so the UI textures
+//! would not wait behind thousands of terrain tiles. Requests could be canceled with
+//! `Request::cancel` or the `CancelToken` of callbacks before they are completed.
+//!
+//! Both `Request` and `ResourceReady`, which waits for the resource of handle, implement
+//! `Future`, so they could be awaited in async code. Their tasks are woken once per frame
+//! after the requests are advanced.
 //!
 //! ## Manifest
 //!
@@ -41,26 +65,48 @@
 //! from general UUID or readable identifier. The `Manifest` file is generated after the build
 //! process of `crayon-cli`.
 //!
+//! The `Manifest` also records the dependencies of resources, e.g. the meshes of a prefab.
+//! `load_with_dependencies` loads the whole dependency closure before the resource itself,
+//! and the requests of the same resource share one in-flight loading.
+//!
+//! Patches and mods could be shipped as overlays with their own manifests, which are mounted
+//! above the base resources by `mount_overlay`, and unmounted by `unmount`.
+//!
 
+pub mod header;
 pub mod manifest;
 pub mod request;
 pub mod shortcut;
+pub mod stream;
 pub mod url;
 pub mod utils;
 pub mod vfs;
 
 pub mod prelude {
-    pub use super::utils::prelude::ResourceState;
+    pub use super::request::{CancelToken, Priority, Progress};
+    pub use super::utils::prelude::{ResourceGroup, ResourceReady, ResourceState};
     pub use super::ResourceParams;
 }
 
+mod dispatch;
+mod inflight;
 mod system;
+mod watcher;
+
+use std::ops::Range;
+use std::sync::Arc;
+use std::task::Waker;
+use std::time::Duration;
 
 use uuid::Uuid;
 
+use crate::sched::prelude::{CountLatch, Latch};
+
 use self::inside::{ctx, CTX};
-use self::request::{Request, Response};
+use self::request::{CancelToken, Priority, Request, Response};
 use self::shortcut::ShortcutResolver;
+use self::stream::Stream;
+use self::vfs::encrypt::Key;
 use self::vfs::SchemaResolver;
 
 #[derive(Debug, Clone)]
@@ -68,6 +114,16 @@ pub struct ResourceParams {
     pub shortcuts: ShortcutResolver,
     pub schemas: SchemaResolver,
     pub dirs: Vec<String>,
+    /// The preferred tags of resource variants, with the highest priority first. The
+    /// texture variants supported by device are appended when the engine is set up.
+    pub variants: Vec<String>,
+    /// Polls the modification of loaded files with this interval, and reloads the changed
+    /// resources in place. It should only be enabled during development, and works only with
+    /// VFS that could tell the modification time of files, e.g. `Dir`.
+    pub hot_reload: Option<Duration>,
+    /// Decrypts and authenticates all the files with this key, which are encrypted by
+    /// `vfs::encrypt::encrypt`. The VFS of every schema is wrapped with `Encrypted`.
+    pub key: Option<Key>,
 }
 
 impl Default for ResourceParams {
@@ -76,13 +132,25 @@ impl Default for ResourceParams {
             shortcuts: ShortcutResolver::new(),
             schemas: SchemaResolver::new(),
             dirs: Vec::new(),
+            variants: Vec::new(),
+            hot_reload: None,
+            key: None,
         };
 
         #[cfg(not(target_arch = "wasm32"))]
-        params.schemas.add("file", self::vfs::dir::Dir::new());
+        {
+            let http = self::vfs::http::Http::new();
+            params.schemas.add("file", self::vfs::dir::Dir::new());
+            params.schemas.add("http", http.clone());
+            params.schemas.add("https", http);
+        }
+
         #[cfg(target_arch = "wasm32")]
         params.schemas.add("http", self::vfs::http::Http::new());
 
+        let user = self::vfs::user::User::platform();
+        params.schemas.add(self::vfs::user::SCHEMA, user);
+
         params
     }
 }
@@ -111,18 +179,144 @@ pub fn exists(uuid: Uuid) -> bool {
     ctx().exists(uuid)
 }
 
-/// Loads file asynchronously with response callback.
+/// Returns the size of resource file in bytes if it's recorded in manifest.
 #[inline]
-pub fn load_with_callback<T>(uuid: Uuid, func: T) -> Result<(), failure::Error>
+pub fn size(uuid: Uuid) -> Option<u64> {
+    ctx().size(uuid)
+}
+
+/// Returns the received bytes of resource file if it's being loaded.
+#[inline]
+pub fn progress(uuid: Uuid) -> Option<u64> {
+    ctx().progress(uuid)
+}
+
+/// Mounts the manifest under `dir` as overlay of the resources under `target`, e.g. a patch
+/// or mod which replaces some of the shipped resources. The resources of overlay override the
+/// ones with the same UUIDs or filenames, and the later mounted overlays take precedence.
+///
+/// Only the resources loaded after the returned latch is set are affected, the loaded ones
+/// are kept untouched until they are reloaded.
+pub fn mount_overlay<T1, T2>(dir: T1, target: T2) -> Result<Arc<CountLatch>, failure::Error>
+where
+    T1: Into<String>,
+    T2: Into<String>,
+{
+    let (dir, target) = (dir.into(), target.into());
+    let latch = Arc::new(CountLatch::new());
+    latch.increment();
+
+    let clone = latch.clone();
+    let prefix = dir.clone();
+    ctx().load_manifest_with_callback(dir, move |rsp| {
+        let rsp = rsp.and_then(|bytes| {
+            let mut cursor = std::io::Cursor::new(bytes);
+            ctx().attach_overlay(&prefix, &target, &mut cursor)
+        });
+
+        if let Err(err) = rsp {
+            warn!(
+                "[ResourceSystem] Failed to mount overlay {}. {}",
+                prefix, err
+            );
+        }
+
+        clone.set();
+    })?;
+
+    Ok(latch)
+}
+
+/// Unmounts the manifest under `dir`, returns false if it's not mounted. Just like
+/// `mount_overlay`, the loaded resources are not affected.
+#[inline]
+pub fn unmount<T: AsRef<str>>(dir: T) -> bool {
+    ctx().detach(dir)
+}
+
+/// Returns the directories of mounted manifests, from the lowest precedence to the highest.
+#[inline]
+pub fn layers() -> Vec<String> {
+    ctx().layers()
+}
+
+/// Returns the directory of mounted manifest that supplies the resource.
+#[inline]
+pub fn layer(uuid: Uuid) -> Option<String> {
+    ctx().layer(uuid)
+}
+
+/// Wakes the task after the requests are advanced in `on_post_update` of next frame. It's
+/// used to drive the futures of resources, e.g. `Request` and `ResourceReady`, with the
+/// frames of engine instead of threads, so they work on the single-threaded scheduler of
+/// web too. The task is woken immediately if the resource system is not enabled.
+pub fn wake_on_advance(waker: &Waker) {
+    if valid() {
+        ctx().wake_on_advance(waker);
+    } else {
+        waker.wake_by_ref();
+    }
+}
+
+/// Returns the UUIDs of resources that have been modified on disk after `cursor`, and
+/// moves the cursor to the latest modification. It always returns a empty vector if the
+/// hot-reloading is disabled.
+#[inline]
+pub fn changes(cursor: &mut usize) -> Vec<Uuid> {
+    ctx().changes(cursor)
+}
+
+/// Stops polling the modification of resource file, which is called by pools once the
+/// resource is destroyed.
+#[inline]
+pub fn unwatch(uuid: Uuid) {
+    ctx().unwatch(uuid)
+}
+
+/// Loads file asynchronously with response callback. The returned token could be used to
+/// cancel the loading, and the callback is dropped without being called if so.
+#[inline]
+pub fn load_with_callback<T>(uuid: Uuid, func: T) -> Result<CancelToken, failure::Error>
 where
     T: FnOnce(Response) + Send + 'static,
 {
     ctx().load_with_callback(uuid, func)
 }
 
+/// Loads file asynchronously with priority and response callback.
+#[inline]
+pub fn load_with_priority_and_callback<T>(
+    uuid: Uuid,
+    priority: Priority,
+    func: T,
+) -> Result<CancelToken, failure::Error>
+where
+    T: FnOnce(Response) + Send + 'static,
+{
+    ctx().load_with_priority_and_callback(uuid, priority, func)
+}
+
+/// Loads file asynchronously with response callback, after all of its dependencies. The
+/// loaded dependencies are kept until the callback returns, so the resources created inside
+/// it share the same bytes instead of fetching them again.
+#[inline]
+pub fn load_with_dependencies_and_callback<T>(
+    uuid: Uuid,
+    priority: Priority,
+    func: T,
+) -> Result<CancelToken, failure::Error>
+where
+    T: FnOnce(Response) + Send + 'static,
+{
+    ctx().load_with_dependencies_and_callback(uuid, priority, func)
+}
+
 /// Loads file asynchronously with response callback.
 #[inline]
-pub fn load_from_with_callback<T1, T2>(filename: T1, func: T2) -> Result<(), failure::Error>
+pub fn load_from_with_callback<T1, T2>(
+    filename: T1,
+    func: T2,
+) -> Result<CancelToken, failure::Error>
 where
     T1: AsRef<str>,
     T2: FnOnce(Response) + Send + 'static,
@@ -136,6 +330,57 @@ pub fn load(uuid: Uuid) -> Result<Request, failure::Error> {
     ctx().load(uuid)
 }
 
+/// Loads file asynchronously with priority. Pending requests with higher priority are
+/// sent to VFS first, if there are too many in-flight requests of the same schema.
+pub fn load_with_priority(uuid: Uuid, priority: Priority) -> Result<Request, failure::Error> {
+    ctx().load_with_priority(uuid, priority)
+}
+
+/// Loads file asynchronously after all of its dependencies recorded in manifest. The
+/// request is completed once the whole dependency closure is loaded, and fails if any of
+/// them fails or there are cyclic dependencies.
+pub fn load_with_dependencies(uuid: Uuid, priority: Priority) -> Result<Request, failure::Error> {
+    ctx().load_with_dependencies(uuid, priority)
+}
+
+/// Loads the file at url asynchronously without looking up the manifest, e.g. the files
+/// written under `user://`.
+pub fn load_url<T: AsRef<str>>(filename: T) -> Result<Request, failure::Error> {
+    ctx().load_url(filename)
+}
+
+/// Writes the file at url, which is created if it does not exist. Only writable VFS, e.g.
+/// the `user` schema and `Dir`, supports it. The writing is blocking.
+pub fn write<T: AsRef<str>>(filename: T, bytes: &[u8]) -> Result<(), failure::Error> {
+    ctx().write(filename, bytes)
+}
+
+/// Returns the names of files in the directory at url, in lexical order.
+pub fn list<T: AsRef<str>>(dir: T) -> Result<Vec<String>, failure::Error> {
+    ctx().list(dir)
+}
+
+/// Deletes the file at url.
+pub fn delete<T: AsRef<str>>(filename: T) -> Result<(), failure::Error> {
+    ctx().delete(filename)
+}
+
+/// Reads the byte range of resource file asynchronously. The response is shorter than the
+/// range if it exceeds the end of file.
+pub fn load_range(
+    uuid: Uuid,
+    range: Range<u64>,
+    priority: Priority,
+) -> Result<Request, failure::Error> {
+    ctx().load_range(uuid, range, priority)
+}
+
+/// Creates a `Stream` which reads the resource file in chunks of `chunk` bytes, so it could
+/// be decoded before the whole file is loaded.
+pub fn stream(uuid: Uuid, chunk: usize) -> Result<Stream, failure::Error> {
+    ctx().stream(uuid, chunk)
+}
+
 /// Loads file asynchronously. This method will returns a `Request` object immediatedly,
 /// its user's responsibility to store the object and frequently check it for completion.
 pub fn load_from<T: AsRef<str>>(filename: T) -> Result<Request, failure::Error> {
@@ -1,16 +1,142 @@
 //! A asynchronous loading request.
 
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 
 use crate::sched::prelude::{LatchProbe, LockLatch};
 
 pub type Response = Result<Box<[u8]>, failure::Error>;
 
+/// The priority of loading request. Pending requests with higher priority are sent to
+/// VFS first, and the requests with the same priority are sent in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+/// A shared flag to cancel the loading request. Requests that are still pending are
+/// dropped without touching VFS, and the responses of in-flight ones are discarded.
+///
+/// A token could be attached to others, e.g. the requests that share the same in-flight
+/// loading. The shared one is canceled only after all of its attached tokens are canceled.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<TokenState>);
+
+#[derive(Debug, Default)]
+struct TokenState {
+    canceled: AtomicBool,
+    children: AtomicUsize,
+    parents: Mutex<Vec<CancelToken>>,
+}
+
+// The number of children once it reaches zero, which prevents more attachments.
+const CLOSED: usize = usize::MAX;
+
+impl CancelToken {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        if self.0.canceled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let parents = std::mem::take(&mut *self.0.parents.lock().unwrap());
+        for v in parents {
+            v.release();
+        }
+    }
+
+    #[inline]
+    pub fn is_canceled(&self) -> bool {
+        self.0.canceled.load(Ordering::Acquire)
+    }
+
+    /// Attaches `child` to this token, which will be canceled once all the attached
+    /// tokens are canceled. Returns false if either of them has been canceled.
+    pub(crate) fn attach(&self, child: &CancelToken) -> bool {
+        let mut parents = child.0.parents.lock().unwrap();
+        if child.is_canceled() {
+            return false;
+        }
+
+        let mut n = self.0.children.load(Ordering::Acquire);
+        loop {
+            if n == CLOSED || self.is_canceled() {
+                return false;
+            }
+
+            match self.0.children.compare_exchange_weak(
+                n,
+                n + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(v) => n = v,
+            }
+        }
+
+        parents.push(self.clone());
+        true
+    }
+
+    fn release(&self) {
+        let children = &self.0.children;
+        if children.fetch_sub(1, Ordering::AcqRel) == 1
+            && children
+                .compare_exchange(0, CLOSED, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            self.cancel();
+        }
+    }
+}
+
+/// The received bytes of a loading request, which are reported by VFS while the file is
+/// being read, see `VFS::request_with_progress`.
+#[derive(Debug, Clone, Default)]
+pub struct Progress(Arc<AtomicU64>);
+
+impl Progress {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Gets the number of received bytes.
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub fn add(&self, len: u64) {
+        self.0.fetch_add(len, Ordering::AcqRel);
+    }
+
+    /// Sets the number of received bytes, e.g. resets it when the request is retried.
+    #[inline]
+    pub fn set(&self, len: u64) {
+        self.0.store(len, Ordering::Release);
+    }
+}
+
 /// A asynchronous loading request. You sould checks the completion status with
 /// `poll` method manually. Once the polling returns true, you could fetch the
 /// result by `response`.
+///
+/// `Request` is also a `Future` of its response, which is polled again after every
+/// advance of the resource system, see `res::wake_on_advance`.
 pub enum Request {
-    NotReady(Arc<LockLatch<Response>>),
+    NotReady(Arc<LockLatch<Response>>, CancelToken),
     Ok(Response),
 }
 
@@ -22,7 +148,12 @@ impl Request {
 
     #[inline]
     pub fn new(latch: Arc<LockLatch<Response>>) -> Self {
-        Request::NotReady(latch)
+        Request::NotReady(latch, CancelToken::new())
+    }
+
+    #[inline]
+    pub fn with_token(latch: Arc<LockLatch<Response>>, token: CancelToken) -> Self {
+        Request::NotReady(latch, token)
     }
 
     #[inline]
@@ -35,13 +166,35 @@ impl Request {
         Request::Ok(Err(err.into()))
     }
 
+    /// Cancels the request if it's not ready yet, the response becomes an error.
+    pub fn cancel(&mut self) {
+        if let Request::NotReady(_, ref token) = *self {
+            token.cancel();
+            *self = Request::err(format_err!("[Request] The request has been canceled."));
+        }
+    }
+
+    /// Returns true if the request has been canceled before it's ready.
+    #[inline]
+    pub fn is_canceled(&self) -> bool {
+        match *self {
+            Request::NotReady(_, ref token) => token.is_canceled(),
+            Request::Ok(_) => false,
+        }
+    }
+
     /// Attempt to resolve the request to a final state, and returns true if the
     /// loading result is ready for user.
     #[inline]
     pub fn poll(&mut self) -> bool {
+        if self.is_canceled() {
+            self.cancel();
+            return true;
+        }
+
         let rsp = match *self {
             Request::Ok(_) => return true,
-            Request::NotReady(ref state) => {
+            Request::NotReady(ref state, _) => {
                 if !state.is_set() {
                     return false;
                 }
@@ -74,6 +227,24 @@ impl Into<Option<Response>> for Request {
     }
 }
 
+impl Future for Request {
+    type Output = Response;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Response> {
+        let req = self.get_mut();
+        if !req.poll() {
+            super::wake_on_advance(cx.waker());
+            return Poll::Pending;
+        }
+
+        let taken = Request::err(format_err!("[Request] The response has been taken."));
+        match std::mem::replace(req, taken) {
+            Request::Ok(rsp) => Poll::Ready(rsp),
+            Request::NotReady(_, _) => unreachable!(),
+        }
+    }
+}
+
 type FrameTasks = Mutex<Vec<(Request, Box<dyn FnMut(Response) + Send>)>>;
 
 #[derive(Default)]
@@ -82,6 +253,7 @@ pub struct RequestQueue {
     last_frame_tasks: FrameTasks,
     tasks: FrameTasks,
     idxes: Mutex<Vec<usize>>,
+    wakers: Mutex<Vec<Waker>>,
 }
 
 impl RequestQueue {
@@ -90,6 +262,15 @@ impl RequestQueue {
             last_frame_tasks: Mutex::new(Vec::new()),
             tasks: Mutex::new(Vec::new()),
             idxes: Mutex::new(Vec::new()),
+            wakers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Wakes the task at the end of next `advance`.
+    pub fn wake_on_advance(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock().unwrap();
+        if !wakers.iter().any(|v| v.will_wake(waker)) {
+            wakers.push(waker.clone());
         }
     }
 
@@ -121,6 +302,9 @@ impl RequestQueue {
             tasks.extend(last_frame_tasks.drain(..));
         }
 
+        // The callbacks of canceled requests are dropped silently.
+        tasks.retain(|(request, _)| !request.is_canceled());
+
         // FIXME: Use drain_filter instead of retain and `for` iteration.
         for (i, &mut (ref mut request, _)) in tasks.iter_mut().enumerate().rev() {
             if request.poll() {
@@ -133,5 +317,83 @@ impl RequestQueue {
             let v: Option<Response> = request.into();
             crate::sched::spawn(move || func(v.unwrap()));
         }
+
+        let wakers = std::mem::take(&mut *self.wakers.lock().unwrap());
+        for v in wakers {
+            v.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::testing::Counter;
+
+    use super::*;
+
+    #[test]
+    fn cancel() {
+        let state = Request::latch();
+        let token = CancelToken::new();
+        let mut req = Request::with_token(state.clone(), token.clone());
+        assert!(!req.poll());
+
+        token.cancel();
+        assert!(req.is_canceled());
+        assert!(req.poll());
+        assert!(req.response().unwrap().is_err());
+
+        let mut req = Request::new(state.clone());
+        state.set(Ok(vec![1].into_boxed_slice()));
+        req.cancel();
+        assert!(req.response().unwrap().is_err());
+    }
+
+    #[test]
+    fn future() {
+        let (counter, waker) = Counter::waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let queue = RequestQueue::new();
+        queue.wake_on_advance(&waker);
+        queue.wake_on_advance(&waker);
+        queue.advance();
+        queue.advance();
+        assert_eq!(counter.count(), 1);
+
+        let state = Request::latch();
+        let mut req = Request::new(state.clone());
+        assert!(Pin::new(&mut req).poll(&mut cx).is_pending());
+        assert_eq!(counter.count(), 2);
+
+        state.set(Ok(vec![1].into_boxed_slice()));
+        match Pin::new(&mut req).poll(&mut cx) {
+            Poll::Ready(Ok(bytes)) => assert_eq!(&bytes[..], &[1]),
+            _ => panic!(),
+        }
+
+        let mut req = Request::new(Request::latch());
+        req.cancel();
+        match Pin::new(&mut req).poll(&mut cx) {
+            Poll::Ready(rsp) => assert!(rsp.is_err()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn attach() {
+        let shared = CancelToken::new();
+        let (a, b) = (CancelToken::new(), CancelToken::new());
+        assert!(shared.attach(&a));
+        assert!(shared.attach(&b));
+
+        a.cancel();
+        a.cancel();
+        assert!(!shared.is_canceled());
+
+        b.cancel();
+        assert!(shared.is_canceled());
+        assert!(!shared.attach(&CancelToken::new()));
+        assert!(!CancelToken::new().attach(&a));
     }
 }
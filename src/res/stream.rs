@@ -0,0 +1,200 @@
+//! Streams a file from VFS in chunks.
+//!
+//! Big files do not have to be fully loaded before using. A `Stream` reads the file in
+//! chunks with `VFS::request_range`, and keeps a few chunks in-flight ahead of the
+//! consumer, so the decoding could begin once the first chunk arrives:
+//!
+//! ```rust,ignore
+//! let mut stream = res::stream(uuid, 64 * 1024)?;
+//!
+//! // Polls the stream every frame.
+//! match stream.poll_chunk() {
+//!     Poll::Ready(Some(chunk)) => decoder.feed(&chunk?),
+//!     Poll::Ready(None) => decoder.finish(),
+//!     Poll::Pending => {}
+//! }
+//! ```
+//!
+//! The chunks are requested from VFS directly instead of the priority queue of resource
+//! system, since they are usually needed in time. The caller feeds its own decoder, e.g.
+//! audio clips are still decoded from whole files by `AudioClipLoader`.
+//!
+//! Streaming is only efficient if the VFS reads ranges, e.g. `Dir`, `Pack` and `Http`.
+//! `Encrypted` and `Compressed` fetch the whole file for every chunk.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::task::Poll;
+
+use crate::sched::prelude::LatchProbe;
+
+use super::request::{Request, Response};
+use super::url::Url;
+use super::vfs::VFS;
+
+/// The default number of chunks that are requested ahead of the consumer.
+pub const DEFAULT_PREFETCH: usize = 2;
+
+/// Reads a file in chunks of fixed size, which are delivered in order. The last chunk
+/// might be shorter than the others.
+pub struct Stream {
+    vfs: Arc<dyn VFS>,
+    url: Url,
+    chunk: u64,
+    prefetch: usize,
+    offset: u64,
+    pending: VecDeque<Request>,
+    finished: bool,
+}
+
+impl Stream {
+    /// Creates a stream of the file at `url`, which is read in chunks of `chunk` bytes.
+    pub fn new(vfs: Arc<dyn VFS>, url: Url, chunk: usize) -> Self {
+        Stream {
+            vfs,
+            url,
+            chunk: chunk.max(1) as u64,
+            prefetch: DEFAULT_PREFETCH,
+            offset: 0,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Sets the number of chunks that are requested ahead of the consumer.
+    #[inline]
+    pub fn set_prefetch(&mut self, prefetch: usize) {
+        self.prefetch = prefetch;
+    }
+
+    /// Returns the offset of next chunk that would be requested.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns true if all the chunks have been consumed, or the stream is failed.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Attempts to pull out the next chunk of file. It returns `Poll::Ready(None)` once the
+    /// end of file is reached, and the stream is finished after any error.
+    pub fn poll_chunk(&mut self) -> Poll<Option<Response>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+
+        while self.pending.len() <= self.prefetch {
+            let range = self.offset..self.offset + self.chunk;
+            let state = Request::latch();
+            let (vfs, url, clone) = (self.vfs.clone(), self.url.clone(), state.clone());
+            let func = move || vfs.request_range(&url, range, clone);
+
+            if crate::sched::valid() {
+                crate::sched::spawn(func);
+            } else {
+                func();
+            }
+
+            self.pending.push_back(Request::new(state));
+            self.offset += self.chunk;
+        }
+
+        if !self.pending[0].poll() {
+            return Poll::Pending;
+        }
+
+        let rsp: Option<Response> = self.pending.pop_front().unwrap().into();
+        let rsp = rsp.unwrap();
+
+        match rsp {
+            Ok(ref bytes) if (bytes.len() as u64) < self.chunk => self.finish(),
+            Err(_) => self.finish(),
+            _ => {}
+        }
+
+        match rsp {
+            Ok(ref bytes) if bytes.is_empty() => Poll::Ready(None),
+            _ => Poll::Ready(Some(rsp)),
+        }
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        self.pending.clear();
+    }
+}
+
+impl LatchProbe for Stream {
+    /// Returns true if the next chunk is ready.
+    fn is_set(&self) -> bool {
+        match self.pending.front() {
+            Some(Request::NotReady(state, _)) => state.is_set(),
+            Some(Request::Ok(_)) => true,
+            None => self.finished,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::vfs::dir::Dir;
+    use super::super::vfs::slice;
+    use super::*;
+    use crate::sched::prelude::LockLatch;
+
+    #[derive(Debug)]
+    struct Bytes(&'static [u8]);
+
+    impl VFS for Bytes {
+        fn request(&self, _: &Url, state: Arc<LockLatch<Response>>) {
+            state.set(Ok(self.0.into()));
+        }
+    }
+
+    // Drains the stream, the chunks might be read on the threads of `sched`.
+    fn drain(stream: &mut Stream) -> Vec<Box<[u8]>> {
+        let mut chunks = Vec::new();
+        loop {
+            match stream.poll_chunk() {
+                Poll::Ready(Some(rsp)) => chunks.push(rsp.unwrap()),
+                Poll::Ready(None) => return chunks,
+                Poll::Pending => std::thread::yield_now(),
+            }
+        }
+    }
+
+    #[test]
+    fn stream() {
+        let vfs = Arc::new(Bytes(b"0123456789"));
+        let url = Url::new("mem://a/b").unwrap();
+
+        let mut stream = Stream::new(vfs.clone(), url.clone(), 4);
+        let chunks = drain(&mut stream);
+        assert!(stream.is_finished());
+        assert_eq!(&chunks[0][..], b"0123");
+        assert_eq!(&chunks[1][..], b"4567");
+        assert_eq!(&chunks[2][..], b"89");
+        assert_eq!(chunks.len(), 3);
+
+        // The empty chunk at the end is not delivered.
+        let mut stream = Stream::new(vfs, url, 5);
+        stream.set_prefetch(0);
+        assert_eq!(drain(&mut stream).len(), 2);
+        assert_eq!(stream.offset(), 15);
+
+        // Reads the chunks from local file directly.
+        let path = crate::utils::testing::temp_path("stream");
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let url = Url::new(format!("file://{}", path.display())).unwrap();
+        let mut stream = Stream::new(Arc::new(Dir::new()), url, 3);
+        assert_eq!(&drain(&mut stream).concat()[..], b"0123456789");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(&slice(b"0123", 2..8)[..], b"23");
+        assert!(slice(b"0123", 6..8).is_empty());
+    }
+}
@@ -1,15 +1,24 @@
 use std::io::Read;
-use std::sync::{Arc, RwLock};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::Waker;
 
 use uuid::Uuid;
 
 use crate::application::prelude::{LifecycleListener, LifecycleListenerHandle};
+use crate::sched::prelude::LockLatch;
 
+use super::dispatch::{Dispatcher, Job};
+use super::inflight::{Entry, InFlight};
 use super::manifest::ManfiestResolver;
-use super::request::{Request, RequestQueue, Response};
+use super::request::{CancelToken, Priority, Progress, Request, RequestQueue, Response};
 use super::shortcut::ShortcutResolver;
+use super::stream::Stream;
 use super::url::Url;
-use super::vfs::SchemaResolver;
+use super::vfs::encrypt::Encrypted;
+use super::vfs::{SchemaResolver, VFS};
+use super::watcher::Watcher;
 use super::ResourceParams;
 
 pub struct ResourceSystem {
@@ -17,16 +26,25 @@ pub struct ResourceSystem {
     schemas: SchemaResolver,
     manifest: RwLock<ManfiestResolver>,
     requests: Arc<RequestQueue>,
+    dispatcher: Arc<Dispatcher>,
+    inflight: InFlight,
+    watcher: Option<Arc<Watcher>>,
     lifecycle: LifecycleListenerHandle,
 }
 
 struct Lifecycle {
     requests: Arc<RequestQueue>,
+    watcher: Option<Arc<Watcher>>,
 }
 
 impl LifecycleListener for Lifecycle {
     fn on_post_update(&mut self) -> Result<(), failure::Error> {
         self.requests.advance();
+
+        if let Some(ref watcher) = self.watcher {
+            watcher.poll();
+        }
+
         Ok(())
     }
 }
@@ -41,13 +59,26 @@ impl ResourceSystem {
     pub fn new(params: ResourceParams) -> Result<Self, failure::Error> {
         debug_assert!(crate::application::valid(), "");
 
+        let mut manifest = ManfiestResolver::new();
+        manifest.set_preferences(params.variants);
+
         let requests = Arc::new(RequestQueue::new());
+        let watcher = params.hot_reload.map(|v| Arc::new(Watcher::new(v)));
+
+        let mut schemas = params.schemas;
+        if let Some(key) = params.key {
+            schemas.wrap(|vfs| Arc::new(Encrypted::from_shared(vfs, key.clone())));
+        }
+
         let sys = ResourceSystem {
             shortcut: params.shortcuts,
-            schemas: params.schemas,
-            manifest: RwLock::new(ManfiestResolver::new()),
+            schemas,
+            manifest: RwLock::new(manifest),
             requests: requests.clone(),
-            lifecycle: crate::application::attach(Lifecycle { requests }),
+            dispatcher: Arc::new(Dispatcher::new()),
+            inflight: InFlight::new(),
+            watcher: watcher.clone(),
+            lifecycle: crate::application::attach(Lifecycle { requests, watcher }),
         };
 
         Ok(sys)
@@ -67,6 +98,56 @@ impl ResourceSystem {
         self.manifest.write().unwrap().add(url, file)
     }
 
+    /// Attach a manifest as overlay of the resources under `target`, whose files are located
+    /// under `prefix` instead.
+    pub fn attach_overlay<T1, T2>(
+        &self,
+        prefix: T1,
+        target: T2,
+        file: &mut dyn Read,
+    ) -> Result<(), failure::Error>
+    where
+        T1: AsRef<str>,
+        T2: AsRef<str>,
+    {
+        let (prefix, target) = (prefix.as_ref(), target.as_ref());
+        let url = self
+            .shortcut
+            .resolve(prefix)
+            .ok_or_else(|| format_err!("Could not resolve manifest filename: {}.", prefix))?;
+        let target = self
+            .shortcut
+            .resolve(target)
+            .ok_or_else(|| format_err!("Could not resolve overlay target: {}.", target))?;
+        self.manifest
+            .write()
+            .unwrap()
+            .add_overlay(url, target, file)
+    }
+
+    /// Detach the manifest that attached with `prefix`, returns false if there is none.
+    pub fn detach<T: AsRef<str>>(&self, prefix: T) -> bool {
+        match self.shortcut.resolve(prefix.as_ref()) {
+            Some(url) => self.manifest.write().unwrap().unmount(url),
+            None => false,
+        }
+    }
+
+    /// Returns the prefixes of attached manifests, from the lowest precedence to the highest.
+    pub fn layers(&self) -> Vec<String> {
+        let manifest = self.manifest.read().unwrap();
+        manifest.layers().map(|v| v.to_owned()).collect()
+    }
+
+    /// Returns the prefix of manifest that supplies the resource.
+    pub fn layer(&self, uuid: Uuid) -> Option<String> {
+        self.manifest
+            .read()
+            .unwrap()
+            .layer(uuid)
+            .map(|v| v.to_owned())
+    }
+
     /// Resolve shortcuts in the provided string recursively and return None if not exists.
     #[inline]
     pub fn resolve<T: AsRef<str>>(&self, url: T) -> Option<String> {
@@ -88,15 +169,86 @@ impl ResourceSystem {
         self.manifest.read().unwrap().contains(uuid)
     }
 
-    /// Loads file asynchronously with response callback.
+    /// Returns the size of resource file in bytes if it's recorded in manifest.
     #[inline]
-    pub fn load_with_callback<T>(&self, uuid: Uuid, func: T) -> Result<(), failure::Error>
+    pub fn size(&self, uuid: Uuid) -> Option<u64> {
+        self.manifest.read().unwrap().size(uuid)
+    }
+
+    /// Returns the received bytes of resource if it's being loaded.
+    #[inline]
+    pub fn progress(&self, uuid: Uuid) -> Option<u64> {
+        self.inflight.progress(uuid)
+    }
+
+    /// Wakes the task after the next advance of requests.
+    #[inline]
+    pub fn wake_on_advance(&self, waker: &Waker) {
+        self.requests.wake_on_advance(waker);
+    }
+
+    /// Returns the UUIDs of resources that have been modified on disk after `cursor`, and
+    /// moves the cursor to the latest modification.
+    pub fn changes(&self, cursor: &mut usize) -> Vec<Uuid> {
+        match self.watcher {
+            Some(ref watcher) => watcher.changes(cursor),
+            None => Vec::new(),
+        }
+    }
+
+    /// Stops polling the modification of resource file.
+    pub fn unwatch(&self, uuid: Uuid) {
+        if let Some(ref watcher) = self.watcher {
+            watcher.unwatch(uuid);
+        }
+    }
+
+    /// Loads file asynchronously with response callback. The returned token could be used
+    /// to cancel the loading, and the callback is dropped without being called if so.
+    #[inline]
+    pub fn load_with_callback<T>(&self, uuid: Uuid, func: T) -> Result<CancelToken, failure::Error>
+    where
+        T: FnOnce(Response) + Send + 'static,
+    {
+        self.load_with_priority_and_callback(uuid, Priority::Normal, func)
+    }
+
+    /// Loads file asynchronously with priority and response callback.
+    pub fn load_with_priority_and_callback<T>(
+        &self,
+        uuid: Uuid,
+        priority: Priority,
+        func: T,
+    ) -> Result<CancelToken, failure::Error>
     where
         T: FnOnce(Response) + Send + 'static,
     {
-        let req = self.load(uuid)?;
+        let (req, token) = self.load_with_priority(uuid, priority).map(split)?;
         self.requests.add(req, func);
-        Ok(())
+        Ok(token)
+    }
+
+    /// Loads file asynchronously after all of its dependencies recorded in manifest. The
+    /// loaded dependencies are kept until the callback returns, so the resources created
+    /// inside it share the same bytes instead of fetching them again.
+    pub fn load_with_dependencies_and_callback<T>(
+        &self,
+        uuid: Uuid,
+        priority: Priority,
+        func: T,
+    ) -> Result<CancelToken, failure::Error>
+    where
+        T: FnOnce(Response) + Send + 'static,
+    {
+        let (req, entries) = self.load_group(uuid, priority)?;
+        let (req, token) = split(req);
+
+        self.requests.add(req, move |rsp| {
+            func(rsp);
+            drop(entries);
+        });
+
+        Ok(token)
     }
 
     #[inline]
@@ -116,12 +268,9 @@ impl ResourceSystem {
             .ok_or_else(|| format_err!("Could not resolve filename: {}.", filename))?;
         let url = Url::new(url)?;
 
-        let state = Request::latch();
-        let req = Request::new(state.clone());
-        self.requests.add(req, func);
-
         let vfs = self.schemas.locate(url.schema())?;
-        crate::sched::spawn(move || vfs.request(&url, state));
+        let req = self.dispatch(vfs, url, None, Priority::High);
+        self.requests.add(req, func);
 
         Ok(())
     }
@@ -132,33 +281,90 @@ impl ResourceSystem {
         &self,
         filename: T1,
         func: T2,
-    ) -> Result<(), failure::Error>
+    ) -> Result<CancelToken, failure::Error>
     where
         T1: AsRef<str>,
         T2: FnOnce(Response) + Send + 'static,
     {
         let filename = filename.as_ref();
-        let req = self.load_from(filename)?;
+        let (req, token) = self.load_from(filename).map(split)?;
         self.requests.add(req, func);
-        Ok(())
+        Ok(token)
     }
 
     /// Loads file asynchronously. This method will returns a `Request` object immediatedly,
     /// its user's responsibility to store the object and frequently check it for completion.
+    #[inline]
     pub fn load(&self, uuid: Uuid) -> Result<Request, failure::Error> {
-        let url =
-            self.manifest.read().unwrap().resolve(uuid).ok_or_else(|| {
-                format_err!("Could not found resource {} in this registry.", uuid)
-            })?;
-
-        let url = Url::new(url)?;
-        let vfs = self.schemas.locate(url.schema())?;
+        self.load_with_priority(uuid, Priority::Normal)
+    }
 
+    /// Loads file asynchronously with priority. Requests with higher priority are sent to
+    /// VFS first if there are too many in-flight requests of the same schema.
+    pub fn load_with_priority(
+        &self,
+        uuid: Uuid,
+        priority: Priority,
+    ) -> Result<Request, failure::Error> {
         let state = Request::latch();
-        let req = Request::new(state.clone());
+        let token = CancelToken::new();
+        self.join(uuid, priority, state.clone(), &token)?;
+        Ok(Request::with_token(state, token))
+    }
 
-        crate::sched::spawn(move || vfs.request(&url, state));
-        Ok(req)
+    /// Loads file asynchronously after all of its dependencies recorded in manifest. The
+    /// request is completed once the whole dependency closure is loaded, and it fails if
+    /// any of them fails.
+    #[inline]
+    pub fn load_with_dependencies(
+        &self,
+        uuid: Uuid,
+        priority: Priority,
+    ) -> Result<Request, failure::Error> {
+        self.load_group(uuid, priority).map(|(req, _)| req)
+    }
+
+    /// Loads the file at url asynchronously, without looking up the manifest.
+    pub fn load_url<T: AsRef<str>>(&self, filename: T) -> Result<Request, failure::Error> {
+        let (vfs, url) = self.open(filename.as_ref())?;
+        Ok(self.dispatch(vfs, url, None, Priority::Normal))
+    }
+
+    /// Writes the file at url, which is created if it does not exist.
+    pub fn write<T: AsRef<str>>(&self, filename: T, bytes: &[u8]) -> Result<(), failure::Error> {
+        let (vfs, url) = self.open(filename.as_ref())?;
+        vfs.write(&url, bytes)
+    }
+
+    /// Returns the names of files in the directory at url.
+    pub fn list<T: AsRef<str>>(&self, dir: T) -> Result<Vec<String>, failure::Error> {
+        let (vfs, url) = self.open(dir.as_ref())?;
+        vfs.list(&url)
+    }
+
+    /// Deletes the file at url.
+    pub fn delete<T: AsRef<str>>(&self, filename: T) -> Result<(), failure::Error> {
+        let (vfs, url) = self.open(filename.as_ref())?;
+        vfs.delete(&url)
+    }
+
+    /// Reads the byte range of resource file asynchronously.
+    pub fn load_range(
+        &self,
+        uuid: Uuid,
+        range: Range<u64>,
+        priority: Priority,
+    ) -> Result<Request, failure::Error> {
+        let url = self.locate(uuid)?;
+        let vfs = self.schemas.locate(url.schema())?;
+        Ok(self.dispatch(vfs, url, Some(range), priority))
+    }
+
+    /// Creates a stream which reads the resource file in chunks.
+    pub fn stream(&self, uuid: Uuid, chunk: usize) -> Result<Stream, failure::Error> {
+        let url = self.locate(uuid)?;
+        let vfs = self.schemas.locate(url.schema())?;
+        Ok(Stream::new(vfs, url, chunk))
     }
 
     /// Loads file asynchronously. This method will returns a `Request` object immediatedly,
@@ -181,4 +387,168 @@ impl ResourceSystem {
 
         self.load(uuid)
     }
+
+    // Joins the in-flight loading of resource, or dispatches a new one.
+    fn join(
+        &self,
+        uuid: Uuid,
+        priority: Priority,
+        state: Arc<LockLatch<Response>>,
+        token: &CancelToken,
+    ) -> Result<Arc<Entry>, failure::Error> {
+        let url = self.locate(uuid)?;
+        let vfs = self.schemas.locate(url.schema())?;
+
+        if let Some(ref watcher) = self.watcher {
+            watcher.watch(uuid, vfs.clone(), url.clone());
+        }
+
+        let entry = self
+            .inflight
+            .join(uuid, state, token, |state, token, progress| {
+                let limit = self.schemas.concurrency(url.schema());
+                let job = Job {
+                    vfs,
+                    url,
+                    range: None,
+                    progress,
+                    state,
+                    token,
+                    priority,
+                };
+
+                self.dispatcher.submit(job, limit);
+            });
+
+        Ok(entry)
+    }
+
+    // Loads the dependency closure of resource, and returns the request of the resource
+    // itself with the shared loadings of its dependencies.
+    fn load_group(
+        &self,
+        uuid: Uuid,
+        priority: Priority,
+    ) -> Result<(Request, Vec<Arc<Entry>>), failure::Error> {
+        let closure = self.manifest.read().unwrap().closure(uuid)?;
+
+        let state = Request::latch();
+        let token = CancelToken::new();
+        let group = Arc::new(Group {
+            uuid,
+            remaining: AtomicUsize::new(closure.len()),
+            rsp: Mutex::new(None),
+            state: state.clone(),
+        });
+
+        let mut entries = Vec::with_capacity(closure.len());
+        for v in closure {
+            let group = group.clone();
+            let latch = LockLatch::with_callback(move |rsp| group.set(v, rsp));
+
+            match self.join(v, priority, Arc::new(latch), &token) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => {
+                    token.cancel();
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok((Request::with_token(state, token), entries))
+    }
+
+    // Resolves the url of file and its VFS.
+    fn open(&self, filename: &str) -> Result<(Arc<dyn VFS>, Url), failure::Error> {
+        let url = self
+            .shortcut
+            .resolve(filename)
+            .ok_or_else(|| format_err!("Could not resolve filename: {}.", filename))?;
+        let url = Url::new(url)?;
+        let vfs = self.schemas.locate(url.schema())?;
+        Ok((vfs, url))
+    }
+
+    // Resolves the url of resource file.
+    fn locate(&self, uuid: Uuid) -> Result<Url, failure::Error> {
+        let url =
+            self.manifest.read().unwrap().resolve(uuid).ok_or_else(|| {
+                format_err!("Could not found resource {} in this registry.", uuid)
+            })?;
+
+        Url::new(url)
+    }
+
+    fn dispatch(
+        &self,
+        vfs: Arc<dyn VFS>,
+        url: Url,
+        range: Option<Range<u64>>,
+        priority: Priority,
+    ) -> Request {
+        let state = Request::latch();
+        let token = CancelToken::new();
+        let limit = self.schemas.concurrency(url.schema());
+
+        let job = Job {
+            vfs,
+            url,
+            range,
+            progress: Progress::new(),
+            state: state.clone(),
+            token: token.clone(),
+            priority,
+        };
+
+        self.dispatcher.submit(job, limit);
+        Request::with_token(state, token)
+    }
+}
+
+// The loading of a resource and its dependencies, which is completed with the response
+// of resource, or the first error of them.
+struct Group {
+    uuid: Uuid,
+    remaining: AtomicUsize,
+    rsp: Mutex<Option<Response>>,
+    state: Arc<LockLatch<Response>>,
+}
+
+impl Group {
+    fn set(&self, uuid: Uuid, rsp: Response) {
+        {
+            let mut v = self.rsp.lock().unwrap();
+            match rsp {
+                Err(err) if uuid != self.uuid => {
+                    if v.as_ref().map(|v| v.is_ok()).unwrap_or(true) {
+                        *v = Some(Err(format_err!(
+                            "[ResourceSystem] Failed to load dependency {} of {}. {}",
+                            uuid,
+                            self.uuid,
+                            err
+                        )));
+                    }
+                }
+                rsp => {
+                    if uuid == self.uuid && v.is_none() {
+                        *v = Some(rsp);
+                    }
+                }
+            }
+        }
+
+        if self.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let rsp = self.rsp.lock().unwrap().take().unwrap();
+            self.state.set(rsp);
+        }
+    }
+}
+
+fn split(req: Request) -> (Request, CancelToken) {
+    let token = match req {
+        Request::NotReady(_, ref token) => token.clone(),
+        Request::Ok(_) => CancelToken::new(),
+    };
+
+    (req, token)
 }
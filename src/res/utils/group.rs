@@ -0,0 +1,214 @@
+//! # ResourceGroup
+//!
+//! The `ResourceGroup` collects the handles of resources from any `ResourcePool`, e.g. the
+//! textures, meshes, audio clips and prefabs required by a level. It reports the aggregate
+//! progress of loading, which could be used to draw a loading screen, and releases all the
+//! members together when they are no longer needed.
+//!
+//! ```rust,ignore
+//! let mut group = ResourceGroup::new();
+//! let texture = group.add(video::create_texture_from("res:crate.png")?);
+//! let prefab = group.add(crayon_world::create_prefab_from("res:cornell_box.obj")?);
+//!
+//! let progress = group.progress();
+//! info!("{} / {} bytes loaded.", progress.loaded_bytes, progress.total_bytes);
+//! ```
+//!
+//! The members are released when the group is dropped, so the group should be kept alive as
+//! long as its resources are used.
+//!
+//! Since `ResourceGroup` implements `LatchProbe`, it could be passed to the launcher of
+//! engine directly, which waits until all the members are loaded or failed.
+
+use crate::sched::prelude::LatchProbe;
+
+use super::state::ResourceState;
+
+/// The handle of resource that could be collected by `ResourceGroup`.
+pub trait GroupMember: Send + Sync + 'static {
+    /// Returns the state of resource.
+    fn state(&self) -> ResourceState;
+    /// Returns the loaded and total bytes of resource file.
+    fn bytes(&self) -> (u64, u64);
+    /// Returns the error of resource if it's failed to load.
+    fn error(&self) -> Option<failure::Error>;
+    /// Releases the ownership of resource.
+    fn release(&self);
+}
+
+/// The aggregate loading progress of `ResourceGroup`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GroupProgress {
+    pub loaded_bytes: u64,
+    pub total_bytes: u64,
+    pub ok: usize,
+    pub not_ready: usize,
+    pub err: usize,
+}
+
+impl GroupProgress {
+    /// Returns the ratio of completed loading in range [0, 1]. It's measured in bytes if
+    /// the sizes of resource files are known, otherwise in the number of resources. Failed
+    /// resources are excluded from the bytes, and treated as completed in the number.
+    pub fn ratio(&self) -> f32 {
+        let len = self.ok + self.not_ready + self.err;
+        if len == 0 || self.not_ready == 0 {
+            return 1.0;
+        }
+
+        if self.total_bytes > 0 {
+            (self.loaded_bytes as f64 / self.total_bytes as f64).min(1.0) as f32
+        } else {
+            (self.ok + self.err) as f32 / len as f32
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ResourceGroup {
+    members: Vec<Box<dyn GroupMember>>,
+}
+
+impl ResourceGroup {
+    /// Creates a new and empty `ResourceGroup`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a handle into this group, which takes the ownership of resource. The handle
+    /// is returned for convenience.
+    pub fn add<T: GroupMember + Copy>(&mut self, handle: T) -> T {
+        self.members.push(Box::new(handle));
+        handle
+    }
+
+    /// Returns the number of resources in this group.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns true if there is no resource in this group.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Returns the aggregate loading progress of all the resources.
+    pub fn progress(&self) -> GroupProgress {
+        let mut progress = GroupProgress::default();
+
+        for v in &self.members {
+            let (state, (loaded, total)) = (v.state(), v.bytes());
+
+            match state {
+                ResourceState::Ok => progress.ok += 1,
+                ResourceState::NotReady => progress.not_ready += 1,
+                ResourceState::Err => progress.err += 1,
+            }
+
+            // The bytes of failed resources are never loaded.
+            if state != ResourceState::Err {
+                progress.total_bytes += total;
+                progress.loaded_bytes += loaded;
+            }
+        }
+
+        progress
+    }
+
+    /// Returns the combined state of all the resources, see `ResourceState::combine`.
+    pub fn state(&self) -> ResourceState {
+        ResourceState::combine(self.members.iter().map(|v| v.state()))
+    }
+
+    /// Releases all the resources in this group.
+    pub fn release(&mut self) {
+        for v in self.members.drain(..) {
+            v.release();
+        }
+    }
+}
+
+impl Drop for ResourceGroup {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl LatchProbe for ResourceGroup {
+    fn is_set(&self) -> bool {
+        self.members
+            .iter()
+            .all(|v| v.state() != ResourceState::NotReady)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Clone, Copy)]
+    struct Member(ResourceState, u64, u64);
+
+    impl GroupMember for Member {
+        fn state(&self) -> ResourceState {
+            self.0
+        }
+
+        fn bytes(&self) -> (u64, u64) {
+            (self.1, self.2)
+        }
+
+        fn error(&self) -> Option<failure::Error> {
+            None
+        }
+
+        fn release(&self) {
+            RELEASED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn progress() {
+        let mut group = ResourceGroup::new();
+        assert!(group.is_set());
+        assert_eq!(group.progress().ratio(), 1.0);
+
+        group.add(Member(ResourceState::Ok, 10, 10));
+        group.add(Member(ResourceState::NotReady, 5, 20));
+        group.add(Member(ResourceState::Err, 0, 10));
+        assert!(!group.is_set());
+        assert_eq!(group.state(), ResourceState::Err);
+
+        let progress = group.progress();
+        assert_eq!((progress.loaded_bytes, progress.total_bytes), (15, 30));
+        assert_eq!((progress.ok, progress.not_ready, progress.err), (1, 1, 1));
+        assert_eq!(progress.ratio(), 0.5);
+
+        let progress = GroupProgress {
+            total_bytes: 0,
+            loaded_bytes: 0,
+            ..progress
+        };
+        assert!((progress.ratio() - 2.0 / 3.0).abs() < 1e-6);
+
+        group.release();
+        assert!(group.is_empty());
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 3);
+
+        // The released members are not released again when the group is dropped.
+        drop(group);
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 3);
+
+        let mut group = ResourceGroup::new();
+        group.add(Member(ResourceState::Ok, 10, 10));
+        group.add(Member(ResourceState::NotReady, 5, 20));
+        drop(group);
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 5);
+    }
+}
@@ -1,7 +1,11 @@
+pub mod group;
 pub mod pool;
+pub mod ready;
 pub mod state;
 
 pub mod prelude {
-    pub use super::pool::{ResourceLoader, ResourcePool};
+    pub use super::group::{GroupMember, GroupProgress, ResourceGroup};
+    pub use super::pool::{ResourceLoader, ResourcePool, UnloadPolicy};
+    pub use super::ready::{ready, ResourceReady};
     pub use super::state::ResourceState;
 }
@@ -23,7 +23,28 @@
 //! Everytime you create a resource at runtime, the `ResourcePool` will increases the reference count of
 //! the resource by 1. And when you are done with the resource, its the user's responsibility to
 //! drop the ownership of the resource. And when the last ownership to a given resource is dropped,
-//! the corresponding resource is also destroyed.
+//! the corresponding resource is also destroyed. If the resource is still loading at that time,
+//! the pending request is canceled as well.
+//!
+//! ## Unload Policy
+//!
+//! By default, the resource is destroyed as soon as its last ownership is dropped. With
+//! `UnloadPolicy::Cache`, the idle resources that are created from files are kept in a LRU
+//! cache instead, so creating them again later would reuse them without loading. The least
+//! recently used ones are destroyed once the memory of all the resources in pool, which is
+//! reported by `ResourceLoader::size`, exceeds the budget.
+//!
+//! ## Dependencies
+//!
+//! If `set_load_dependencies` is enabled, the dependencies of resources recorded in manifest are
+//! loaded before them, and the loader could create the dependent resources from UUIDs without
+//! fetching them again.
+//!
+//! ## Hot-Reloading
+//!
+//! If the hot-reloading is enabled in `ResourceParams`, the resources that are modified on disk
+//! will be loaded again and swapped with `ResourceLoader::reload`, while keeping the same handle.
+//! Its only supported by the loaders which implement `ResourceLoader::reload`.
 
 use failure::Error;
 use std::sync::{Arc, Mutex};
@@ -31,6 +52,7 @@ use uuid::Uuid;
 
 use crate::utils::prelude::{FastHashMap, HandleLike, ObjectPool};
 
+use super::super::request::{CancelToken, Priority};
 use super::state::ResourceState;
 
 pub trait ResourceLoader: Send + Sync {
@@ -41,6 +63,34 @@ pub trait ResourceLoader: Send + Sync {
     fn load(&self, _: Self::Handle, _: &[u8]) -> Result<Self::Intermediate, Error>;
     fn create(&self, _: Self::Handle, _: Self::Intermediate) -> Result<Self::Resource, Error>;
     fn delete(&self, _: Self::Handle, _: Self::Resource);
+
+    /// Returns the memory size of resource in bytes, which is used to enforce the budget
+    /// of `UnloadPolicy::Cache`.
+    fn size(&self, _: &Self::Resource) -> usize {
+        0
+    }
+
+    /// Replaces the resource with the reloaded one in place, while keeping the same handle.
+    /// Loaders have to opt in the hot-reloading by implementing it, the default one returns
+    /// a error and the previous resource is kept.
+    fn reload(
+        &self,
+        _: Self::Handle,
+        _: &mut Self::Resource,
+        _: Self::Intermediate,
+    ) -> Result<(), Error> {
+        bail!("Hot-reloading is unsupported.");
+    }
+}
+
+/// The policy to unload resources when their last ownerships are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnloadPolicy {
+    /// Destroys the resource immediately.
+    Immediate,
+    /// Keeps the idle resources in a LRU cache for quick reuse. The least recently used
+    /// ones are destroyed once the memory of all the resources exceeds `budget` bytes.
+    Cache { budget: usize },
 }
 
 // The `ResourcePool` is a standardized resources manager that defines a set of interface for creation,
@@ -51,9 +101,14 @@ where
     Loader: ResourceLoader<Handle = H> + Clone + 'static,
 {
     items: ObjectPool<H, Item<Loader::Resource>>,
-    requests: FastHashMap<H, Arc<Mutex<ResourceAsyncState<Loader::Intermediate>>>>,
+    requests: FastHashMap<H, (AsyncState<Loader::Intermediate>, CancelToken)>,
     registry: FastHashMap<Uuid, H>,
     loader: Loader,
+    changes: usize,
+    dependencies: bool,
+    policy: UnloadPolicy,
+    memory: usize,
+    idle: Vec<H>,
 }
 
 impl<H, Loader> ResourcePool<H, Loader>
@@ -68,14 +123,52 @@ where
             registry: FastHashMap::default(),
             requests: FastHashMap::default(),
             loader,
+            changes: 0,
+            dependencies: false,
+            policy: UnloadPolicy::Immediate,
+            memory: 0,
+            idle: Vec::new(),
         }
     }
 
+    /// Sets the policy to unload resources, the idle resources that exceed the budget are
+    /// destroyed immediately.
+    pub fn set_unload_policy(&mut self, policy: UnloadPolicy) {
+        self.policy = policy;
+        self.evict();
+    }
+
+    /// Returns the memory of all the resources in bytes, including the idle ones.
+    #[inline]
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Loads the dependencies of resources before them if enabled.
+    #[inline]
+    pub fn set_load_dependencies(&mut self, enabled: bool) {
+        self.dependencies = enabled;
+    }
+
     pub fn advance(&mut self) -> Result<(), Error> {
+        if crate::res::valid() {
+            for uuid in crate::res::changes(&mut self.changes) {
+                if let Some(&handle) = self.registry.get(&uuid) {
+                    if !self.requests.contains_key(&handle) {
+                        info!("[ResourcePool] reload {:?} ({}).", handle, uuid);
+                        if let Err(err) = self.request(handle, uuid, Priority::Normal) {
+                            warn!("{:?}", err);
+                        }
+                    }
+                }
+            }
+        }
+
         let items = &mut self.items;
         let loader = &self.loader;
+        let memory = &mut self.memory;
 
-        self.requests.retain(|&handle, req| {
+        self.requests.retain(|&handle, (req, _)| {
             let mut req = req.lock().unwrap();
             if let ResourceAsyncState::NotReady = *req {
                 return true;
@@ -91,13 +184,39 @@ where
                         item.error = Some(err);
                     }
                 }
-                ResourceAsyncState::Ok(intermediate) => {
+                ResourceAsyncState::Ok(intermediate, len) => {
                     if let Some(item) = items.get_mut(handle) {
-                        match loader.create(handle, intermediate) {
-                            Ok(resource) => item.resource = Some(resource),
-                            Err(err) => {
-                                warn!("{:?}", err);
-                                item.error = Some(err);
+                        item.bytes = (len, len);
+
+                        let uuid = item.uuid;
+                        let named = |action: &str, err: Error| match uuid {
+                            Some(uuid) => {
+                                format_err!("[ResourcePool] Failed to {} {}. {}", action, uuid, err)
+                            }
+                            None => err,
+                        };
+
+                        // Keeps the previous resource if reloading failed.
+                        if let Some(ref mut resource) = item.resource {
+                            if let Err(err) = loader.reload(handle, resource, intermediate) {
+                                warn!("{:?}", named("reload", err));
+                            }
+
+                            *memory -= item.size;
+                            item.size = loader.size(resource);
+                            *memory += item.size;
+                        } else {
+                            match loader.create(handle, intermediate) {
+                                Ok(resource) => {
+                                    item.size = loader.size(&resource);
+                                    *memory += item.size;
+                                    item.resource = Some(resource);
+                                }
+                                Err(err) => {
+                                    let err = named("create", err);
+                                    warn!("{:?}", err);
+                                    item.error = Some(err);
+                                }
                             }
                         }
                     }
@@ -108,6 +227,7 @@ where
             false
         });
 
+        self.evict();
         Ok(())
     }
 
@@ -119,7 +239,12 @@ where
         let handle = self.alloc(None);
         match self.loader.create(handle, params) {
             Ok(value) => {
-                self.items.get_mut(handle).unwrap().resource = Some(value);
+                let item = self.items.get_mut(handle).unwrap();
+                item.size = self.loader.size(&value);
+                item.resource = Some(value);
+
+                self.memory += item.size;
+                self.evict();
                 Ok(handle)
             }
             Err(error) => {
@@ -132,50 +257,50 @@ where
     /// Create a resource from file asynchronously.
     #[inline]
     pub fn create_from<T: AsRef<str>>(&mut self, url: T) -> Result<H, Error> {
+        self.create_from_with_priority(url, Priority::Normal)
+    }
+
+    /// Create a resource from file asynchronously with the priority of loading.
+    pub fn create_from_with_priority<T: AsRef<str>>(
+        &mut self,
+        url: T,
+        priority: Priority,
+    ) -> Result<H, Error> {
         let url = url.as_ref();
         let uuid = crate::res::find(url)
             .ok_or_else(|| format_err!("Could not found resource '{}'.", url))?;
-        self.create_from_uuid(uuid)
+        self.create_from_uuid_with_priority(uuid, priority)
     }
 
     /// Create a named resource from file asynchronously.
     #[inline]
     pub fn create_from_uuid(&mut self, uuid: Uuid) -> Result<H, Error> {
+        self.create_from_uuid_with_priority(uuid, Priority::Normal)
+    }
+
+    /// Create a named resource from file asynchronously with the priority of loading. The
+    /// priority is ignored if the resource has been created already.
+    pub fn create_from_uuid_with_priority(
+        &mut self,
+        uuid: Uuid,
+        priority: Priority,
+    ) -> Result<H, Error> {
         if let Some(&handle) = self.registry.get(&uuid) {
-            self.items.get_mut(handle).unwrap().rc += 1;
+            let item = self.items.get_mut(handle).unwrap();
+            item.rc += 1;
+
+            // Revives the idle resource.
+            if item.rc == 1 {
+                self.idle.retain(|&v| v != handle);
+            }
+
             return Ok(handle);
         }
 
         let handle = self.alloc(Some(uuid));
 
-        let rx = Arc::new(Mutex::new(ResourceAsyncState::NotReady));
-        let tx = rx.clone();
-        let loader = self.loader.clone();
-
-        let result = crate::res::load_with_callback(uuid, move |rsp| match rsp {
-            Ok(bytes) => {
-                let itermediate = loader.load(handle, &bytes);
-
-                match itermediate {
-                    Ok(item) => {
-                        *tx.lock().unwrap() = ResourceAsyncState::Ok(item);
-                    }
-                    Err(err) => {
-                        *tx.lock().unwrap() = ResourceAsyncState::Err(err);
-                    }
-                }
-            }
-
-            Err(err) => {
-                *tx.lock().unwrap() = ResourceAsyncState::Err(err);
-            }
-        });
-
-        match result {
-            Ok(_) => {
-                self.requests.insert(handle, rx);
-                Ok(handle)
-            }
+        match self.request(handle, uuid, priority) {
+            Ok(_) => Ok(handle),
             Err(err) => {
                 self.delete(handle);
                 Err(err)
@@ -185,9 +310,11 @@ where
 
     /// Deletes a resource from loadery.
     pub fn delete(&mut self, handle: H) {
+        let cache = self.policy != UnloadPolicy::Immediate;
         let disposed = self
             .items
             .get_mut(handle)
+            .filter(|e| e.rc > 0)
             .map(|e| {
                 e.rc -= 1;
                 e.rc == 0
@@ -195,23 +322,53 @@ where
             .unwrap_or(false);
 
         if disposed {
-            let e = self.items.free(handle).unwrap();
-
-            if let Some(uuid) = e.uuid {
-                self.registry.remove(&uuid);
+            let e = self.items.get(handle).unwrap();
+            if cache && e.uuid.is_some() && e.resource.is_some() {
+                self.idle.push(handle);
+                self.evict();
+            } else {
+                self.free(handle);
             }
+        }
+    }
 
-            if let Some(resource) = e.resource {
-                self.loader.delete(handle, resource);
+    // Destroys the least recently used idle resources until the memory is within budget.
+    fn evict(&mut self) {
+        let budget = match self.policy {
+            UnloadPolicy::Immediate => None,
+            UnloadPolicy::Cache { budget } => Some(budget),
+        };
+
+        while !self.idle.is_empty() && budget.map(|v| self.memory > v).unwrap_or(true) {
+            let handle = self.idle.remove(0);
+            self.free(handle);
+        }
+    }
+
+    fn free(&mut self, handle: H) {
+        let e = self.items.free(handle).unwrap();
+        self.memory -= e.size;
+
+        if let Some(uuid) = e.uuid {
+            self.registry.remove(&uuid);
+            if crate::res::valid() {
+                crate::res::unwatch(uuid);
             }
         }
+
+        if let Some((_, token)) = self.requests.remove(&handle) {
+            token.cancel();
+        }
+
+        if let Some(resource) = e.resource {
+            self.loader.delete(handle, resource);
+        }
     }
 
     /// Get the resource state.
     #[inline]
     pub fn state(&self, handle: H) -> ResourceState {
-        self.items
-            .get(handle)
+        self.alive(handle)
             .map(|e| {
                 if e.resource.is_some() {
                     ResourceState::Ok
@@ -227,19 +384,96 @@ where
     /// Checks if the handle is still avaiable in this pool.
     #[inline]
     pub fn contains(&self, handle: H) -> bool {
-        self.items.contains(handle)
+        self.alive(handle).is_some()
+    }
+
+    /// Return an iterator over the handles of all the alive resources.
+    #[inline]
+    pub fn keys<'a>(&'a self) -> impl DoubleEndedIterator<Item = H> + 'a {
+        let items = &self.items;
+        items.keys().filter(move |&v| items.get(v).unwrap().rc > 0)
+    }
+
+    /// Returns the error of resource if it's failed to load.
+    #[inline]
+    pub fn error(&self, handle: H) -> Option<&Error> {
+        self.alive(handle).and_then(|e| e.error.as_ref())
+    }
+
+    /// Returns the loaded and total bytes of the resource file. The total is recorded in
+    /// manifest, and it's zero if unknown or the resource is not created from file. While
+    /// the file is being loaded, the loaded bytes are the ones received from VFS so far.
+    pub fn bytes(&self, handle: H) -> (u64, u64) {
+        let e = match self.alive(handle) {
+            Some(e) => e,
+            None => return (0, 0),
+        };
+
+        match e.uuid {
+            Some(uuid) if e.resource.is_none() && self.requests.contains_key(&handle) => {
+                let loaded = crate::res::progress(uuid).unwrap_or(0);
+                let (_, total) = e.bytes;
+                (if total > 0 { loaded.min(total) } else { loaded }, total)
+            }
+            _ => e.bytes,
+        }
     }
 
     /// Return immutable reference to internal value with name `Handle`.
     #[inline]
     pub fn resource(&self, handle: H) -> Option<&Loader::Resource> {
-        self.items.get(handle).and_then(|e| e.resource.as_ref())
+        self.alive(handle).and_then(|e| e.resource.as_ref())
     }
 
     /// Return mutable reference to internal value with name `Handle`.
     #[inline]
     pub fn resource_mut(&mut self, handle: H) -> Option<&mut Loader::Resource> {
-        self.items.get_mut(handle).and_then(|e| e.resource.as_mut())
+        self.items
+            .get_mut(handle)
+            .filter(|e| e.rc > 0)
+            .and_then(|e| e.resource.as_mut())
+    }
+
+    // Returns the item if it's not idle.
+    #[inline]
+    fn alive(&self, handle: H) -> Option<&Item<Loader::Resource>> {
+        self.items.get(handle).filter(|e| e.rc > 0)
+    }
+
+    fn request(&mut self, handle: H, uuid: Uuid, priority: Priority) -> Result<(), Error> {
+        let rx = Arc::new(Mutex::new(ResourceAsyncState::NotReady));
+        let tx = rx.clone();
+        let loader = self.loader.clone();
+
+        let func = move |rsp: crate::res::request::Response| {
+            let itermediate = rsp.and_then(|bytes| {
+                let len = bytes.len() as u64;
+                loader.load(handle, &bytes).map(|v| (v, len))
+            });
+
+            match itermediate {
+                Ok((item, len)) => {
+                    *tx.lock().unwrap() = ResourceAsyncState::Ok(item, len);
+                }
+                Err(err) => {
+                    let err = format_err!("[ResourcePool] Failed to load {}. {}", uuid, err);
+                    *tx.lock().unwrap() = ResourceAsyncState::Err(err);
+                }
+            }
+        };
+
+        let token = if self.dependencies {
+            crate::res::load_with_dependencies_and_callback(uuid, priority, func)?
+        } else {
+            crate::res::load_with_priority_and_callback(uuid, priority, func)?
+        };
+
+        if let Some(item) = self.items.get_mut(handle) {
+            item.bytes.1 = crate::res::size(uuid).unwrap_or(item.bytes.1);
+        }
+
+        self.requests.insert(handle, (rx, token));
+        Ok(())
     }
 
     #[inline]
@@ -249,6 +483,8 @@ where
             uuid,
             resource: None,
             error: None,
+            bytes: (0, 0),
+            size: 0,
         };
 
         let handle = self.items.create(entry);
@@ -266,10 +502,158 @@ struct Item<T> {
     uuid: Option<Uuid>,
     resource: Option<T>,
     error: Option<Error>,
+    bytes: (u64, u64),
+    size: usize,
 }
 
+type AsyncState<T> = Arc<Mutex<ResourceAsyncState<T>>>;
+
 enum ResourceAsyncState<T> {
-    Ok(T),
+    Ok(T, u64),
     Err(Error),
     NotReady,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    impl_handle!(TestHandle);
+
+    #[derive(Clone, Default)]
+    struct Loader {
+        deleted: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl ResourceLoader for Loader {
+        type Handle = TestHandle;
+        type Intermediate = usize;
+        type Resource = usize;
+
+        fn load(&self, _: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate, Error> {
+            Ok(bytes.len())
+        }
+
+        fn create(&self, _: Self::Handle, item: Self::Intermediate) -> Result<usize, Error> {
+            Ok(item)
+        }
+
+        fn delete(&self, _: Self::Handle, resource: Self::Resource) {
+            self.deleted.lock().unwrap().push(resource);
+        }
+
+        fn size(&self, resource: &Self::Resource) -> usize {
+            *resource
+        }
+    }
+
+    // Creates a loaded resource as if its loaded from file.
+    fn create(pool: &mut ResourcePool<TestHandle, Loader>, uuid: u8, size: usize) -> TestHandle {
+        let handle = pool.alloc(Some(Uuid::from_bytes([uuid; 16])));
+        let item = pool.items.get_mut(handle).unwrap();
+        item.resource = Some(size);
+        item.size = size;
+        pool.memory += size;
+        handle
+    }
+
+    #[test]
+    fn unload() {
+        let loader = Loader::default();
+        let deleted = loader.deleted.clone();
+
+        let mut pool = ResourcePool::new(loader);
+        pool.set_unload_policy(UnloadPolicy::Cache { budget: 10 });
+
+        let (a, b) = (create(&mut pool, 1, 4), create(&mut pool, 2, 4));
+        assert_eq!(pool.memory(), 8);
+
+        // Idle resources are kept, but they are invisible to users.
+        pool.delete(a);
+        assert!(!pool.contains(a));
+        assert!(pool.resource(a).is_none());
+        assert_eq!(pool.keys().collect::<Vec<_>>(), vec![b]);
+        assert_eq!(pool.memory(), 8);
+
+        let c = pool.create(4).unwrap();
+        assert_eq!(*deleted.lock().unwrap(), vec![4]);
+        assert_eq!(pool.memory(), 8);
+
+        // The idle resource is revived with the same handle.
+        pool.delete(b);
+        assert_eq!(pool.create_from_uuid(Uuid::from_bytes([2; 16])).unwrap(), b);
+        assert_eq!(pool.resource(b), Some(&4));
+
+        pool.delete(b);
+        pool.delete(c);
+        assert_eq!(pool.memory(), 4);
+        pool.set_unload_policy(UnloadPolicy::Immediate);
+        assert_eq!(pool.memory(), 0);
+        assert_eq!(deleted.lock().unwrap().len(), 3);
+    }
+
+    #[derive(Clone, Default)]
+    struct Reloader(Loader);
+
+    impl ResourceLoader for Reloader {
+        type Handle = TestHandle;
+        type Intermediate = usize;
+        type Resource = usize;
+
+        fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<usize, Error> {
+            self.0.load(handle, bytes)
+        }
+
+        fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<usize, Error> {
+            self.0.create(handle, item)
+        }
+
+        fn delete(&self, handle: Self::Handle, resource: Self::Resource) {
+            self.0.delete(handle, resource)
+        }
+
+        fn reload(&self, _: Self::Handle, resource: &mut usize, item: usize) -> Result<(), Error> {
+            *resource = item;
+            Ok(())
+        }
+    }
+
+    // Completes a reloading request of resource as if the file has been modified.
+    fn modify<L>(pool: &mut ResourcePool<TestHandle, L>, handle: TestHandle, item: usize)
+    where
+        L: ResourceLoader<Handle = TestHandle, Intermediate = usize> + Clone,
+    {
+        let state = Arc::new(Mutex::new(ResourceAsyncState::Ok(item, item as u64)));
+        pool.requests.insert(handle, (state, CancelToken::new()));
+        pool.advance().unwrap();
+    }
+
+    #[test]
+    fn reload() {
+        // The previous resource is kept if the loader does not support hot-reloading.
+        let loader = Loader::default();
+        let deleted = loader.deleted.clone();
+        let mut pool = ResourcePool::new(loader);
+
+        let handle = pool.create(4).unwrap();
+        modify(&mut pool, handle, 8);
+        assert_eq!(pool.state(handle), ResourceState::Ok);
+        assert_eq!(pool.resource(handle), Some(&4));
+        assert!(deleted.lock().unwrap().is_empty());
+
+        // The handle still resolves to the reloaded one.
+        let loader = Reloader::default();
+        let deleted = loader.0.deleted.clone();
+        let mut pool = ResourcePool::new(loader);
+
+        let handle = pool.create(4).unwrap();
+        modify(&mut pool, handle, 8);
+        assert_eq!(pool.state(handle), ResourceState::Ok);
+        assert_eq!(pool.resource(handle), Some(&8));
+        assert_eq!(pool.bytes(handle), (8, 8));
+        assert!(deleted.lock().unwrap().is_empty());
+
+        pool.delete(handle);
+        assert_eq!(*deleted.lock().unwrap(), vec![8]);
+    }
+}
@@ -0,0 +1,151 @@
+//! # ResourceReady
+//!
+//! The `ResourceReady` is a `Future` which resolves once the resource of handle is loaded
+//! or failed. It works with the handles of any `ResourcePool` that implement `GroupMember`,
+//! e.g. textures, meshes, audio clips and prefabs.
+//!
+//! ```rust,ignore
+//! let texture = ready(video::create_texture_from("res:crate.png")?).await?;
+//! ```
+//!
+//! The future is polled again after the requests are advanced in every frame, so it does
+//! not depend on threads and works on web as well.
+//!
+//! The future takes the ownership of handle until it resolves. The handle is handed back
+//! once the resource is loaded, and released if the loading failed or the future is
+//! dropped before completion.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::group::GroupMember;
+use super::state::ResourceState;
+
+/// Waits for the resource of `handle`, see `ResourceReady`.
+#[inline]
+pub fn ready<T: GroupMember + Copy>(handle: T) -> ResourceReady<T> {
+    ResourceReady {
+        handle: Some(handle),
+    }
+}
+
+/// A future which resolves to the handle once its resource is loaded. If the loading
+/// failed, the handle is released and the error of resource is returned instead.
+#[derive(Debug)]
+pub struct ResourceReady<T: GroupMember> {
+    handle: Option<T>,
+}
+
+impl<T: GroupMember> Drop for ResourceReady<T> {
+    fn drop(&mut self) {
+        // Releases the handle which is never handed back.
+        if let Some(handle) = self.handle.take() {
+            handle.release();
+        }
+    }
+}
+
+impl<T: GroupMember + Copy + Unpin> Future for ResourceReady<T> {
+    type Output = Result<T, failure::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let fut = self.get_mut();
+        let handle = fut
+            .handle
+            .expect("[ResourceReady] Polled after completion.");
+
+        match handle.state() {
+            ResourceState::NotReady => {
+                crate::res::wake_on_advance(cx.waker());
+                Poll::Pending
+            }
+            ResourceState::Ok => {
+                fut.handle = None;
+                Poll::Ready(Ok(handle))
+            }
+            ResourceState::Err => {
+                let err = handle
+                    .error()
+                    .unwrap_or_else(|| format_err!("[ResourceReady] Failed to load the resource."));
+
+                fut.handle = None;
+                handle.release();
+                Poll::Ready(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::utils::testing::Counter;
+
+    use super::*;
+
+    static STATE: AtomicUsize = AtomicUsize::new(0);
+    static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, Clone, Copy)]
+    struct Member;
+
+    impl GroupMember for Member {
+        fn state(&self) -> ResourceState {
+            match STATE.load(Ordering::SeqCst) {
+                0 => ResourceState::NotReady,
+                1 => ResourceState::Ok,
+                _ => ResourceState::Err,
+            }
+        }
+
+        fn bytes(&self) -> (u64, u64) {
+            (0, 0)
+        }
+
+        fn error(&self) -> Option<failure::Error> {
+            Some(format_err!("[Member] Failed to load."))
+        }
+
+        fn release(&self) {
+            RELEASED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn ready() {
+        let (counter, waker) = Counter::waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = super::ready(Member);
+        assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+        assert_eq!(counter.count(), 1);
+
+        STATE.store(1, Ordering::SeqCst);
+        assert!(matches!(
+            Pin::new(&mut fut).poll(&mut cx),
+            Poll::Ready(Ok(_))
+        ));
+
+        drop(fut);
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 0);
+
+        // The error of resource is returned, and the handle is released.
+        STATE.store(2, Ordering::SeqCst);
+        let mut fut = super::ready(Member);
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Err(err)) => assert_eq!(err.to_string(), "[Member] Failed to load."),
+            _ => unreachable!(),
+        }
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1);
+
+        drop(fut);
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1);
+
+        // The handle is released if the future is dropped before completion.
+        STATE.store(0, Ordering::SeqCst);
+        drop(super::ready(Member));
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 2);
+    }
+}
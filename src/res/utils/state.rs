@@ -4,3 +4,11 @@ pub enum ResourceState {
     NotReady,
     Err,
 }
+
+impl ResourceState {
+    /// Combines the states of a group of resources. The group is failed if any of them
+    /// is failed, and it's ready only if all of them are ready.
+    pub fn combine<T: IntoIterator<Item = ResourceState>>(states: T) -> ResourceState {
+        states.into_iter().max().unwrap_or(ResourceState::Ok)
+    }
+}
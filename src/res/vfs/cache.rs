@@ -0,0 +1,598 @@
+//! A `VFS` adapter that caches the files of remote VFS on local disk.
+//!
+//! Files are keyed by the UUID of resource plus the CRC32 checksum in manifest, so an entry
+//! is only used if the resource in the latest build is exactly the same. The cache learns
+//! checksums from the `MANIFEST` files that are requested through it, and drops outdated
+//! entries at the same time. The manifest is cached as well, so resources could be loaded
+//! without network after the first run:
+//!
+//! ```rust,ignore
+//! let mut params = ResourceParams::default();
+//! let cache = Cache::new(Http::new(), CacheParams::new("cache", 256 * 1024 * 1024))?;
+//! params.schemas.add("http", cache);
+//! ```
+//!
+//! Received files are only cached if their CRC32 checksums match the ones in manifest, so
+//! builds that compress or encrypt files for the adapters above should record the checksums
+//! of the bytes as they are served. Cached files are stored with a resource header, and
+//! entries are evicted in least-recently-used order when the size limit is exceeded.
+//! Resources without checksums in manifest are never cached.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use uuid::Uuid;
+
+use crate::errors::*;
+use crate::sched::prelude::LockLatch;
+use crate::utils::hash::FastHashMap;
+
+use super::super::header::{crc32, Format};
+use super::super::manifest::{self, Manifest};
+use super::super::request::{Progress, Response};
+use super::super::url::Url;
+use super::dir::replace;
+use super::{slice, VFS};
+
+pub const FORMAT: Format = Format {
+    tag: *b"CCHE",
+    version: 1,
+    migrations: &[],
+};
+
+/// The setup parameters of `Cache`.
+#[derive(Debug, Clone)]
+pub struct CacheParams {
+    /// The directory to store cached files.
+    pub dir: PathBuf,
+    /// The maximum size of cached files in bytes.
+    pub max_size: u64,
+}
+
+impl CacheParams {
+    pub fn new<T: Into<PathBuf>>(dir: T, max_size: u64) -> Self {
+        CacheParams {
+            dir: dir.into(),
+            max_size,
+        }
+    }
+}
+
+/// A `VFS` adapter that caches the files of underlying `VFS` on local disk.
+///
+/// The lock of entries is never held across file IO, so requests are not blocked by each
+/// other.
+#[derive(Debug, Clone)]
+pub struct Cache<T: VFS> {
+    vfs: Arc<T>,
+    storage: Arc<Mutex<Storage>>,
+}
+
+impl<T: VFS> Cache<T> {
+    /// Creates the cache, and indexes the files that have been cached in the directory.
+    pub fn new(vfs: T, params: CacheParams) -> Result<Self> {
+        let storage = Storage::open(params)?;
+        Ok(Cache {
+            vfs: Arc::new(vfs),
+            storage: Arc::new(Mutex::new(storage)),
+        })
+    }
+
+    /// Learns the checksums of resources in manifest, and removes the outdated entries. It's
+    /// called automatically for manifests that are requested through the cache.
+    pub fn validate(&self, manifest: &Manifest) {
+        let garbage = self.storage.lock().unwrap().validate(manifest);
+        purge(garbage);
+    }
+
+    /// Returns the total size of cached files in bytes.
+    pub fn size(&self) -> u64 {
+        self.storage.lock().unwrap().size
+    }
+
+    fn request_manifest(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        let storage = self.storage.clone();
+        let name = format!("{}-{:08X}", manifest::NAME, crc32(url.as_bytes()));
+        let path = self.storage.lock().unwrap().dir.join(&name);
+
+        let latch = LockLatch::with_callback(move |rsp: Response| {
+            let rsp = match rsp {
+                Ok(bytes) => {
+                    if let Err(err) = replace(&path, &bytes) {
+                        warn!("[Cache] Failed to save {}. {}", path.display(), err);
+                    }
+
+                    Ok(bytes)
+                }
+                Err(err) => match fs::read(&path) {
+                    Ok(bytes) => {
+                        warn!("[Cache] Use the cached manifest instead. {}", err);
+                        Ok(bytes.into_boxed_slice())
+                    }
+                    Err(_) => Err(err),
+                },
+            };
+
+            if let Ok(ref bytes) = rsp {
+                if let Ok(manifest) = Manifest::load_from(&mut &bytes[..]) {
+                    let garbage = storage.lock().unwrap().validate(&manifest);
+                    purge(garbage);
+                }
+            }
+
+            state.set(rsp);
+        });
+
+        self.vfs.request(url, Arc::new(latch));
+    }
+}
+
+impl<T: VFS> VFS for Cache<T> {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        self.request_with_progress(url, Progress::new(), state);
+    }
+
+    fn request_with_progress(
+        &self,
+        url: &Url,
+        progress: Progress,
+        state: Arc<LockLatch<Response>>,
+    ) {
+        let path = url.path();
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        if name == manifest::NAME {
+            return self.request_manifest(url, state);
+        }
+
+        let key = Uuid::parse_str(name).ok().and_then(|uuid| {
+            let storage = self.storage.lock().unwrap();
+            storage.hashes.get(&uuid).map(|&hash| (uuid, hash))
+        });
+
+        let (uuid, hash) = match key {
+            Some(key) => key,
+            None => return self.vfs.request_with_progress(url, progress, state),
+        };
+
+        if let Some(bytes) = load(&self.storage, uuid, hash) {
+            progress.set(bytes.len() as u64);
+            return state.set(Ok(bytes));
+        }
+
+        let storage = self.storage.clone();
+        let latch = LockLatch::with_callback(move |rsp: Response| {
+            if let Ok(ref bytes) = rsp {
+                save(&storage, uuid, hash, bytes);
+            }
+
+            state.set(rsp);
+        });
+
+        self.vfs
+            .request_with_progress(url, progress, Arc::new(latch));
+    }
+
+    /// Slices the cached file if there is one. Otherwise the range is requested from the
+    /// underlying VFS, and it's not cached since it's only a part of file.
+    fn request_range(&self, url: &Url, range: Range<u64>, state: Arc<LockLatch<Response>>) {
+        let path = url.path();
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        let key = Uuid::parse_str(name).ok().and_then(|uuid| {
+            let storage = self.storage.lock().unwrap();
+            storage.hashes.get(&uuid).map(|&hash| (uuid, hash))
+        });
+
+        if let Some((uuid, hash)) = key {
+            if let Some(bytes) = load(&self.storage, uuid, hash) {
+                return state.set(Ok(slice(&bytes, range)));
+            }
+        }
+
+        self.vfs.request_range(url, range, state);
+    }
+
+    fn modified(&self, url: &Url) -> Option<SystemTime> {
+        self.vfs.modified(url)
+    }
+}
+
+/// Reads the cached file, and removes it if it's corrupted.
+fn load(storage: &Mutex<Storage>, uuid: Uuid, hash: u32) -> Option<Box<[u8]>> {
+    let path = storage.lock().unwrap().lookup(uuid, hash)?;
+
+    let bytes = fs::read(&path)
+        .map_err(Error::from)
+        .and_then(|bytes| Ok(FORMAT.read(&bytes)?.into_owned()));
+
+    match bytes {
+        Ok(bytes) => {
+            storage.lock().unwrap().touch(uuid);
+            touch(&path);
+            Some(bytes.into_boxed_slice())
+        }
+        Err(err) => {
+            warn!("[Cache] {} is corrupted. {}", path.display(), err);
+            let garbage = storage.lock().unwrap().remove(uuid, hash);
+            purge(garbage);
+            None
+        }
+    }
+}
+
+/// Writes the file into cache if it matches the checksum in manifest, and evicts the least
+/// recently used entries if the size limit is exceeded.
+fn save(storage: &Mutex<Storage>, uuid: Uuid, hash: u32, bytes: &[u8]) {
+    if crc32(bytes) != hash {
+        warn!(
+            "[Cache] The checksum of {} does not match the manifest, it's not cached.",
+            uuid
+        );
+        return;
+    }
+
+    let (path, max_size) = {
+        let storage = storage.lock().unwrap();
+        (storage.path(uuid, hash), storage.max_size)
+    };
+
+    let file = FORMAT.write(bytes);
+    let len = file.len() as u64;
+    if len > max_size {
+        return;
+    }
+
+    if let Err(err) = replace(&path, &file) {
+        warn!("[Cache] Failed to save {}. {}", path.display(), err);
+        return;
+    }
+
+    let garbage = storage.lock().unwrap().insert(uuid, hash, len);
+    purge(garbage);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    hash: u32,
+    len: u64,
+    tick: u64,
+}
+
+/// The index of cached files. It only does the bookkeeping, and returns the files that
+/// should be removed, so the file IO could be done after the lock is released.
+#[derive(Debug)]
+struct Storage {
+    dir: PathBuf,
+    max_size: u64,
+    size: u64,
+    tick: u64,
+    entries: FastHashMap<Uuid, Entry>,
+    lru: BTreeMap<u64, Uuid>,
+    hashes: FastHashMap<Uuid, u32>,
+}
+
+impl Storage {
+    fn open(params: CacheParams) -> Result<Self> {
+        fs::create_dir_all(&params.dir).map_err(|err| {
+            format_err!("[Cache] Failed to create {}. {}", params.dir.display(), err)
+        })?;
+
+        let mut files = Vec::new();
+        for v in fs::read_dir(&params.dir)? {
+            let v = v?;
+            let path = v.path();
+            let name = v.file_name();
+            let name = name.to_string_lossy();
+
+            if name.ends_with(".tmp") {
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+
+            if let Some((uuid, hash)) = parse(&name) {
+                let metadata = v.metadata()?;
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                files.push((modified, uuid, hash, metadata.len()));
+            }
+        }
+
+        // The recently used files are touched, so their modification times are the order
+        // of LRU.
+        files.sort_by_key(|v| v.0);
+
+        let mut storage = Storage {
+            dir: params.dir,
+            max_size: params.max_size,
+            size: 0,
+            tick: 0,
+            entries: FastHashMap::default(),
+            lru: BTreeMap::new(),
+            hashes: FastHashMap::default(),
+        };
+
+        let mut garbage = Vec::new();
+        for (_, uuid, hash, len) in files {
+            garbage.extend(storage.insert(uuid, hash, len));
+        }
+
+        purge(garbage);
+        Ok(storage)
+    }
+
+    fn path(&self, uuid: Uuid, hash: u32) -> PathBuf {
+        self.dir
+            .join(format!("{:X}.{:08X}", uuid.to_simple(), hash))
+    }
+
+    fn validate(&mut self, manifest: &Manifest) -> Vec<PathBuf> {
+        let mut garbage = Vec::new();
+
+        for v in &manifest.items {
+            match v.hash {
+                Some(hash) => {
+                    self.hashes.insert(v.uuid, hash);
+                }
+                None => {
+                    self.hashes.remove(&v.uuid);
+                }
+            }
+
+            if let Some(&entry) = self.entries.get(&v.uuid) {
+                if v.hash != Some(entry.hash) {
+                    garbage.extend(self.remove(v.uuid, entry.hash));
+                }
+            }
+        }
+
+        garbage
+    }
+
+    /// Returns the path of entry if it has been cached.
+    fn lookup(&self, uuid: Uuid, hash: u32) -> Option<PathBuf> {
+        match self.entries.get(&uuid) {
+            Some(entry) if entry.hash == hash => Some(self.path(uuid, hash)),
+            _ => None,
+        }
+    }
+
+    /// Marks the entry as the most recently used one.
+    fn touch(&mut self, uuid: Uuid) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(&uuid) {
+            self.lru.remove(&entry.tick);
+            self.lru.insert(self.tick, uuid);
+            entry.tick = self.tick;
+        }
+    }
+
+    fn insert(&mut self, uuid: Uuid, hash: u32, len: u64) -> Vec<PathBuf> {
+        let mut garbage = Vec::new();
+
+        // The file of previous entry has been overwritten if the checksums are the same.
+        if let Some(prev) = self.entries.get(&uuid).cloned() {
+            let path = self.remove(uuid, prev.hash);
+            if prev.hash != hash {
+                garbage.extend(path);
+            }
+        }
+
+        self.tick += 1;
+        self.size += len;
+        self.lru.insert(self.tick, uuid);
+
+        let tick = self.tick;
+        self.entries.insert(uuid, Entry { hash, len, tick });
+
+        while self.size > self.max_size {
+            let lru = self.lru.values().next().cloned();
+            match lru.and_then(|uuid| self.entries.get(&uuid).map(|v| (uuid, v.hash))) {
+                Some((uuid, hash)) => garbage.extend(self.remove(uuid, hash)),
+                None => break,
+            }
+        }
+
+        garbage
+    }
+
+    /// Removes the entry if it's the same, and returns its file.
+    fn remove(&mut self, uuid: Uuid, hash: u32) -> Option<PathBuf> {
+        match self.entries.get(&uuid) {
+            Some(entry) if entry.hash == hash => {}
+            _ => return None,
+        }
+
+        let entry = self.entries.remove(&uuid).unwrap();
+        self.lru.remove(&entry.tick);
+        self.size -= entry.len;
+        Some(self.path(uuid, hash))
+    }
+}
+
+/// Parses the file name of entry, e.g. `0123456789ABCDEF0123456789ABCDEF.89ABCDEF`.
+fn parse(name: &str) -> Option<(Uuid, u32)> {
+    let mut iter = name.split('.');
+    let uuid = Uuid::parse_str(iter.next()?).ok()?;
+    let hash = u32::from_str_radix(iter.next()?, 16).ok()?;
+
+    if iter.next().is_some() {
+        return None;
+    }
+
+    Some((uuid, hash))
+}
+
+fn touch(path: &Path) {
+    if let Ok(file) = fs::OpenOptions::new().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+fn purge<T: IntoIterator<Item = PathBuf>>(files: T) {
+    for v in files {
+        let _ = fs::remove_file(v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::super::super::header;
+    use super::super::super::manifest::ManifestItem;
+    use super::super::super::request::Request;
+    use super::*;
+
+    /// A remote VFS that serves files from memory, and counts the requests.
+    #[derive(Debug, Clone, Default)]
+    struct Remote {
+        files: Arc<Mutex<FastHashMap<String, Vec<u8>>>>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl VFS for Remote {
+        fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let files = self.files.lock().unwrap();
+            state.set(match files.get(url.path()) {
+                Some(bytes) => Ok(bytes.clone().into_boxed_slice()),
+                None => Err(format_err!("{} is unreachable.", url)),
+            });
+        }
+    }
+
+    // The size of cached file, which is stored with a header.
+    fn len(n: usize) -> u64 {
+        (header::LEN + n) as u64
+    }
+
+    fn publish(remote: &Remote, files: &[(Uuid, &[u8])]) {
+        let mut manifest = Manifest::new();
+        let mut remote = remote.files.lock().unwrap();
+
+        for &(uuid, bytes) in files {
+            manifest.items.push(ManifestItem {
+                filename: manifest.buf.extend_from_str(format!("{}", uuid)),
+                dependencies: manifest.buf.extend_from_slice::<usize>(&[]),
+                uuid,
+                hash: Some(crc32(bytes)),
+                size: Some(bytes.len() as u64),
+            });
+
+            remote.insert(format!("/{:X}", uuid.to_simple()), bytes.to_vec());
+        }
+
+        remote.insert("/MANIFEST".into(), manifest.to_bytes().unwrap());
+    }
+
+    fn load<T: VFS>(vfs: &T, path: &str) -> Response {
+        let state = Request::latch();
+        vfs.request(
+            &Url::new(format!("http://remote{}", path)).unwrap(),
+            state.clone(),
+        );
+        state.take()
+    }
+
+    fn load_range<T: VFS>(vfs: &T, path: &str, range: Range<u64>) -> Response {
+        let state = Request::latch();
+        vfs.request_range(
+            &Url::new(format!("http://remote{}", path)).unwrap(),
+            range,
+            state.clone(),
+        );
+        state.take()
+    }
+
+    #[test]
+    fn cache() {
+        let dir = crate::utils::testing::temp_path("cache");
+        let (a, b) = (Uuid::from_bytes([1; 16]), Uuid::from_bytes([2; 16]));
+        let path = |uuid: Uuid| format!("/{:X}", uuid.to_simple());
+
+        let remote = Remote::default();
+        publish(&remote, &[(a, &[1; 6]), (b, &[2; 6])]);
+
+        let cache = Cache::new(remote.clone(), CacheParams::new(&dir, len(10))).unwrap();
+        assert!(load(&cache, "/MANIFEST").is_ok());
+        assert_eq!(&load(&cache, &path(a)).unwrap()[..], &[1; 6]);
+        assert_eq!(&load(&cache, &path(a)).unwrap()[..], &[1; 6]);
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 2);
+
+        // Ranges are sliced from the cached files, and requested from remote otherwise.
+        assert_eq!(&load_range(&cache, &path(a), 2..4).unwrap()[..], &[1; 2]);
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(&load_range(&cache, &path(b), 4..8).unwrap()[..], &[2; 2]);
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.size(), len(6));
+
+        // Evicts the least recently used entry.
+        assert_eq!(&load(&cache, &path(b)).unwrap()[..], &[2; 6]);
+        assert_eq!(cache.size(), len(6));
+        assert_eq!(&load(&cache, &path(a)).unwrap()[..], &[1; 6]);
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 5);
+
+        // Loads from disk without network on the next run.
+        remote.files.lock().unwrap().clear();
+        let cache = Cache::new(remote.clone(), CacheParams::new(&dir, len(10))).unwrap();
+        assert_eq!(cache.size(), len(6));
+        assert!(load(&cache, "/MANIFEST").is_ok());
+        assert_eq!(&load(&cache, &path(a)).unwrap()[..], &[1; 6]);
+        assert!(load(&cache, &path(b)).is_err());
+
+        // Drops the outdated entries when the manifest changes.
+        publish(&remote, &[(a, &[3; 4])]);
+        assert!(load(&cache, "/MANIFEST").is_ok());
+        assert_eq!(cache.size(), 0);
+        assert_eq!(&load(&cache, &path(a)).unwrap()[..], &[3; 4]);
+        assert_eq!(cache.size(), len(4));
+
+        // Corrupted files are fetched again.
+        let file = dir.join(format!("{:X}.{:08X}", a.to_simple(), crc32(&[3; 4])));
+        fs::write(&file, b"corrupted").unwrap();
+        let requests = remote.requests.load(Ordering::SeqCst);
+        assert_eq!(&load(&cache, &path(a)).unwrap()[..], &[3; 4]);
+        assert_eq!(&load(&cache, &path(a)).unwrap()[..], &[3; 4]);
+        assert_eq!(remote.requests.load(Ordering::SeqCst), requests + 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn transformed() {
+        let dir = crate::utils::testing::temp_path("cache-t");
+        let uuid = Uuid::from_bytes([1; 16]);
+        let path = format!("/{:X}", uuid.to_simple());
+
+        // The remote serves the bytes that differ from the ones in manifest, which are
+        // never cached.
+        let remote = Remote::default();
+        publish(&remote, &[(uuid, b"plain")]);
+        remote
+            .files
+            .lock()
+            .unwrap()
+            .insert(path.clone(), b"cipher".to_vec());
+
+        let cache = Cache::new(remote.clone(), CacheParams::new(&dir, 1024)).unwrap();
+        assert!(load(&cache, "/MANIFEST").is_ok());
+        assert_eq!(&load(&cache, &path).unwrap()[..], b"cipher");
+        assert_eq!(&load(&cache, &path).unwrap()[..], b"cipher");
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.size(), 0);
+
+        // The encrypted files are cached if the manifest records the checksums of them.
+        publish(&remote, &[(uuid, b"cipher")]);
+        assert!(load(&cache, "/MANIFEST").is_ok());
+        assert_eq!(&load(&cache, &path).unwrap()[..], b"cipher");
+        assert_eq!(&load(&cache, &path).unwrap()[..], b"cipher");
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 5);
+        assert_eq!(cache.size(), len(6));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,306 @@
+//! A `VFS` adapter that decompresses files transparently.
+//!
+//! Compressed files start with a resource header of tag `CMPR`, whose payload is made up of
+//! the compression method, the length of decompressed bytes and the compressed bytes. Files
+//! without the header are passed through as they are, so it's safe to mix compressed and
+//! uncompressed resources in one directory.
+//!
+//! The compressed bytes are standard LZ4 frames, zstd frames or zlib streams, so they could
+//! be produced by other tools as well.
+//!
+//! The adapter could wrap any `VFS`, and it's registered to `SchemaResolver` like others:
+//!
+//! ```rust,ignore
+//! let mut params = ResourceParams::default();
+//! params.schemas.add("file", Compressed::new(Dir::new()));
+//! ```
+//!
+//! Decompression runs on the worker threads of `sched`.
+//!
+//! Streaming is not supported: the whole file has to be decompressed before any range of it
+//! is known, so every `request_range` fetches and decompresses the whole file again.
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use miniz_oxide::{deflate, inflate};
+
+use crate::errors::*;
+use crate::sched::prelude::LockLatch;
+
+use super::super::header::{self, Format};
+use super::super::request::{Progress, Response};
+use super::super::url::Url;
+use super::VFS;
+
+pub const FORMAT: Format = Format {
+    tag: *b"CMPR",
+    version: 1,
+    migrations: &[],
+};
+
+/// The maximum length of decompressed file.
+pub const MAX_LEN: u64 = 1 << 30;
+
+/// The compression methods.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// The LZ4 frame format, which is fast to decompress.
+    Lz4,
+    /// The zlib stream.
+    Deflate,
+    /// The zstd frame format, which has a better compression ratio.
+    Zstd,
+}
+
+impl Method {
+    fn from_u8(v: u8) -> Result<Method> {
+        match v {
+            1 => Ok(Method::Lz4),
+            2 => Ok(Method::Deflate),
+            3 => Ok(Method::Zstd),
+            _ => bail!("[Compressed] Compression method {} is undefined.", v),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Method::Lz4 => 1,
+            Method::Deflate => 2,
+            Method::Zstd => 3,
+        }
+    }
+
+    // The upper bound of compression ratio. A byte of LZ4 block extends the match length by
+    // 255 at most, a deflate block of 258 bytes could be encoded in 2 bits in theory, and a
+    // zstd RLE block of 128KB is encoded in 4 bytes.
+    fn max_ratio(self) -> u64 {
+        match self {
+            Method::Lz4 => 255,
+            Method::Deflate => 1032,
+            Method::Zstd => 32768,
+        }
+    }
+}
+
+/// Compresses the bytes into a file which could be decompressed by `Compressed`.
+pub fn compress(method: Method, bytes: &[u8]) -> Vec<u8> {
+    let mut payload = vec![method.to_u8()];
+    payload.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+
+    match method {
+        Method::Lz4 => {
+            // Writes into memory, which never fails.
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(payload);
+            encoder.write_all(bytes).unwrap();
+            payload = encoder.finish().unwrap();
+        }
+        Method::Deflate => payload.extend_from_slice(&deflate::compress_to_vec_zlib(bytes, 6)),
+        Method::Zstd => {
+            let level = ruzstd::encoding::CompressionLevel::Fastest;
+            ruzstd::encoding::compress(bytes, &mut payload, level);
+        }
+    }
+
+    FORMAT.write(&payload)
+}
+
+/// Decompresses the file if it's compressed, otherwise the bytes are returned as they are.
+pub fn decompress(bytes: Box<[u8]>) -> Result<Box<[u8]>> {
+    if bytes.len() < header::LEN || bytes[0..4] != FORMAT.tag {
+        return Ok(bytes);
+    }
+
+    unpack(&bytes, None)
+}
+
+/// Decompresses the file which is compressed with `method`. Unlike `decompress`, files
+/// without the header are reported as errors.
+pub fn decompress_with(method: Method, bytes: &[u8]) -> Result<Box<[u8]>> {
+    unpack(bytes, Some(method))
+}
+
+fn unpack(bytes: &[u8], expects: Option<Method>) -> Result<Box<[u8]>> {
+    let payload = FORMAT.read(bytes)?;
+    if payload.len() < 9 {
+        bail!("[Compressed] The payload is truncated.");
+    }
+
+    let method = Method::from_u8(payload[0])?;
+    if let Some(v) = expects {
+        if v != method {
+            bail!(
+                "[Compressed] Expects {:?}, but compressed with {:?}.",
+                v,
+                method
+            );
+        }
+    }
+
+    let mut len = [0; 8];
+    len.copy_from_slice(&payload[1..9]);
+    let len = u64::from_le_bytes(len);
+
+    // The length is read from file, which should be checked before any allocation.
+    let src = &payload[9..];
+    if len > MAX_LEN || len > (src.len() as u64 + 1) * method.max_ratio() {
+        bail!(
+            "[Compressed] The length {} of decompressed bytes is invalid.",
+            len
+        );
+    }
+
+    let len = len as usize;
+
+    let buf = match method {
+        Method::Lz4 => read(lz4_flex::frame::FrameDecoder::new(src), len)?,
+        Method::Deflate => inflate::decompress_to_vec_zlib_with_limit(src, len)
+            .map_err(|err| format_err!("[Compressed] Failed to inflate. {:?}", err))?,
+        Method::Zstd => {
+            let decoder = ruzstd::decoding::StreamingDecoder::new(src)
+                .map_err(|err| format_err!("[Compressed] Failed to decode zstd. {}", err))?;
+            read(decoder, len)?
+        }
+    };
+
+    if buf.len() != len {
+        bail!(
+            "[Compressed] Decompressed {} bytes, expects {} bytes.",
+            buf.len(),
+            len
+        );
+    }
+
+    Ok(buf.into_boxed_slice())
+}
+
+// Reads at most one byte more than `len`, so longer outputs are detected without reading
+// them all.
+fn read<R: Read>(decoder: R, len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len);
+    decoder.take(len as u64 + 1).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// A `VFS` adapter that decompresses the files of underlying `VFS`.
+#[derive(Debug, Clone)]
+pub struct Compressed<T: VFS> {
+    vfs: Arc<T>,
+}
+
+impl<T: VFS> Compressed<T> {
+    pub fn new(vfs: T) -> Self {
+        Compressed { vfs: Arc::new(vfs) }
+    }
+}
+
+impl<T: VFS> VFS for Compressed<T> {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        self.request_with_progress(url, Progress::new(), state);
+    }
+
+    fn request_with_progress(
+        &self,
+        url: &Url,
+        progress: Progress,
+        state: Arc<LockLatch<Response>>,
+    ) {
+        let latch = LockLatch::with_callback(move |rsp: Response| {
+            let func = move || state.set(rsp.and_then(decompress));
+
+            if crate::sched::valid() {
+                crate::sched::spawn(func);
+            } else {
+                func();
+            }
+        });
+
+        self.vfs
+            .request_with_progress(url, progress, Arc::new(latch));
+    }
+
+    fn modified(&self, url: &Url) -> Option<SystemTime> {
+        self.vfs.modified(url)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compress() {
+        let mut bytes = Vec::new();
+        for i in 0..4096u32 {
+            bytes.extend_from_slice(&(i % 100).to_le_bytes());
+        }
+        bytes.extend_from_slice(&[7; 300]);
+        bytes.extend_from_slice(b"tail");
+
+        for &method in &[Method::Lz4, Method::Deflate, Method::Zstd] {
+            let file = super::compress(method, &bytes);
+            assert!(file.len() < bytes.len() / 4);
+            assert_eq!(&decompress(file.into()).unwrap()[..], &bytes[..]);
+
+            for v in &[&b""[..], &b"short"[..]] {
+                let file = super::compress(method, v);
+                assert_eq!(&decompress(file.into()).unwrap()[..], *v);
+            }
+        }
+
+        // Uncompressed files are passed through.
+        let raw: Box<[u8]> = b"VTEX raw".to_vec().into();
+        assert_eq!(&decompress(raw.clone()).unwrap()[..], &raw[..]);
+
+        let mut file = super::compress(Method::Lz4, &bytes);
+        file[header::LEN] = 9;
+        assert!(decompress(file.into()).is_err());
+
+        // The method is checked if it's known in advance.
+        let file = super::compress(Method::Zstd, &bytes);
+        assert_eq!(
+            &decompress_with(Method::Zstd, &file).unwrap()[..],
+            &bytes[..]
+        );
+        assert!(decompress_with(Method::Lz4, &file).is_err());
+        assert!(decompress_with(Method::Zstd, &raw).is_err());
+    }
+
+    #[test]
+    fn corrupted() {
+        // Rewrites the length of decompressed bytes, and fixes up the header.
+        let corrupt = |method, len: u64| {
+            let file = super::compress(method, b"hello, hello, hello");
+            let mut payload = FORMAT.read(&file).unwrap().into_owned();
+            payload[1..9].copy_from_slice(&len.to_le_bytes());
+            decompress(FORMAT.write(&payload).into())
+        };
+
+        for &method in &[Method::Lz4, Method::Deflate, Method::Zstd] {
+            assert!(corrupt(method, u64::MAX).is_err());
+            assert!(corrupt(method, MAX_LEN + 1).is_err());
+            assert!(corrupt(method, 1 << 20).is_err());
+            assert!(corrupt(method, 18).is_err());
+            assert_eq!(&corrupt(method, 19).unwrap()[..], b"hello, hello, hello");
+        }
+    }
+
+    #[test]
+    fn vfs() {
+        use super::super::super::request::Request;
+        use super::super::dir::Dir;
+
+        let path = crate::utils::testing::temp_path("compress");
+        std::fs::write(&path, super::compress(Method::Lz4, b"hello, hello, hello")).unwrap();
+
+        let vfs = Compressed::new(Dir::new());
+        let state = Request::latch();
+        let url = Url::new(format!("file://{}", path.display())).unwrap();
+        vfs.request(&url, state.clone());
+
+        assert_eq!(&state.take().unwrap()[..], b"hello, hello, hello");
+        assert!(vfs.modified(&url).is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
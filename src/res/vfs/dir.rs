@@ -1,12 +1,16 @@
 use std::fs;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use crate::sched::prelude::LockLatch;
 
-use super::super::request::Response;
+use super::super::request::{Progress, Response};
 use super::super::url::Url;
-use super::VFS;
+use super::{read_to_end, VFS};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Dir {}
@@ -16,17 +20,136 @@ impl Dir {
         Dir {}
     }
 
-    fn load_from(self, location: &str) -> Response {
+    fn load_from(self, location: &str, progress: &Progress) -> Response {
+        let file = fs::File::open(location)?;
+        let len = file.metadata().map(|v| v.len()).unwrap_or(0);
+        let mut buf = Vec::with_capacity(len as usize);
+        read_to_end(file, &mut buf, progress)?;
+        Ok(buf.into_boxed_slice())
+    }
+
+    fn load_range_from(self, location: &str, range: Range<u64>) -> Response {
         let mut file = fs::File::open(location)?;
         let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
+
+        if range.start < range.end {
+            file.seek(SeekFrom::Start(range.start))?;
+            file.take(range.end - range.start).read_to_end(&mut buf)?;
+        }
+
         Ok(buf.into_boxed_slice())
     }
 }
 
 impl VFS for Dir {
     fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
-        let response = self.load_from(url.path());
+        let response = self.load_from(url.path(), &Progress::new());
+        state.set(response);
+    }
+
+    fn request_with_progress(
+        &self,
+        url: &Url,
+        progress: Progress,
+        state: Arc<LockLatch<Response>>,
+    ) {
+        let response = self.load_from(url.path(), &progress);
+        state.set(response);
+    }
+
+    fn request_range(&self, url: &Url, range: Range<u64>, state: Arc<LockLatch<Response>>) {
+        let response = self.load_range_from(url.path(), range);
         state.set(response);
     }
+
+    fn modified(&self, url: &Url) -> Option<SystemTime> {
+        fs::metadata(url.path()).and_then(|v| v.modified()).ok()
+    }
+
+    fn write(&self, url: &Url, bytes: &[u8]) -> Result<(), failure::Error> {
+        write(Path::new(url.path()), bytes)
+    }
+
+    fn list(&self, url: &Url) -> Result<Vec<String>, failure::Error> {
+        list(Path::new(url.path()))
+    }
+
+    fn delete(&self, url: &Url) -> Result<(), failure::Error> {
+        fs::remove_file(url.path())?;
+        Ok(())
+    }
+}
+
+/// Writes the file, and creates its parent directories if they do not exist. The file is
+/// replaced atomically, so a crash while saving never leaves a partial file behind.
+pub(crate) fn write(path: &Path, bytes: &[u8]) -> Result<(), failure::Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    replace(path, bytes)?;
+    Ok(())
+}
+
+/// Writes into a temporary file first, so readers never see partial files. The temporary
+/// files are unique, since the same file might be saved concurrently.
+///
+/// The temporary file is flushed to disk before it's renamed, so the file is either the old
+/// one or the new one after a power loss. On Unix, the directory is flushed as well, so the
+/// new one is kept.
+pub(crate) fn replace(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_extension(format!("{}.tmp", n));
+
+    let rsp = fs::File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+
+    if rsp.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+
+    rsp?;
+    sync_dir(path)
+}
+
+// Flushes the directory entries, so the rename survives a power loss.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::File::open(parent)?.sync_all(),
+        _ => fs::File::open(".")?.sync_all(),
+    }
+}
+
+// Directories could not be synced on other platforms, so the content of file is durable
+// but the rename itself might be lost after a power loss.
+#[cfg(not(unix))]
+fn sync_dir(_: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Returns the names of files in the directory, or nothing if it does not exist. The
+/// temporary files left by interrupted writes are skipped.
+pub(crate) fn list(path: &Path) -> Result<Vec<String>, failure::Error> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for v in fs::read_dir(path)? {
+        let v = v?;
+        let name = v.file_name().to_string_lossy().into_owned();
+        if v.file_type()?.is_file() && !name.ends_with(".tmp") {
+            names.push(name);
+        }
+    }
+
+    names.sort();
+    Ok(names)
 }
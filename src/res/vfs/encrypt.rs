@@ -0,0 +1,291 @@
+//! A `VFS` adapter that decrypts and authenticates files with ChaCha20-Poly1305.
+//!
+//! Encrypted files start with a resource header of tag `CRPT`, whose payload is made up of
+//! the 12-bytes nonce, the cipher text and the 16-bytes authentication tag. The file name
+//! (e.g. the hex UUID of resource) is used as associated data, so files could not be
+//! swapped with each other either. Files that fail to authenticate are reported as errors
+//! of `Response`, and files without the header are rejected.
+//!
+//! The key is usually supplied by `ResourceParams::key`, which wraps all the schemas with
+//! `Encrypted` when the resource system is set up:
+//!
+//! ```rust,ignore
+//! let mut params = ResourceParams::default();
+//! params.key = Some(Key::new(bytes));
+//! ```
+//!
+//! Since `Encrypted` wraps whatever VFS is registered, e.g. `Compressed`, files are
+//! compressed before encryption, and the decrypted bytes are decompressed here if they are
+//! compressed.
+//!
+//! Streaming is not supported either: the tag authenticates the whole file, so every
+//! `request_range` fetches and decrypts the whole file again.
+//!
+//! The key is never printed, its `Debug` implementation only shows a placeholder.
+
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+use crate::errors::*;
+use crate::sched::prelude::LockLatch;
+
+use super::super::header::Format;
+use super::super::request::{Progress, Response};
+use super::super::url::Url;
+use super::compress;
+use super::VFS;
+
+pub const FORMAT: Format = Format {
+    tag: *b"CRPT",
+    version: 1,
+    migrations: &[],
+};
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// A 256-bits symmetric key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Key(bytes)
+    }
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Key(..)")
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        for v in &mut self.0 {
+            // Prevents the zeroing from being optimized out.
+            unsafe { std::ptr::write_volatile(v, 0) };
+        }
+    }
+}
+
+/// Encrypts the file with name. A random nonce is generated for every file, and stored in
+/// front of the cipher text.
+pub fn encrypt(key: &Key, name: &str, bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0; NONCE_LEN];
+    getrandom::getrandom(&mut nonce)
+        .map_err(|err| format_err!("[Encrypted] Failed to generate nonce. {}", err))?;
+
+    seal(key, name, nonce, bytes)
+}
+
+fn seal(key: &Key, name: &str, nonce: [u8; NONCE_LEN], bytes: &[u8]) -> Result<Vec<u8>> {
+    let msg = Payload {
+        msg: bytes,
+        aad: name.as_bytes(),
+    };
+
+    let text = cipher(key)
+        .encrypt(Nonce::from_slice(&nonce), msg)
+        .map_err(|_| format_err!("[Encrypted] Failed to encrypt {}.", name))?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&text);
+    Ok(FORMAT.write(&payload))
+}
+
+/// Authenticates and decrypts the file with name.
+pub fn decrypt(key: &Key, name: &str, bytes: &[u8]) -> Result<Box<[u8]>> {
+    let payload = FORMAT.read(bytes)?;
+    if payload.len() < NONCE_LEN + TAG_LEN {
+        bail!("[Encrypted] The payload of {} is truncated.", name);
+    }
+
+    // The tag is appended to the cipher text, and verified in constant time.
+    let msg = Payload {
+        msg: &payload[NONCE_LEN..],
+        aad: name.as_bytes(),
+    };
+
+    let text = cipher(key)
+        .decrypt(Nonce::from_slice(&payload[0..NONCE_LEN]), msg)
+        .map_err(|_| {
+            format_err!(
+                "[Encrypted] Failed to authenticate {}, it has been tampered.",
+                name
+            )
+        })?;
+
+    Ok(text.into_boxed_slice())
+}
+
+fn cipher(key: &Key) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key.0))
+}
+
+/// A `VFS` adapter that decrypts the files of underlying `VFS`.
+#[derive(Debug, Clone)]
+pub struct Encrypted {
+    vfs: Arc<dyn VFS>,
+    key: Arc<Key>,
+}
+
+impl Encrypted {
+    pub fn new<T: VFS>(vfs: T, key: Key) -> Self {
+        Encrypted::from_shared(Arc::new(vfs), key)
+    }
+
+    pub(crate) fn from_shared(vfs: Arc<dyn VFS>, key: Key) -> Self {
+        Encrypted {
+            vfs,
+            key: Arc::new(key),
+        }
+    }
+}
+
+impl VFS for Encrypted {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        self.request_with_progress(url, Progress::new(), state);
+    }
+
+    fn request_with_progress(
+        &self,
+        url: &Url,
+        progress: Progress,
+        state: Arc<LockLatch<Response>>,
+    ) {
+        let key = self.key.clone();
+        let path = url.path();
+        let name = path.rsplit('/').next().unwrap_or(path).to_owned();
+
+        let latch = LockLatch::with_callback(move |rsp: Response| {
+            let func = move || {
+                let rsp = rsp.and_then(|bytes| decrypt(&key, &name, &bytes));
+                state.set(rsp.and_then(compress::decompress));
+            };
+
+            if crate::sched::valid() {
+                crate::sched::spawn(func);
+            } else {
+                func();
+            }
+        });
+
+        self.vfs
+            .request_with_progress(url, progress, Arc::new(latch));
+    }
+
+    fn modified(&self, url: &Url) -> Option<SystemTime> {
+        self.vfs.modified(url)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rfc8439() {
+        let mut key = [0; 32];
+        for (i, v) in key.iter_mut().enumerate() {
+            *v = 0x80 + i as u8;
+        }
+
+        let key = Key::new(key);
+        let nonce = [7, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+        let aad = [
+            0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7,
+        ];
+        let text = b"Ladies and Gentlemen of the class of '99: If I could offer you only one \
+                     tip for the future, sunscreen would be it.";
+
+        let msg = Payload {
+            msg: &text[..],
+            aad: &aad,
+        };
+
+        let sealed = cipher(&key)
+            .encrypt(Nonce::from_slice(&nonce), msg)
+            .unwrap();
+        let (text, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+
+        assert_eq!(
+            &text[..],
+            &[
+                0xd3, 0x1a, 0x8d, 0x34, 0x64, 0x8e, 0x60, 0xdb, 0x7b, 0x86, 0xaf, 0xbc, 0x53, 0xef,
+                0x7e, 0xc2, 0xa4, 0xad, 0xed, 0x51, 0x29, 0x6e, 0x08, 0xfe, 0xa9, 0xe2, 0xb5, 0xa7,
+                0x36, 0xee, 0x62, 0xd6, 0x3d, 0xbe, 0xa4, 0x5e, 0x8c, 0xa9, 0x67, 0x12, 0x82, 0xfa,
+                0xfb, 0x69, 0xda, 0x92, 0x72, 0x8b, 0x1a, 0x71, 0xde, 0x0a, 0x9e, 0x06, 0x0b, 0x29,
+                0x05, 0xd6, 0xa5, 0xb6, 0x7e, 0xcd, 0x3b, 0x36, 0x92, 0xdd, 0xbd, 0x7f, 0x2d, 0x77,
+                0x8b, 0x8c, 0x98, 0x03, 0xae, 0xe3, 0x28, 0x09, 0x1b, 0x58, 0xfa, 0xb3, 0x24, 0xe4,
+                0xfa, 0xd6, 0x75, 0x94, 0x55, 0x85, 0x80, 0x8b, 0x48, 0x31, 0xd7, 0xbc, 0x3f, 0xf4,
+                0xde, 0xf0, 0x8e, 0x4b, 0x7a, 0x9d, 0xe5, 0x76, 0xd2, 0x65, 0x86, 0xce, 0xc6, 0x4b,
+                0x61, 0x16
+            ][..]
+        );
+
+        assert_eq!(
+            tag,
+            &[
+                0x1a, 0xe1, 0x0b, 0x59, 0x4f, 0x09, 0xe2, 0x6a, 0x7e, 0x90, 0x2e, 0xcb, 0xd0, 0x60,
+                0x06, 0x91
+            ][..]
+        );
+    }
+
+    #[test]
+    fn decrypt() {
+        let key = Key::new([1; 32]);
+        let bytes = encrypt(&key, "A1B2", b"resource").unwrap();
+        assert_eq!(
+            &super::decrypt(&key, "A1B2", &bytes).unwrap()[..],
+            b"resource"
+        );
+
+        // The nonces are never reused.
+        let other = encrypt(&key, "A1B2", b"resource").unwrap();
+        assert!(bytes != other);
+
+        let bytes = seal(&key, "A1B2", [2; NONCE_LEN], b"resource").unwrap();
+        assert_eq!(
+            &super::decrypt(&key, "A1B2", &bytes).unwrap()[..],
+            b"resource"
+        );
+
+        assert!(super::decrypt(&Key::new([2; 32]), "A1B2", &bytes).is_err());
+        assert!(super::decrypt(&key, "C3D4", &bytes).is_err());
+        assert!(super::decrypt(&key, "A1B2", b"plain text").is_err());
+
+        // Tampers the cipher text, and fixes the checksum of header.
+        let mut payload = bytes[crate::res::header::LEN..].to_vec();
+        payload[NONCE_LEN] ^= 1;
+        let tampered = FORMAT.write(&payload);
+        assert!(super::decrypt(&key, "A1B2", &tampered).is_err());
+
+        assert_eq!(format!("{:?}", key), "Key(..)");
+    }
+
+    #[test]
+    fn compressed() {
+        use super::super::super::request::Request;
+        use super::super::compress::{self, Compressed, Method};
+        use super::super::memory::Memory;
+
+        let key = Key::new([1; 32]);
+        let text = b"hello, hello, hello";
+        let bytes = encrypt(&key, "A1B2", &compress::compress(Method::Lz4, text)).unwrap();
+
+        let memory = Memory::new();
+        memory.insert("A1B2", bytes);
+
+        // The encrypted files pass through `Compressed`, and are decompressed after
+        // decryption.
+        let vfs = Encrypted::new(Compressed::new(memory), key);
+        let state = Request::latch();
+        vfs.request(&Url::new("mem://res/A1B2").unwrap(), state.clone());
+        assert_eq!(&state.take().unwrap()[..], text);
+    }
+}
@@ -0,0 +1,23 @@
+//! Loads files from web servers.
+//!
+//! On web, files are fetched with `XMLHttpRequest` of browser. On native, they are fetched
+//! with a pool of keep-alive connections, which supports both `http` and `https` schemas,
+//! and retries requests after transient failures:
+//!
+//! ```rust,ignore
+//! let mut params = ResourceParams::default();
+//! params.dirs.push("http://localhost:8080/resources/".into());
+//! ```
+//!
+//! `HttpPack` loads files from a pack on server with HTTP range requests, so only the index
+//! and requested files are transferred.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::native::{Http, HttpPack, HttpParams};
+
+#[cfg(target_arch = "wasm32")]
+mod web;
+#[cfg(target_arch = "wasm32")]
+pub use self::web::{Http, HttpPack};
@@ -0,0 +1,414 @@
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::errors::*;
+use crate::sched::prelude::LockLatch;
+
+use super::super::super::request::{Progress, Response};
+use super::super::super::url::Url;
+use super::super::pack::{PackIndex, FOOTER_LEN};
+use super::super::{read_to_end, slice, VFS};
+
+// The `Content-Length` is reported by server, so it only hints the initial capacity of
+// buffer up to this length.
+const MAX_PREALLOC: usize = 1024 * 1024;
+
+/// The setup parameters of `Http`.
+#[derive(Debug, Clone, Copy)]
+pub struct HttpParams {
+    /// The timeout of establishing a connection.
+    pub connect_timeout: Duration,
+    /// The timeout of a whole request, including the reading of response body.
+    pub timeout: Duration,
+    /// The number of retries after transient failures, e.g. IO errors or `5xx` statuses.
+    pub retries: u32,
+    /// The delay before the first retry, which is doubled after every retry.
+    pub retry_delay: Duration,
+    /// The maximum number of idle connections that are kept alive for every host.
+    pub max_idle_connections: usize,
+    /// The number of threads that execute requests.
+    pub threads: usize,
+}
+
+impl Default for HttpParams {
+    fn default() -> Self {
+        HttpParams {
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(60),
+            retries: 3,
+            retry_delay: Duration::from_millis(250),
+            max_idle_connections: 8,
+            threads: 4,
+        }
+    }
+}
+
+/// A `VFS` that fetches files from web servers. Clones of it share the same connection pool.
+///
+/// Requests are blocking, so they are executed on the dedicated threads of `Http` instead of
+/// the workers of `sched`, which would be stalled by network IO and retry delays otherwise.
+#[derive(Debug, Clone)]
+pub struct Http {
+    agent: ureq::Agent,
+    params: HttpParams,
+    workers: Arc<Workers>,
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Http::new()
+    }
+}
+
+impl Http {
+    pub fn new() -> Self {
+        Http::with_params(HttpParams::default())
+    }
+
+    pub fn with_params(params: HttpParams) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(params.connect_timeout)
+            .timeout(params.timeout)
+            .max_idle_connections_per_host(params.max_idle_connections)
+            .build();
+
+        let workers = Arc::new(Workers::new(params.threads));
+        Http {
+            agent,
+            params,
+            workers,
+        }
+    }
+
+    /// Fetches the url, or the byte range of it if specified. Servers that ignore the
+    /// `Range` header are also supported, by slicing the whole content.
+    pub fn fetch(&self, url: &str, range: Option<Range<u64>>) -> Response {
+        match range {
+            Some(ref range) if range.start >= range.end => Ok(Vec::new().into_boxed_slice()),
+            Some(range) => self.fetch_with_retries(url, Some(Span::Range(range)), &Progress::new()),
+            None => self.fetch_with_retries(url, None, &Progress::new()),
+        }
+    }
+
+    /// Fetches the byte range of the url, which is truncated at the end of content instead
+    /// of being treated as an error.
+    pub fn fetch_chunk(&self, url: &str, range: Range<u64>) -> Response {
+        if range.start >= range.end {
+            return Ok(Vec::new().into_boxed_slice());
+        }
+
+        self.fetch_with_retries(url, Some(Span::Chunk(range)), &Progress::new())
+    }
+
+    /// Fetches the last `len` bytes of the url.
+    pub fn fetch_tail(&self, url: &str, len: u64) -> Response {
+        self.fetch_with_retries(url, Some(Span::Tail(len)), &Progress::new())
+    }
+
+    fn fetch_with_retries(&self, url: &str, span: Option<Span>, progress: &Progress) -> Response {
+        let mut delay = self.params.retry_delay;
+        let mut retries = self.params.retries;
+
+        loop {
+            // The bytes received by the failed attempts are discarded.
+            progress.set(0);
+
+            match self.send(url, span.as_ref(), progress) {
+                Err(Failure::Transient(err)) if retries > 0 => {
+                    warn!("[Http] Retry to fetch {} in {:?}. {}", url, delay, err);
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    retries -= 1;
+                }
+                Err(Failure::Transient(err)) | Err(Failure::Fatal(err)) => {
+                    bail!("[Http] Failed to fetch {}. {}", url, err)
+                }
+                Ok(bytes) => return Ok(bytes),
+            }
+        }
+    }
+
+    fn send(
+        &self,
+        url: &str,
+        span: Option<&Span>,
+        progress: &Progress,
+    ) -> std::result::Result<Box<[u8]>, Failure> {
+        let mut request = self.agent.get(url);
+        match span {
+            Some(Span::Range(range)) | Some(Span::Chunk(range)) => {
+                let v = format!("bytes={}-{}", range.start, range.end - 1);
+                request = request.set("Range", &v);
+            }
+            Some(Span::Tail(len)) => request = request.set("Range", &format!("bytes=-{}", len)),
+            None => {}
+        }
+
+        let rsp = match request.call() {
+            Ok(rsp) => rsp,
+            Err(ureq::Error::Status(416, _)) if matches!(span, Some(Span::Chunk(_))) => {
+                // The chunk starts after the end of content.
+                return Ok(Vec::new().into_boxed_slice());
+            }
+            Err(ureq::Error::Status(code, _)) => {
+                let err = format_err!("The server responded with status {}.", code);
+                return Err(if code >= 500 || code == 429 {
+                    Failure::Transient(err)
+                } else {
+                    Failure::Fatal(err)
+                });
+            }
+            Err(ureq::Error::Transport(err)) => {
+                return Err(Failure::Transient(format_err!("{}", err)));
+            }
+        };
+
+        let partial = rsp.status() == 206;
+        let len = rsp
+            .header("Content-Length")
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+
+        // Reads the body to the end, so the connection could be reused.
+        let mut buf = Vec::with_capacity(len.min(MAX_PREALLOC));
+        read_to_end(rsp.into_reader(), &mut buf, progress)
+            .map_err(|err| Failure::Transient(err.into()))?;
+
+        let expects = match span {
+            Some(Span::Range(range)) if partial => range.end - range.start,
+            Some(Span::Tail(_)) if partial => buf.len() as u64,
+            Some(Span::Chunk(range)) if partial => (buf.len() as u64).min(range.end - range.start),
+            Some(Span::Chunk(range)) => {
+                buf = slice(&buf, range.clone()).into_vec();
+                buf.len() as u64
+            }
+            Some(Span::Range(range)) => {
+                // The server ignores the `Range` header, and responds the whole content.
+                if range.end > buf.len() as u64 {
+                    let err = format_err!("The range {:?} is out of bounds.", range);
+                    return Err(Failure::Fatal(err));
+                }
+
+                buf = buf[range.start as usize..range.end as usize].to_vec();
+                range.end - range.start
+            }
+            Some(Span::Tail(len)) => {
+                let start = buf.len().saturating_sub(*len as usize);
+                buf = buf[start..].to_vec();
+                buf.len() as u64
+            }
+            None => buf.len() as u64,
+        };
+
+        if buf.len() as u64 != expects {
+            let err = format_err!("Received {} bytes, expects {} bytes.", buf.len(), expects);
+            return Err(Failure::Fatal(err));
+        }
+
+        Ok(buf.into_boxed_slice())
+    }
+}
+
+/// The part of content to fetch.
+enum Span {
+    Range(Range<u64>),
+    Tail(u64),
+    /// The range that might exceed the end of content.
+    Chunk(Range<u64>),
+}
+
+enum Failure {
+    /// Failures that might be recovered by retrying, e.g. timeouts.
+    Transient(failure::Error),
+    Fatal(failure::Error),
+}
+
+impl VFS for Http {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        let http = self.clone();
+        let url = url.to_string();
+        self.workers.spawn(state, move || http.fetch(&url, None));
+    }
+
+    fn request_with_progress(
+        &self,
+        url: &Url,
+        progress: Progress,
+        state: Arc<LockLatch<Response>>,
+    ) {
+        let http = self.clone();
+        let url = url.to_string();
+        self.workers.spawn(state, move || {
+            http.fetch_with_retries(&url, None, &progress)
+        });
+    }
+
+    fn request_range(&self, url: &Url, range: Range<u64>, state: Arc<LockLatch<Response>>) {
+        let http = self.clone();
+        let url = url.to_string();
+        self.workers
+            .spawn(state, move || http.fetch_chunk(&url, range));
+    }
+}
+
+/// A `VFS` that fetches files from a pack on web server with HTTP range requests. The
+/// footer and index of pack are fetched when the first file is requested.
+#[derive(Debug, Clone)]
+pub struct HttpPack {
+    http: Http,
+    url: String,
+    index: Arc<Mutex<Option<Arc<PackIndex>>>>,
+}
+
+impl HttpPack {
+    pub fn new<T: Into<String>>(url: T) -> Self {
+        HttpPack::with_http(url, Http::new())
+    }
+
+    /// Creates a pack that shares the connection pool with `Http`.
+    pub fn with_http<T: Into<String>>(url: T, http: Http) -> Self {
+        HttpPack {
+            http,
+            url: url.into(),
+            index: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn index(&self) -> Result<Arc<PackIndex>> {
+        if let Some(ref index) = *self.index.lock().unwrap() {
+            return Ok(index.clone());
+        }
+
+        // The lock is released while fetching, so the first few requests might fetch the
+        // index concurrently, and the one that finishes first is kept.
+        let footer = self.http.fetch_tail(&self.url, FOOTER_LEN as u64)?;
+        let (offset, len) = PackIndex::parse_footer(&footer)?;
+        let bytes = self.http.fetch(&self.url, Some(span(offset, len)?))?;
+        let v = Arc::new(PackIndex::parse(&bytes, offset)?);
+
+        let mut index = self.index.lock().unwrap();
+        Ok(index.get_or_insert(v).clone())
+    }
+
+    fn load_from(&self, path: &str) -> Response {
+        let index = self
+            .index()
+            .map_err(|err| format_err!("[HttpPack] Failed to load {}. {}", self.url, err))?;
+
+        let entry = index.find(path)?;
+        let range = span(entry.offset, entry.len)?;
+        entry.decompress(self.http.fetch(&self.url, Some(range))?)
+    }
+}
+
+// The offsets and lengths are read from server, which might overflow if corrupted.
+fn span(offset: u64, len: u64) -> Result<Range<u64>> {
+    match offset.checked_add(len) {
+        Some(end) => Ok(offset..end),
+        None => bail!("[HttpPack] The range {}+{} is out of bounds.", offset, len),
+    }
+}
+
+impl VFS for HttpPack {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        let pack = self.clone();
+        let path = url.path().to_owned();
+        self.http
+            .workers
+            .spawn(state, move || pack.load_from(&path));
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The threads that execute blocking requests. They are spawned with the first request,
+/// and exit once all the clones of `Http` are dropped.
+#[derive(Debug)]
+struct Workers {
+    threads: usize,
+    sender: Mutex<Option<mpsc::Sender<Job>>>,
+}
+
+impl Workers {
+    fn new(threads: usize) -> Self {
+        Workers {
+            threads: threads.max(1),
+            sender: Mutex::new(None),
+        }
+    }
+
+    /// Executes the request, and sets its response to `state`. An error is set instead if
+    /// the request panics, so it never hangs.
+    fn spawn<F>(&self, state: Arc<LockLatch<Response>>, func: F)
+    where
+        F: FnOnce() -> Response + Send + 'static,
+    {
+        let job = move || {
+            let rsp = panic::catch_unwind(AssertUnwindSafe(func))
+                .unwrap_or_else(|_| Err(format_err!("[Http] The request panicked.")));
+            state.set(rsp);
+        };
+
+        let mut sender = self.sender.lock().unwrap();
+        let sender = sender.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel::<Job>();
+            let receiver = Arc::new(Mutex::new(receiver));
+
+            for i in 0..self.threads {
+                let receiver = receiver.clone();
+                thread::Builder::new()
+                    .name(format!("crayon-http-{}", i))
+                    .spawn(move || loop {
+                        let job = match receiver.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            Err(_) => return,
+                        };
+
+                        // Keeps the thread alive even if the job panics.
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    })
+                    .unwrap();
+            }
+
+            sender
+        });
+
+        sender.send(Box::new(job)).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sched::prelude::LatchProbe;
+
+    use super::super::super::super::request::Request;
+    use super::*;
+
+    fn wait(state: &LockLatch<Response>) -> Response {
+        while !state.is_set() {
+            thread::yield_now();
+        }
+
+        state.take()
+    }
+
+    #[test]
+    fn panicked() {
+        let workers = Workers::new(1);
+
+        // The request fails instead of hanging, and the thread is kept alive.
+        let state = Request::latch();
+        workers.spawn(state.clone(), || panic!("request"));
+        assert!(wait(&state).is_err());
+
+        let state = Request::latch();
+        workers.spawn(state.clone(), || Ok(vec![1].into_boxed_slice()));
+        assert_eq!(&wait(&state).unwrap()[..], &[1]);
+
+        assert_eq!(span(2, 3).unwrap(), 2..5);
+        assert!(span(u64::max_value(), 1).is_err());
+    }
+}
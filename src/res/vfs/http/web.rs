@@ -0,0 +1,298 @@
+use std::cell::RefCell;
+use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{XmlHttpRequest, XmlHttpRequestResponseType};
+
+use crate::sched::prelude::LockLatch;
+
+use super::super::super::request::Response;
+use super::super::super::url::Url;
+use super::super::pack::{PackIndex, FOOTER_LEN};
+use super::super::{slice, VFS};
+
+/// The timeout of a whole request in milliseconds.
+const TIMEOUT: u32 = 60_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Http {}
+
+impl Http {
+    pub fn new() -> Self {
+        Http {}
+    }
+}
+
+impl VFS for Http {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        let location = url.to_string();
+
+        send(url, None, move |rsp| {
+            let rsp = match rsp {
+                Ok((status, bytes)) if (200..300).contains(&status) => Ok(bytes),
+                Ok((status, _)) => Err(format_err!("The server responded with status {}.", status)),
+                Err(err) => Err(err),
+            };
+
+            state.set(
+                rsp.map_err(|err| format_err!("[Http] Failed to fetch {}. {}", location, err)),
+            );
+        });
+    }
+
+    fn request_range(&self, url: &Url, range: Range<u64>, state: Arc<LockLatch<Response>>) {
+        if range.start >= range.end {
+            return state.set(Ok(Vec::new().into_boxed_slice()));
+        }
+
+        let header = format!("bytes={}-{}", range.start, range.end - 1);
+        let location = url.to_string();
+
+        send(url, Some(header), move |rsp| {
+            let rsp = match rsp {
+                Ok((206, bytes)) => Ok(bytes),
+                // The server ignores the `Range` header, and responds the whole content.
+                Ok((200, bytes)) => Ok(slice(&bytes, range)),
+                // The chunk starts after the end of content.
+                Ok((416, _)) => Ok(Vec::new().into_boxed_slice()),
+                Ok((status, _)) => Err(format_err!("The server responded with status {}.", status)),
+                Err(err) => Err(err),
+            };
+
+            state.set(
+                rsp.map_err(|err| format_err!("[Http] Failed to fetch {}. {}", location, err)),
+            );
+        });
+    }
+}
+
+/// A `VFS` that fetches files from a pack on web server with HTTP range requests. The
+/// footer and index of pack are fetched when the first file is requested.
+#[derive(Clone)]
+pub struct HttpPack {
+    url: String,
+    state: Arc<Mutex<PackState>>,
+}
+
+enum PackState {
+    Unloaded,
+    Loading(Vec<(String, Arc<LockLatch<Response>>)>),
+    Loaded(Arc<PackIndex>),
+    Failed(String),
+}
+
+impl std::fmt::Debug for HttpPack {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "HttpPack({})", self.url)
+    }
+}
+
+impl HttpPack {
+    pub fn new<T: Into<String>>(url: T) -> Self {
+        HttpPack {
+            url: url.into(),
+            state: Arc::new(Mutex::new(PackState::Unloaded)),
+        }
+    }
+
+    fn load_index(&self) {
+        let clone = self.clone();
+
+        fetch(&self.url, Span::Tail(FOOTER_LEN as u64), move |rsp| {
+            let footer = match rsp.and_then(|v| PackIndex::parse_footer(&v)) {
+                Ok(footer) => footer,
+                Err(err) => return clone.on_index_loaded(Err(err)),
+            };
+
+            let (offset, len) = footer;
+            let pack = clone.clone();
+
+            fetch(&clone.url, Span::Range(offset..offset + len), move |rsp| {
+                pack.on_index_loaded(rsp.and_then(|v| PackIndex::parse(&v, offset)));
+            });
+        });
+    }
+
+    fn on_index_loaded(&self, index: Result<PackIndex, failure::Error>) {
+        let next = match index {
+            Ok(index) => PackState::Loaded(Arc::new(index)),
+            Err(err) => PackState::Failed(format!("{}", err)),
+        };
+
+        let prev = mem::replace(&mut *self.state.lock().unwrap(), next);
+        if let PackState::Loading(requests) = prev {
+            for (path, state) in requests {
+                self.load_from(&path, state);
+            }
+        }
+    }
+
+    fn load_from(&self, path: &str, state: Arc<LockLatch<Response>>) {
+        let mut pack = self.state.lock().unwrap();
+
+        let index = match *pack {
+            PackState::Loaded(ref index) => index.clone(),
+            PackState::Failed(ref err) => {
+                let err = format_err!("[HttpPack] Failed to load {}. {}", self.url, err);
+                return state.set(Err(err));
+            }
+            PackState::Loading(ref mut requests) => {
+                requests.push((path.to_owned(), state));
+                return;
+            }
+            PackState::Unloaded => {
+                *pack = PackState::Loading(vec![(path.to_owned(), state)]);
+                drop(pack);
+                return self.load_index();
+            }
+        };
+
+        drop(pack);
+
+        let entry = match index.find(path) {
+            Ok(entry) => entry,
+            Err(err) => return state.set(Err(err)),
+        };
+
+        if entry.len == 0 {
+            return state.set(Ok(Vec::new().into_boxed_slice()));
+        }
+
+        let range = entry.offset..entry.offset + entry.len;
+        let entry = entry.clone();
+        fetch(&self.url, Span::Range(range), move |rsp| {
+            state.set(rsp.and_then(|v| entry.decompress(v)));
+        });
+    }
+}
+
+impl VFS for HttpPack {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        self.load_from(url.path(), state);
+    }
+}
+
+/// The part of content to fetch.
+enum Span {
+    Range(Range<u64>),
+    Tail(u64),
+}
+
+/// Fetches the byte range of url. The partial content is expected, but the whole content
+/// from servers that ignore the `Range` header is sliced as well.
+fn fetch<T>(url: &str, span: Span, func: T)
+where
+    T: FnOnce(Response) + 'static,
+{
+    let location = url.to_owned();
+    let header = match span {
+        Span::Range(ref range) => format!("bytes={}-{}", range.start, range.end - 1),
+        Span::Tail(len) => format!("bytes=-{}", len),
+    };
+
+    send(url, Some(header), move |rsp| {
+        let rsp = rsp.and_then(|(status, bytes)| match (status, span) {
+            (206, Span::Range(range)) if bytes.len() as u64 != range.end - range.start => {
+                Err(format_err!(
+                    "Received {} bytes, expects {} bytes.",
+                    bytes.len(),
+                    range.end - range.start
+                ))
+            }
+            (206, _) => Ok(bytes),
+            (200, Span::Range(range)) if range.end > bytes.len() as u64 => {
+                Err(format_err!("The range {:?} is out of bounds.", range))
+            }
+            (200, Span::Range(range)) => Ok(slice(&bytes, range)),
+            (200, Span::Tail(len)) => {
+                let start = bytes.len().saturating_sub(len as usize);
+                Ok(bytes[start..].into())
+            }
+            (status, _) => Err(format_err!("The server responded with status {}.", status)),
+        });
+
+        func(rsp.map_err(|err| format_err!("[HttpPack] Failed to fetch {}. {}", location, err)));
+    });
+}
+
+/// A in-flight request, which keeps its listeners alive until it's completed.
+struct Pending {
+    inner: XmlHttpRequest,
+    listeners: Vec<(&'static str, Closure<dyn FnMut()>)>,
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        let target: &web_sys::EventTarget = self.inner.as_ref();
+        for (event, closure) in &self.listeners {
+            let func = closure.as_ref().unchecked_ref();
+            let _ = target.remove_event_listener_with_callback(event, func);
+        }
+    }
+}
+
+/// Sends the request, and hands the status and body of response over to `func`. The
+/// request is resolved on all outcomes, including network errors, timeouts and aborts.
+fn send<T>(url: &str, range: Option<String>, func: T)
+where
+    T: FnOnce(Result<(u16, Box<[u8]>), failure::Error>) + 'static,
+{
+    let inner = XmlHttpRequest::new().unwrap();
+    let pending = Rc::new(RefCell::new(None));
+    let func = Rc::new(RefCell::new(Some(func)));
+
+    // Resolves the request once, and releases the listeners.
+    let resolve = {
+        let (inner, pending) = (inner.clone(), pending.clone());
+        move |event: &str| {
+            let rsp = if event == "load" {
+                let array = Uint8Array::new(&inner.response().unwrap());
+
+                // FIXME: https://github.com/rustwasm/wasm-bindgen/issues/811
+                let mut bytes = Vec::new();
+                array.for_each(&mut |v, _, _| bytes.push(v));
+                Ok((inner.status().unwrap_or(0), bytes.into_boxed_slice()))
+            } else {
+                Err(format_err!("The request is failed with {} event.", event))
+            };
+
+            if let Some(func) = func.borrow_mut().take() {
+                func(rsp);
+            }
+
+            // The closures themselves are destroyed by wasm-bindgen once this invocation
+            // returns.
+            mem::drop(pending.borrow_mut().take());
+        }
+    };
+
+    let resolve = Rc::new(resolve);
+    let target: &web_sys::EventTarget = inner.as_ref();
+    let mut listeners = Vec::new();
+
+    for &event in &["load", "error", "timeout", "abort"] {
+        let resolve = resolve.clone();
+        let closure = Closure::wrap(Box::new(move || resolve(event)) as Box<dyn FnMut()>);
+        target
+            .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            .unwrap();
+
+        listeners.push((event, closure));
+    }
+
+    inner.set_response_type(XmlHttpRequestResponseType::Arraybuffer);
+    inner.set_timeout(TIMEOUT);
+    inner.open_with_async("Get", url, true).unwrap();
+
+    if let Some(range) = range {
+        inner.set_request_header("Range", &range).unwrap();
+    }
+
+    inner.send().unwrap();
+    *pending.borrow_mut() = Some(Pending { inner, listeners });
+}
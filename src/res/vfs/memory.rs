@@ -0,0 +1,215 @@
+//! Serves files from memory.
+//!
+//! The `Memory` VFS keeps byte blobs under paths, so resources could be tested without
+//! touching disk, together with `ManifestBuilder`:
+//!
+//! ```rust,ignore
+//! let mut builder = ManifestBuilder::new();
+//! builder.add("crate.png", uuid, &bytes);
+//!
+//! let memory = Memory::new();
+//! memory.insert_manifest(&builder)?;
+//! memory.insert_resource(uuid, bytes);
+//!
+//! params.schemas.add("mem", memory);
+//! params.dirs.push("mem://res/".into());
+//! ```
+//!
+//! It could also serve a whole directory of resources that is embedded into the binary
+//! for single-file distribution, see `Memory::from_static` and `crayon_tools::embed`.
+
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use uuid::Uuid;
+
+use crate::sched::prelude::LockLatch;
+use crate::utils::hash::FastHashMap;
+
+use super::super::manifest::{ManifestBuilder, NAME};
+use super::super::request::Response;
+use super::super::url::Url;
+use super::{components, slice, VFS};
+
+/// A writable `VFS` that serves files from memory. The host of url is ignored, and clones
+/// of it share the same files.
+#[derive(Default, Clone)]
+pub struct Memory {
+    files: Arc<RwLock<FastHashMap<String, Cow<'static, [u8]>>>>,
+}
+
+impl std::fmt::Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Memory({} files)", self.files.read().unwrap().len())
+    }
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a VFS with static files, e.g. the ones embedded by `crayon_tools::embed`.
+    pub fn from_static(files: &[(&str, &'static [u8])]) -> Self {
+        let memory = Memory::new();
+        for &(path, bytes) in files {
+            memory.insert(path, bytes);
+        }
+
+        memory
+    }
+
+    /// Inserts the file at `path`, and replaces the existing one.
+    pub fn insert<T1, T2>(&self, path: T1, bytes: T2)
+    where
+        T1: AsRef<str>,
+        T2: Into<Cow<'static, [u8]>>,
+    {
+        let key = normalize(path.as_ref());
+        self.files.write().unwrap().insert(key, bytes.into());
+    }
+
+    /// Inserts the resource file at the path where the manifest locates it.
+    #[inline]
+    pub fn insert_resource<T: Into<Cow<'static, [u8]>>>(&self, uuid: Uuid, bytes: T) {
+        self.insert(format!("{:X}", uuid.to_simple()), bytes);
+    }
+
+    /// Builds the manifest, and inserts it as the `MANIFEST` file.
+    pub fn insert_manifest(&self, builder: &ManifestBuilder) -> Result<(), failure::Error> {
+        self.insert(NAME, builder.to_bytes()?);
+        Ok(())
+    }
+
+    /// Removes the file at `path`, and returns false if it does not exist.
+    #[inline]
+    pub fn remove<T: AsRef<str>>(&self, path: T) -> bool {
+        let key = normalize(path.as_ref());
+        self.files.write().unwrap().remove(&key).is_some()
+    }
+
+    /// Checks if there is a file at `path`.
+    #[inline]
+    pub fn contains<T: AsRef<str>>(&self, path: T) -> bool {
+        let key = normalize(path.as_ref());
+        self.files.read().unwrap().contains_key(&key)
+    }
+
+    fn load_from(&self, url: &Url, range: Option<Range<u64>>) -> Response {
+        let key = components(url.path())?.join("/");
+        let files = self.files.read().unwrap();
+        let bytes = files
+            .get(&key)
+            .ok_or_else(|| format_err!("[Memory] Could not found {}.", url))?;
+
+        match range {
+            Some(range) => Ok(slice(bytes, range)),
+            None => Ok(bytes.as_ref().into()),
+        }
+    }
+}
+
+// Invalid paths are kept as they are, which could not be requested anyway.
+fn normalize(path: &str) -> String {
+    match components(path) {
+        Ok(v) => v.join("/"),
+        Err(_) => path.to_owned(),
+    }
+}
+
+impl VFS for Memory {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        state.set(self.load_from(url, None));
+    }
+
+    fn request_range(&self, url: &Url, range: Range<u64>, state: Arc<LockLatch<Response>>) {
+        state.set(self.load_from(url, Some(range)));
+    }
+
+    fn write(&self, url: &Url, bytes: &[u8]) -> Result<(), failure::Error> {
+        let key = components(url.path())?.join("/");
+        self.files
+            .write()
+            .unwrap()
+            .insert(key, bytes.to_vec().into());
+        Ok(())
+    }
+
+    fn list(&self, url: &Url) -> Result<Vec<String>, failure::Error> {
+        let mut prefix = components(url.path())?.join("/");
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+
+        let files = self.files.read().unwrap();
+        let mut names: Vec<_> = files
+            .keys()
+            .filter_map(|v| v.get(prefix.len()..).filter(|_| v.starts_with(&prefix)))
+            .filter(|v| !v.contains('/'))
+            .map(|v| v.to_owned())
+            .collect();
+
+        names.sort();
+        Ok(names)
+    }
+
+    fn delete(&self, url: &Url) -> Result<(), failure::Error> {
+        let key = components(url.path())?.join("/");
+        if self.files.write().unwrap().remove(&key).is_none() {
+            bail!("[Memory] Could not found {}.", url);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::manifest::ManfiestResolver;
+    use super::super::super::request::Request;
+    use super::*;
+
+    fn load(vfs: &Memory, url: &str) -> Response {
+        let state = Request::latch();
+        vfs.request(&Url::new(url).unwrap(), state.clone());
+        state.take()
+    }
+
+    #[test]
+    fn memory() {
+        let uuid = Uuid::from_bytes([1; 16]);
+        let mut builder = ManifestBuilder::new();
+        builder.add("crate.png", uuid, b"png");
+
+        let memory = Memory::new();
+        memory.insert_manifest(&builder).unwrap();
+        memory.insert_resource(uuid, &b"png"[..]);
+
+        let mut resolver = ManfiestResolver::new();
+        let bytes = load(&memory, "mem://res/MANIFEST").unwrap();
+        resolver.add("mem://res/", &mut &bytes[..]).unwrap();
+
+        let url = resolver.resolve(uuid).unwrap();
+        assert_eq!(&load(&memory, &url).unwrap()[..], b"png");
+        assert!(load(&memory, "mem://res/unknown").is_err());
+
+        let url = Url::new("mem://res/saves/0.sav").unwrap();
+        memory.write(&url, b"0123").unwrap();
+        assert!(memory.contains("/saves/0.sav"));
+
+        let state = Request::latch();
+        memory.request_range(&url, 2..8, state.clone());
+        assert_eq!(&state.take().unwrap()[..], b"23");
+
+        let root = Url::new("mem://res/").unwrap();
+        let names = memory.list(&root).unwrap();
+        assert_eq!(names, vec![format!("{:X}", uuid.to_simple()), NAME.into()]);
+
+        let dir = Url::new("mem://res/saves").unwrap();
+        assert_eq!(memory.list(&dir).unwrap(), vec!["0.sav"]);
+        memory.delete(&url).unwrap();
+        assert!(memory.delete(&url).is_err());
+        assert!(memory.list(&dir).unwrap().is_empty());
+    }
+}
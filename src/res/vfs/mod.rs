@@ -1,37 +1,180 @@
 #[cfg(not(target_arch = "wasm32"))]
+pub mod cache;
+pub mod compress;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod dir;
-#[cfg(target_arch = "wasm32")]
+pub mod encrypt;
 pub mod http;
+pub mod memory;
+pub mod pack;
+pub mod user;
 
+use std::io::{ErrorKind, Read};
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use crate::sched::prelude::LockLatch;
 use crate::utils::hash::FastHashMap;
 
-use super::request::Response;
+use super::request::{Progress, Response};
 use super::url::Url;
 
 pub trait VFS: std::fmt::Debug + Send + Sync + 'static {
     fn request(&self, url: &Url, state: Arc<LockLatch<Response>>);
+
+    /// Requests the file, and reports the received bytes to `progress` while reading it.
+    ///
+    /// By default, the progress is set once the whole file is received. VFS that reads
+    /// files in chunks, e.g. `Dir` and `Http`, should override it.
+    fn request_with_progress(
+        &self,
+        url: &Url,
+        progress: Progress,
+        state: Arc<LockLatch<Response>>,
+    ) {
+        let latch = LockLatch::with_callback(move |rsp: Response| {
+            if let Ok(ref bytes) = rsp {
+                progress.set(bytes.len() as u64);
+            }
+
+            state.set(rsp);
+        });
+
+        self.request(url, Arc::new(latch));
+    }
+
+    /// Reads the bytes of file in `range`, which is used to stream large files in chunks.
+    /// The response is shorter than the range if it exceeds the end of file, and empty if
+    /// the range starts after the end.
+    ///
+    /// By default, the whole file is requested and sliced. VFS that supports partial
+    /// reads, e.g. `Dir`, `Pack` and `Http`, should override it. The adapters that
+    /// transform whole files, e.g. `Encrypted` and `Compressed`, fall back to the default,
+    /// so streaming through them fetches the whole file for every chunk.
+    fn request_range(&self, url: &Url, range: Range<u64>, state: Arc<LockLatch<Response>>) {
+        let latch = LockLatch::with_callback(move |rsp: Response| {
+            state.set(rsp.map(|bytes| slice(&bytes, range)));
+        });
+
+        self.request(url, Arc::new(latch));
+    }
+
+    /// Returns the last modification time of file, which is used to hot-reload resources.
+    /// `None` should be returned if the VFS does not support it.
+    fn modified(&self, _: &Url) -> Option<SystemTime> {
+        None
+    }
+
+    /// Writes the bytes into file, which is created if it does not exist. Most of VFS are
+    /// read-only, so an error is returned by default.
+    fn write(&self, url: &Url, _: &[u8]) -> Result<(), failure::Error> {
+        bail!("[VFS] Could not write {}, it's read-only.", url)
+    }
+
+    /// Returns the names of files in the directory, in lexical order.
+    fn list(&self, url: &Url) -> Result<Vec<String>, failure::Error> {
+        bail!("[VFS] Could not list {}, it's not supported.", url)
+    }
+
+    /// Deletes the file.
+    fn delete(&self, url: &Url) -> Result<(), failure::Error> {
+        bail!("[VFS] Could not delete {}, it's read-only.", url)
+    }
+}
+
+/// Returns the bytes in `range`, which is clamped to the bounds of `bytes`.
+pub(crate) fn slice(bytes: &[u8], range: Range<u64>) -> Box<[u8]> {
+    let len = bytes.len() as u64;
+    let start = range.start.min(len);
+    let end = range.end.min(len).max(start);
+    bytes[start as usize..end as usize].into()
 }
 
+/// Reads all the bytes of `reader` in chunks, and reports the received ones to `progress`.
+pub(crate) fn read_to_end<R: Read>(
+    mut reader: R,
+    buf: &mut Vec<u8>,
+    progress: &Progress,
+) -> std::io::Result<()> {
+    let mut chunk = [0; 16 * 1024];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(n) => {
+                buf.extend_from_slice(&chunk[..n]);
+                progress.add(n as u64);
+            }
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Returns the components of the path of url, and rejects the ones that might escape
+/// from the root of VFS, e.g. `..`.
+pub(crate) fn components(path: &str) -> Result<Vec<&str>, failure::Error> {
+    let mut components = Vec::new();
+    for v in path.split('/') {
+        match v {
+            "" | "." => {}
+            ".." => bail!("[VFS] The path {} should not contains '..'.", path),
+            _ => components.push(v),
+        }
+    }
+
+    Ok(components)
+}
+
+/// The default maximum number of in-flight requests of every schema.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
 #[derive(Debug, Default, Clone)]
 pub struct SchemaResolver {
     schemas: FastHashMap<String, Arc<VFS>>,
+    concurrency: FastHashMap<String, usize>,
 }
 
 impl SchemaResolver {
     pub fn new() -> Self {
         SchemaResolver {
             schemas: FastHashMap::default(),
+            concurrency: FastHashMap::default(),
         }
     }
 
+    /// Sets the maximum number of in-flight requests of schema, the pending requests are
+    /// sent to VFS in the order of priority once others are completed.
+    #[inline]
+    pub fn set_concurrency<T: Into<String>>(&mut self, schema: T, limit: usize) {
+        self.concurrency.insert(schema.into(), limit.max(1));
+    }
+
+    /// Returns the maximum number of in-flight requests of schema.
+    #[inline]
+    pub fn concurrency<T: AsRef<str>>(&self, schema: T) -> usize {
+        self.concurrency
+            .get(schema.as_ref())
+            .cloned()
+            .unwrap_or(DEFAULT_CONCURRENCY)
+    }
+
     #[inline]
     pub fn add<T1: Into<String>, T2: VFS + 'static>(&mut self, schema: T1, vfs: T2) {
         self.schemas.insert(schema.into(), Arc::new(vfs));
     }
 
+    /// Replaces every registered `VFS` with the one returned by `func`, which is used to
+    /// install adapters like `Encrypted` on all the schemas. The `user` schema is skipped,
+    /// since its files are written at runtime instead of built by tools.
+    pub(crate) fn wrap<F: Fn(Arc<dyn VFS>) -> Arc<dyn VFS>>(&mut self, func: F) {
+        for (k, v) in &mut self.schemas {
+            if k != self::user::SCHEMA {
+                *v = func(v.clone());
+            }
+        }
+    }
+
     #[inline]
     pub fn locate<T1: AsRef<str>>(&self, schema: T1) -> Result<Arc<VFS>, failure::Error> {
         let schema = schema.as_ref();
@@ -0,0 +1,495 @@
+//! Packs all the resources of a build into one archive file.
+//!
+//! A pack file is made up of the contents of files, followed by an index and a footer:
+//!
+//! ```text
+//! [0..]          The contents of files, which are placed one after another.
+//! [index..]      The bincode serialized `Vec<PackEntry>`.
+//! [-24..-16]     The offset of index in little-endian.
+//! [-16..-8]      The length of index in little-endian.
+//! [-8..]         The MAGIC number.
+//! ```
+//!
+//! Files in pack are identified by their names, which are the hex UUIDs of resources and
+//! the name of `MANIFEST`. So the pack could be mounted like a directory:
+//!
+//! ```rust,ignore
+//! let mut params = ResourceParams::default();
+//! params.schemas.add("pack", Pack::open("resources.pack")?);
+//! params.dirs.push("pack://resources/".into());
+//! ```
+//!
+//! The footer and index are read once when the pack is opened, and the content of a file is
+//! read with seek on native, or fetched with a HTTP range request on web (see `HttpPack`).
+//!
+//! Every entry records the compression of its content, which is decompressed when the file
+//! is read. `build_with` compresses all the files of pack, and `PackWriter::add_compressed`
+//! compresses a single file.
+
+use std::io::Write;
+
+use bincode;
+
+use crate::errors::*;
+use crate::utils::hash::{FastHashMap, FastHashSet};
+
+use super::super::manifest::{self, Manifest};
+use super::compress::{self, Method};
+
+pub const MAGIC: [u8; 8] = [b'P', b'A', b'C', b'K', b' ', 0, 0, 2];
+
+/// The length of footer in bytes.
+pub const FOOTER_LEN: usize = 24;
+
+/// The location of a file in pack.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PackEntry {
+    pub name: String,
+    pub offset: u64,
+    pub len: u64,
+    /// The compression of content, or `None` if it's stored as it is.
+    pub compression: Option<Method>,
+}
+
+impl PackEntry {
+    /// Decompresses the content of file if it's compressed.
+    pub fn decompress(&self, bytes: Box<[u8]>) -> Result<Box<[u8]>> {
+        match self.compression {
+            Some(method) => compress::decompress_with(method, &bytes),
+            None => Ok(bytes),
+        }
+    }
+}
+
+/// The index of pack, which maps the names of files to their locations.
+#[derive(Debug, Clone, Default)]
+pub struct PackIndex {
+    entries: FastHashMap<String, PackEntry>,
+}
+
+impl PackIndex {
+    /// Parses the footer, and returns the offset and length of index.
+    pub fn parse_footer(footer: &[u8]) -> Result<(u64, u64)> {
+        if footer.len() != FOOTER_LEN || footer[16..] != MAGIC[..] {
+            bail!("[Pack] MAGIC number not match.");
+        }
+
+        let (offset, len) = (le64(&footer[0..8]), le64(&footer[8..16]));
+        if offset.checked_add(len).is_none() {
+            bail!("[Pack] The footer is corrupted.");
+        }
+
+        Ok((offset, len))
+    }
+
+    /// Parses the index at `offset`. The entries should locate files before the index,
+    /// otherwise the pack is corrupted.
+    pub fn parse(bytes: &[u8], offset: u64) -> Result<Self> {
+        let entries: Vec<PackEntry> = bincode::deserialize(bytes)?;
+        for v in &entries {
+            match v.offset.checked_add(v.len) {
+                Some(end) if end <= offset => {}
+                _ => bail!("[Pack] The entry of {} is out of bounds.", v.name),
+            }
+        }
+
+        Ok(PackIndex {
+            entries: entries.into_iter().map(|v| (v.name.clone(), v)).collect(),
+        })
+    }
+
+    /// Finds the entry of file by the file name of URL path, e.g. `/MANIFEST`.
+    pub fn find(&self, path: &str) -> Result<&PackEntry> {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.entries
+            .get(name)
+            .ok_or_else(|| format_err!("[Pack] {} could not be found in pack.", name))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn le64(bytes: &[u8]) -> u64 {
+    let mut v = [0; 8];
+    v.copy_from_slice(bytes);
+    u64::from_le_bytes(v)
+}
+
+/// Writes files into a pack.
+pub struct PackWriter<W: Write> {
+    file: W,
+    entries: Vec<PackEntry>,
+    names: FastHashSet<String>,
+    offset: u64,
+}
+
+impl<W: Write> PackWriter<W> {
+    pub fn new(file: W) -> Self {
+        PackWriter {
+            file,
+            entries: Vec::new(),
+            names: FastHashSet::default(),
+            offset: 0,
+        }
+    }
+
+    /// Appends a file with name.
+    pub fn add<T: Into<String>>(&mut self, name: T, bytes: &[u8]) -> Result<()> {
+        self.write(name.into(), bytes, None)
+    }
+
+    /// Appends a file with name, whose content is compressed with `method`.
+    pub fn add_compressed<T>(&mut self, name: T, method: Method, bytes: &[u8]) -> Result<()>
+    where
+        T: Into<String>,
+    {
+        let bytes = compress::compress(method, bytes);
+        self.write(name.into(), &bytes, Some(method))
+    }
+
+    fn write(&mut self, name: String, bytes: &[u8], compression: Option<Method>) -> Result<()> {
+        if self.names.contains(&name) {
+            bail!("[Pack] {} has been added already.", name);
+        }
+
+        self.file.write_all(bytes)?;
+        self.names.insert(name.clone());
+        self.entries.push(PackEntry {
+            name,
+            offset: self.offset,
+            len: bytes.len() as u64,
+            compression,
+        });
+
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the index and footer, and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        let index = bincode::serialize(&self.entries)?;
+        self.file.write_all(&index)?;
+        self.file.write_all(&self.offset.to_le_bytes())?;
+        self.file.write_all(&(index.len() as u64).to_le_bytes())?;
+        self.file.write_all(&MAGIC)?;
+        self.file.flush()?;
+        Ok(self.file)
+    }
+}
+
+/// Packs the `MANIFEST` and all the resources it lists in the directory, which is usually
+/// the output of `crayon-cli`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build<P1, P2>(dir: P1, dst: P2) -> Result<()>
+where
+    P1: AsRef<std::path::Path>,
+    P2: AsRef<std::path::Path>,
+{
+    build_with(dir, dst, None)
+}
+
+/// Packs the directory like `build`, and compresses all the files with `compression`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build_with<P1, P2>(dir: P1, dst: P2, compression: Option<Method>) -> Result<()>
+where
+    P1: AsRef<std::path::Path>,
+    P2: AsRef<std::path::Path>,
+{
+    use std::fs;
+    use std::io::{BufWriter, Read};
+
+    let dir = dir.as_ref();
+    let bytes = fs::read(dir.join(manifest::NAME))?;
+    let manifest = Manifest::load_from(&mut &bytes[..])?;
+
+    let mut writer = PackWriter::new(BufWriter::new(fs::File::create(dst)?));
+    let mut add = |name: String, bytes: &[u8]| match compression {
+        Some(method) => writer.add_compressed(name, method, bytes),
+        None => writer.add(name, bytes),
+    };
+
+    add(manifest::NAME.into(), &bytes)?;
+
+    for v in &manifest.items {
+        let name = format!("{:X}", v.uuid.to_simple());
+        let mut file = fs::File::open(dir.join(&name))
+            .map_err(|err| format_err!("[Pack] Failed to open {}. {}", name, err))?;
+
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        add(name, &buf)?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::native::Pack;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::fs::File;
+    use std::io::{Read, Seek, SeekFrom};
+    use std::ops::Range;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use crate::errors::*;
+    use crate::sched::prelude::LockLatch;
+
+    use super::super::super::request::Response;
+    use super::super::super::url::Url;
+    use super::super::{slice, VFS};
+    use super::{PackIndex, FOOTER_LEN};
+
+    /// A `VFS` that reads files from a pack with positional reads, so the files could be
+    /// read concurrently from the workers of `sched`.
+    #[derive(Debug)]
+    pub struct Pack {
+        file: File,
+        index: PackIndex,
+    }
+
+    impl Pack {
+        /// Opens the pack file, and reads its index.
+        pub fn open<T: AsRef<Path>>(path: T) -> Result<Self> {
+            let path = path.as_ref();
+            let mut file = File::open(path)
+                .map_err(|err| format_err!("[Pack] Failed to open {}. {}", path.display(), err))?;
+
+            let len = file.seek(SeekFrom::End(0))?;
+            if len < FOOTER_LEN as u64 {
+                bail!("[Pack] {} is not a pack file.", path.display());
+            }
+
+            let mut footer = [0; FOOTER_LEN];
+            file.seek(SeekFrom::Start(len - FOOTER_LEN as u64))?;
+            file.read_exact(&mut footer)?;
+            let (offset, size) = PackIndex::parse_footer(&footer)?;
+
+            // The footer has been checked, so only the length of footer might overflow.
+            if (offset + size).checked_add(FOOTER_LEN as u64) != Some(len) {
+                bail!("[Pack] The index of {} is corrupted.", path.display());
+            }
+
+            let mut index = vec![0; size as usize];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut index)?;
+
+            Ok(Pack {
+                file,
+                index: PackIndex::parse(&index, offset)?,
+            })
+        }
+
+        /// Returns the index of pack.
+        #[inline]
+        pub fn index(&self) -> &PackIndex {
+            &self.index
+        }
+
+        fn load_from(&self, path: &str) -> Response {
+            let entry = self.index.find(path)?;
+            let mut buf = vec![0; entry.len as usize];
+            read_exact_at(&self.file, &mut buf, entry.offset)?;
+            entry.decompress(buf.into_boxed_slice())
+        }
+
+        fn load_range_from(&self, path: &str, range: Range<u64>) -> Response {
+            let entry = self.index.find(path)?;
+
+            // The whole file has to be decompressed before any range of it is known.
+            if entry.compression.is_some() {
+                return Ok(slice(&self.load_from(path)?, range));
+            }
+
+            let start = range.start.min(entry.len);
+            let end = range.end.min(entry.len).max(start);
+
+            let mut buf = vec![0; (end - start) as usize];
+            read_exact_at(&self.file, &mut buf, entry.offset + start)?;
+            Ok(buf.into_boxed_slice())
+        }
+    }
+
+    impl VFS for Pack {
+        fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+            state.set(self.load_from(url.path()));
+        }
+
+        fn request_range(&self, url: &Url, range: Range<u64>, state: Arc<LockLatch<Response>>) {
+            state.set(self.load_range_from(url.path(), range));
+        }
+    }
+
+    #[cfg(unix)]
+    fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+        use std::os::unix::fs::FileExt;
+        file.read_exact_at(buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+        use std::io::{Error, ErrorKind};
+        use std::os::windows::fs::FileExt;
+
+        while !buf.is_empty() {
+            match file.seek_read(buf, offset) {
+                Ok(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "unexpected eof")),
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::io::Cursor;
+
+    use uuid::Uuid;
+
+    use super::super::super::request::Request;
+    use super::super::super::url::Url;
+    use super::super::VFS;
+    use super::*;
+
+    #[test]
+    fn pack() {
+        let dir = crate::utils::testing::temp_path("pack");
+        let src = dir.join("resources");
+        fs::create_dir_all(&src).unwrap();
+
+        let mut manifest = Manifest::new();
+        for i in 1..3u8 {
+            let uuid = Uuid::from_bytes([i; 16]);
+            fs::write(
+                src.join(format!("{:X}", uuid.to_simple())),
+                vec![i; i as usize],
+            )
+            .unwrap();
+
+            manifest.items.push(manifest::ManifestItem {
+                filename: manifest.buf.extend_from_str(format!("{}.bin", i)),
+                dependencies: manifest.buf.extend_from_slice::<usize>(&[]),
+                uuid,
+                hash: None,
+                size: None,
+            });
+        }
+
+        let bytes = manifest.to_bytes().unwrap();
+        fs::write(src.join(manifest::NAME), &bytes).unwrap();
+
+        let dst = dir.join("resources.pack");
+        build(&src, &dst).unwrap();
+
+        let pack = Pack::open(&dst).unwrap();
+        assert_eq!(pack.index().len(), 3);
+
+        let load = |path: &str| {
+            let state = Request::latch();
+            pack.request(&Url::new(path).unwrap(), state.clone());
+            state.take()
+        };
+
+        let uuid = Uuid::from_bytes([2; 16]);
+        let url = format!("pack://resources//{:X}", uuid.to_simple());
+        assert_eq!(&load(&url).unwrap()[..], &[2, 2]);
+        assert_eq!(&load("pack://resources/MANIFEST").unwrap()[..], &bytes[..]);
+        assert!(load("pack://resources/404").is_err());
+
+        // Reads the byte range of file without loading the others.
+        let load_range = |path: &str, range| {
+            let state = Request::latch();
+            pack.request_range(&Url::new(path).unwrap(), range, state.clone());
+            state.take()
+        };
+
+        let url = "pack://resources/MANIFEST";
+        assert_eq!(&load_range(url, 2..6).unwrap()[..], &bytes[2..6]);
+        assert_eq!(
+            &load_range(url, 4..u64::max_value()).unwrap()[..],
+            &bytes[4..]
+        );
+        assert!(load_range(url, u64::max_value()..u64::max_value())
+            .unwrap()
+            .is_empty());
+        assert!(load_range("pack://resources/404", 0..1).is_err());
+
+        // Compressed files are decompressed with the method in index.
+        build_with(&src, &dst, Some(Method::Lz4)).unwrap();
+        let pack = Pack::open(&dst).unwrap();
+        let entry = pack.index().find("/MANIFEST").unwrap();
+        assert_eq!(entry.compression, Some(Method::Lz4));
+
+        let state = Request::latch();
+        let url = Url::new("pack://resources/MANIFEST").unwrap();
+        pack.request(&url, state.clone());
+        assert_eq!(&state.take().unwrap()[..], &bytes[..]);
+
+        let state = Request::latch();
+        pack.request_range(&url, 2..6, state.clone());
+        assert_eq!(&state.take().unwrap()[..], &bytes[2..6]);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut writer = PackWriter::new(Cursor::new(Vec::new()));
+        writer.add("a", &[1]).unwrap();
+        assert!(writer.add("a", &[2]).is_err());
+        assert!(writer.add_compressed("a", Method::Zstd, &[2]).is_err());
+
+        let bytes = writer.finish().unwrap().into_inner();
+        let footer = &bytes[bytes.len() - FOOTER_LEN..];
+        let (offset, len) = PackIndex::parse_footer(footer).unwrap();
+        assert_eq!(offset, 1);
+        assert_eq!(offset + len, (bytes.len() - FOOTER_LEN) as u64);
+        assert!(PackIndex::parse_footer(&bytes[0..FOOTER_LEN]).is_err());
+
+        // Corrupted footers and entries are rejected.
+        let mut footer = footer.to_vec();
+        footer[0..8].copy_from_slice(&u64::max_value().to_le_bytes());
+        assert!(PackIndex::parse_footer(&footer).is_err());
+
+        let index = &bytes[offset as usize..(offset + len) as usize];
+        assert_eq!(PackIndex::parse(index, offset).unwrap().len(), 1);
+        assert!(PackIndex::parse(index, 0).is_err());
+
+        let entries = vec![PackEntry {
+            name: "a".into(),
+            offset: u64::max_value(),
+            len: 2,
+            compression: None,
+        }];
+        let index = bincode::serialize(&entries).unwrap();
+        assert!(PackIndex::parse(&index, offset).is_err());
+
+        // The compression of entry is honoured, instead of sniffing the content.
+        let entry = PackEntry {
+            name: "a".into(),
+            offset: 0,
+            len: 1,
+            compression: Some(Method::Deflate),
+        };
+
+        let file = compress::compress(Method::Deflate, b"hello");
+        assert_eq!(&entry.decompress(file.into()).unwrap()[..], b"hello");
+        assert!(entry.decompress(b"hello".to_vec().into()).is_err());
+    }
+}
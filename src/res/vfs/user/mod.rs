@@ -0,0 +1,65 @@
+//! Stores the data of user, e.g. save games, settings and screenshots.
+//!
+//! The `user` schema is registered by default, and its files could be written, listed and
+//! deleted at runtime besides loading:
+//!
+//! ```rust,ignore
+//! res::write("user:///saves/0.sav", &bytes)?;
+//! let saves = res::list("user:///saves/")?;
+//! let req = res::load_url("user:///saves/0.sav")?;
+//! ```
+//!
+//! On native, the files are stored in the platform-specific data directory of application,
+//! e.g. `%APPDATA%` on Windows, `~/Library/Application Support` on macOS, and
+//! `$XDG_DATA_HOME` on others. On web, they are stored in the `localStorage` of browser.
+//! `User::temp` creates a storage in a temporary directory, which is useful for tests.
+
+/// The schema of user data.
+pub const SCHEMA: &str = "user";
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::native::User;
+
+#[cfg(target_arch = "wasm32")]
+mod web;
+#[cfg(target_arch = "wasm32")]
+pub use self::web::User;
+
+#[cfg(test)]
+mod test {
+    use super::super::super::request::Request;
+    use super::super::super::url::Url;
+    use super::super::VFS;
+    use super::*;
+
+    #[test]
+    fn user() {
+        let user = User::temp().unwrap();
+        let url = |path: &str| Url::new(format!("user://{}", path)).unwrap();
+
+        assert!(user.list(&url("/saves/")).unwrap().is_empty());
+        user.write(&url("/saves/1.sav"), b"one").unwrap();
+        user.write(&url("/saves/0.sav"), b"zero").unwrap();
+        user.write(&url("/settings"), b"{}").unwrap();
+        user.write(&url("/saves/1.sav"), b"ONE").unwrap();
+
+        assert_eq!(user.list(&url("/saves/")).unwrap(), vec!["0.sav", "1.sav"]);
+        assert_eq!(user.list(&url("/")).unwrap(), vec!["settings"]);
+
+        let state = Request::latch();
+        user.request(&url("/saves/1.sav"), state.clone());
+        assert_eq!(&state.take().unwrap()[..], b"ONE");
+
+        user.delete(&url("/saves/1.sav")).unwrap();
+        assert_eq!(user.list(&url("/saves")).unwrap(), vec!["0.sav"]);
+        assert!(user.delete(&url("/saves/1.sav")).is_err());
+
+        let state = Request::latch();
+        user.request(&url("/saves/1.sav"), state.clone());
+        assert!(state.take().is_err());
+
+        assert!(user.write(&url("/../escape"), b"").is_err());
+    }
+}
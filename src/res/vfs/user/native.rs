@@ -0,0 +1,122 @@
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::sched::prelude::LockLatch;
+
+use super::super::super::request::Response;
+use super::super::super::url::Url;
+use super::super::components;
+use super::super::dir;
+use super::super::VFS;
+
+/// A writable `VFS` that stores the files of user under a directory.
+#[derive(Debug)]
+pub struct User {
+    root: PathBuf,
+    temporary: bool,
+}
+
+impl User {
+    /// Creates a storage under `root`, which is created once the first file is written.
+    pub fn new<T: Into<PathBuf>>(root: T) -> Self {
+        User {
+            root: root.into(),
+            temporary: false,
+        }
+    }
+
+    /// Creates a storage in the platform-specific data directory of this application,
+    /// which is named after the executable.
+    pub fn platform() -> Self {
+        let name = std::env::current_exe()
+            .ok()
+            .and_then(|v| v.file_stem().map(|v| v.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "crayon".to_owned());
+
+        User::new(data_dir().join(name))
+    }
+
+    /// Creates a storage in a new temporary directory, which is removed when the storage
+    /// is dropped.
+    pub fn temp() -> Result<Self, failure::Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let name = format!("crayon-user-{}-{}", std::process::id(), n);
+        let root = std::env::temp_dir().join(name);
+        fs::create_dir_all(&root)?;
+
+        Ok(User {
+            root,
+            temporary: true,
+        })
+    }
+
+    /// Returns the directory of this storage.
+    #[inline]
+    pub fn root(&self) -> &std::path::Path {
+        &self.root
+    }
+
+    fn locate(&self, url: &Url) -> Result<PathBuf, failure::Error> {
+        let mut path = self.root.clone();
+        for v in components(url.path())? {
+            path.push(v);
+        }
+
+        Ok(path)
+    }
+
+    fn load_from(&self, url: &Url) -> Response {
+        let mut file = fs::File::open(self.locate(url)?)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        Ok(buf.into_boxed_slice())
+    }
+}
+
+impl Drop for User {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+impl VFS for User {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        state.set(self.load_from(url));
+    }
+
+    fn write(&self, url: &Url, bytes: &[u8]) -> Result<(), failure::Error> {
+        dir::write(&self.locate(url)?, bytes)
+    }
+
+    fn list(&self, url: &Url) -> Result<Vec<String>, failure::Error> {
+        dir::list(&self.locate(url)?)
+    }
+
+    fn delete(&self, url: &Url) -> Result<(), failure::Error> {
+        fs::remove_file(self.locate(url)?)?;
+        Ok(())
+    }
+}
+
+fn data_dir() -> PathBuf {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+
+    let dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|v| v.join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|v| v.join(".local").join("share")))
+    };
+
+    dir.unwrap_or_else(std::env::temp_dir)
+}
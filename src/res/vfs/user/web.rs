@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use web_sys::Storage;
+
+use crate::sched::prelude::LockLatch;
+
+use super::super::super::request::Response;
+use super::super::super::url::Url;
+use super::super::components;
+use super::super::VFS;
+
+/// A writable `VFS` that stores the files of user in the `localStorage` of browser. The
+/// bytes of file are stored as a string with one char per byte.
+#[derive(Debug, Clone)]
+pub struct User {
+    namespace: String,
+}
+
+impl User {
+    /// Creates a storage whose files are keyed under `namespace`.
+    pub fn new<T: Into<String>>(namespace: T) -> Self {
+        User {
+            namespace: namespace.into(),
+        }
+    }
+
+    /// Creates the storage of this application.
+    pub fn platform() -> Self {
+        User::new("crayon")
+    }
+
+    /// Creates a storage under a new namespace for tests, which is cleared when the
+    /// storage is dropped.
+    pub fn temp() -> Result<Self, failure::Error> {
+        let n = js_sys::Math::random().to_bits();
+        Ok(User::new(format!("crayon-temp-{:x}", n)))
+    }
+
+    fn key(&self, url: &Url) -> Result<String, failure::Error> {
+        Ok(format!(
+            "{}/{}",
+            self.namespace,
+            components(url.path())?.join("/")
+        ))
+    }
+
+    fn load_from(&self, url: &Url) -> Response {
+        let key = self.key(url)?;
+        let value = storage()?
+            .get_item(&key)
+            .map_err(|_| format_err!("[User] Failed to read {}.", url))?
+            .ok_or_else(|| format_err!("[User] Could not found {}.", url))?;
+
+        let bytes: Vec<u8> = value.chars().map(|v| v as u32 as u8).collect();
+        Ok(bytes.into_boxed_slice())
+    }
+
+    fn keys(&self) -> Result<Vec<String>, failure::Error> {
+        let storage = storage()?;
+        let len = storage.length().unwrap_or(0);
+        let prefix = format!("{}/", self.namespace);
+
+        let mut keys = Vec::new();
+        for i in 0..len {
+            if let Ok(Some(key)) = storage.key(i) {
+                if key.starts_with(&prefix) {
+                    keys.push(key);
+                }
+            }
+        }
+
+        Ok(keys)
+    }
+}
+
+impl Drop for User {
+    fn drop(&mut self) {
+        if self.namespace.starts_with("crayon-temp-") {
+            if let (Ok(storage), Ok(keys)) = (storage(), self.keys()) {
+                for v in keys {
+                    let _ = storage.remove_item(&v);
+                }
+            }
+        }
+    }
+}
+
+impl VFS for User {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        state.set(self.load_from(url));
+    }
+
+    fn write(&self, url: &Url, bytes: &[u8]) -> Result<(), failure::Error> {
+        let key = self.key(url)?;
+        let value: String = bytes.iter().map(|&v| v as char).collect();
+        storage()?
+            .set_item(&key, &value)
+            .map_err(|_| format_err!("[User] Failed to write {}, the storage might be full.", url))
+    }
+
+    fn list(&self, url: &Url) -> Result<Vec<String>, failure::Error> {
+        let mut prefix = self.key(url)?;
+        if !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+
+        let mut names: Vec<_> = self
+            .keys()?
+            .into_iter()
+            .filter_map(|v| {
+                let name = v.get(prefix.len()..)?;
+                if v.starts_with(&prefix) && !name.contains('/') {
+                    Some(name.to_owned())
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        names.sort();
+        Ok(names)
+    }
+
+    fn delete(&self, url: &Url) -> Result<(), failure::Error> {
+        let key = self.key(url)?;
+        let storage = storage()?;
+
+        if let Ok(None) = storage.get_item(&key) {
+            bail!("[User] Could not found {}.", url);
+        }
+
+        storage
+            .remove_item(&key)
+            .map_err(|_| format_err!("[User] Failed to delete {}.", url))
+    }
+}
+
+fn storage() -> Result<Storage, failure::Error> {
+    web_sys::window()
+        .and_then(|v| v.local_storage().ok())
+        .and_then(|v| v)
+        .ok_or_else(|| format_err!("[User] The localStorage is not available."))
+}
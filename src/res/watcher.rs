@@ -0,0 +1,172 @@
+//! Polls the modification of loaded files, which is used to hot-reload resources
+//! during development.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use uuid::Uuid;
+
+use crate::utils::prelude::FastHashMap;
+use crate::utils::time::Timestamp;
+
+use super::url::Url;
+use super::vfs::VFS;
+
+/// The number of recent changes that are kept. The pools take the changes every frame, so
+/// only the older ones that nobody would take are dropped.
+pub const MAX_CHANGES: usize = 1024;
+
+struct WatchedFile {
+    vfs: Arc<dyn VFS>,
+    url: Url,
+    modified: SystemTime,
+}
+
+pub struct Watcher {
+    interval: Duration,
+    last_poll: Mutex<Timestamp>,
+    files: Mutex<FastHashMap<Uuid, WatchedFile>>,
+    changes: RwLock<Changes>,
+}
+
+/// The recent changes, `offset` is the number of changes that have been dropped, so the
+/// cursors of changes keep counting from the first one.
+#[derive(Default)]
+struct Changes {
+    offset: usize,
+    uuids: VecDeque<Uuid>,
+}
+
+impl Watcher {
+    pub fn new(interval: Duration) -> Self {
+        Watcher {
+            interval,
+            last_poll: Mutex::new(Timestamp::now()),
+            files: Mutex::new(FastHashMap::default()),
+            changes: RwLock::new(Changes::default()),
+        }
+    }
+
+    /// Starts watching the file of resource. Its ignored if the VFS could not tell the
+    /// modification time of file.
+    pub fn watch(&self, uuid: Uuid, vfs: Arc<dyn VFS>, url: Url) {
+        let mut files = self.files.lock().unwrap();
+        if files.contains_key(&uuid) {
+            return;
+        }
+
+        if let Some(modified) = vfs.modified(&url) {
+            files.insert(uuid, WatchedFile { vfs, url, modified });
+        }
+    }
+
+    /// Stops watching the file of resource, e.g. when the resource is destroyed.
+    pub fn unwatch(&self, uuid: Uuid) {
+        self.files.lock().unwrap().remove(&uuid);
+    }
+
+    /// Checks the modification time of all the watched files if the interval is elapsed,
+    /// the UUIDs of changed resources are appended to the changes.
+    pub fn poll(&self) {
+        {
+            let mut last_poll = self.last_poll.lock().unwrap();
+            if last_poll.elapsed() < self.interval {
+                return;
+            }
+
+            *last_poll = Timestamp::now();
+        }
+
+        let mut files = self.files.lock().unwrap();
+        for (&uuid, file) in files.iter_mut() {
+            if let Some(modified) = file.vfs.modified(&file.url) {
+                if modified != file.modified {
+                    info!("[ResourceSystem] {} ({}) is modified.", file.url, uuid);
+                    file.modified = modified;
+
+                    let mut changes = self.changes.write().unwrap();
+                    changes.uuids.push_back(uuid);
+                    if changes.uuids.len() > MAX_CHANGES {
+                        changes.uuids.pop_front();
+                        changes.offset += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the UUIDs of resources that are changed after `cursor`, and moves the
+    /// cursor to the latest change. Only the recent `MAX_CHANGES` changes are returned.
+    pub fn changes(&self, cursor: &mut usize) -> Vec<Uuid> {
+        let changes = self.changes.read().unwrap();
+        let len = changes.offset + changes.uuids.len();
+        let start = (*cursor).max(changes.offset).min(len) - changes.offset;
+        *cursor = len;
+        changes.uuids.iter().skip(start).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::super::vfs::dir::Dir;
+    use super::*;
+
+    #[test]
+    fn poll() {
+        let path = crate::utils::testing::temp_path("watcher");
+        fs::write(&path, b"foo").unwrap();
+
+        let url = Url::new(format!("file://{}", path.display())).unwrap();
+        let uuid = Uuid::from_bytes([1; 16]);
+
+        let watcher = Watcher::new(Duration::from_millis(0));
+        watcher.watch(uuid, Arc::new(Dir::new()), url);
+
+        let mut cursor = 0;
+        watcher.poll();
+        assert!(watcher.changes(&mut cursor).is_empty());
+
+        let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+
+        watcher.poll();
+        assert_eq!(watcher.changes(&mut cursor), vec![uuid]);
+        assert!(watcher.changes(&mut cursor).is_empty());
+
+        // The files of destroyed resources are not polled any more.
+        watcher.unwatch(uuid);
+        file.set_modified(SystemTime::now() + Duration::from_secs(120))
+            .unwrap();
+        watcher.poll();
+        assert!(watcher.changes(&mut cursor).is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn changes() {
+        let watcher = Watcher::new(Duration::from_millis(0));
+        {
+            let mut changes = watcher.changes.write().unwrap();
+            for i in 0..MAX_CHANGES + 2 {
+                changes.uuids.push_back(Uuid::from_bytes([i as u8; 16]));
+            }
+
+            changes.uuids.drain(0..2);
+            changes.offset = 2;
+        }
+
+        // The cursors keep counting from the first change, the dropped ones are skipped.
+        let mut cursor = 0;
+        assert_eq!(watcher.changes(&mut cursor).len(), MAX_CHANGES);
+        assert_eq!(cursor, MAX_CHANGES + 2);
+
+        let mut cursor = MAX_CHANGES + 1;
+        assert_eq!(watcher.changes(&mut cursor).len(), 1);
+        assert!(watcher.changes(&mut cursor).is_empty());
+    }
+}
@@ -68,11 +68,14 @@ impl LatchProbe for SpinLatch {
     }
 }
 
+type Callback<T> = Box<dyn FnOnce(T) + Send>;
+
 /// A Latch starts as false and eventually becomes true. You can block until
 /// it becomes true.
 pub struct LockLatch<T> {
     m: Mutex<Option<T>>,
     v: Condvar,
+    callback: Mutex<Option<Callback<T>>>,
 }
 
 impl<T> Default for LockLatch<T> {
@@ -80,6 +83,7 @@ impl<T> Default for LockLatch<T> {
         LockLatch {
             m: Mutex::new(None),
             v: Condvar::new(),
+            callback: Mutex::new(None),
         }
     }
 }
@@ -90,8 +94,27 @@ impl<T> LockLatch<T> {
         Default::default()
     }
 
+    /// Creates a latch that hands the value over to `func` when it's set, instead of
+    /// keeping it. It's useful to chain asynchronous operations.
+    pub fn with_callback<F>(func: F) -> LockLatch<T>
+    where
+        F: FnOnce(T) + Send + 'static,
+    {
+        LockLatch {
+            m: Mutex::new(None),
+            v: Condvar::new(),
+            callback: Mutex::new(Some(Box::new(func))),
+        }
+    }
+
     #[inline]
     pub fn set(&self, v: T) {
+        let callback = self.callback.lock().unwrap().take();
+        if let Some(func) = callback {
+            func(v);
+            return;
+        }
+
         let mut guard = self.m.lock().unwrap();
         *guard = Some(v);
         self.v.notify_all();
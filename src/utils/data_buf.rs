@@ -1,26 +1,41 @@
 use std::borrow::Borrow;
 use std::marker::PhantomData;
-use std::{mem, slice, str};
+use std::{fmt, mem, ptr, slice, str};
 
-/// Where we store all the intermediate bytes.
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
-pub struct DataBuffer(Vec<u8>);
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The maximum alignment of objects that could be stored in `DataBuffer`.
+pub const MAX_ALIGN: usize = 16;
+
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+struct Block([u8; MAX_ALIGN]);
+
+/// Where we store all the intermediate bytes. The storage is allocated in blocks of
+/// `MAX_ALIGN` bytes, so the objects that are aligned relative to the start of buffer are
+/// aligned in memory as well.
+#[derive(Clone, Default)]
+pub struct DataBuffer {
+    blocks: Vec<Block>,
+    len: usize,
+}
 
 impl DataBuffer {
     /// Creates a new and emplty `DataBuffer`.
     pub fn new() -> Self {
-        DataBuffer(Vec::new())
+        Default::default()
     }
 
     /// Creates a new task buffer with specified capacity.
     pub fn with_capacity(capacity: usize) -> Self {
-        DataBuffer(Vec::with_capacity(capacity))
+        DataBuffer {
+            blocks: Vec::with_capacity(capacity.div_ceil(MAX_ALIGN)),
+            len: 0,
+        }
     }
 
     pub fn clear(&mut self) {
-        unsafe {
-            self.0.set_len(0);
-        }
+        self.len = 0;
     }
 
     pub fn extend<T>(&mut self, value: &T) -> DataBufferPtr<T>
@@ -30,10 +45,11 @@ impl DataBuffer {
         let data =
             unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
 
-        self.0.extend_from_slice(data);
+        self.align(mem::align_of::<T>());
+        self.push(data);
 
         DataBufferPtr {
-            position: (self.0.len() - data.len()) as u32,
+            position: (self.len - data.len()) as u32,
             size: data.len() as u32,
             _phantom: PhantomData,
         }
@@ -46,9 +62,10 @@ impl DataBuffer {
     {
         let len = mem::size_of::<T>().wrapping_mul(slice.len());
         let u8_slice = unsafe { slice::from_raw_parts(slice.as_ptr() as *const u8, len) };
-        self.0.extend_from_slice(u8_slice);
+        self.align(mem::align_of::<T>());
+        self.push(u8_slice);
         DataBufferPtr {
-            position: (self.0.len() - len) as u32,
+            position: (self.len - len) as u32,
             size: len as u32,
             _phantom: PhantomData,
         }
@@ -67,6 +84,32 @@ impl DataBuffer {
         }
     }
 
+    /// Pads the buffer with zeros, so the next object is aligned in memory.
+    fn align(&mut self, align: usize) {
+        assert!(align <= MAX_ALIGN, "Alignment {} is not supported.", align);
+        let padding = self.len.next_multiple_of(align) - self.len;
+        self.push(&[0; MAX_ALIGN][..padding]);
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let len = self.len + bytes.len();
+        self.blocks
+            .resize(len.div_ceil(MAX_ALIGN), Block([0; MAX_ALIGN]));
+
+        unsafe {
+            let dst = (self.blocks.as_mut_ptr() as *mut u8).add(self.len);
+            ptr::copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len());
+        }
+
+        self.len = len;
+    }
+
+    /// Returns all the bytes in buffer.
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.blocks.as_ptr() as *const u8, self.len) }
+    }
+
     /// Returns reference to object indicated by `DataBufferPtr`.
     #[inline]
     pub fn as_ref<T>(&self, ptr: DataBufferPtr<T>) -> &T
@@ -101,7 +144,29 @@ impl DataBuffer {
     where
         T: ?Sized,
     {
-        &self.0[slice.position as usize..(slice.position + slice.size) as usize]
+        &self.bytes()[slice.position as usize..(slice.position + slice.size) as usize]
+    }
+}
+
+impl fmt::Debug for DataBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("DataBuffer").field(&self.bytes()).finish()
+    }
+}
+
+// Serialized as a plain byte vector.
+impl Serialize for DataBuffer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bytes().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DataBuffer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        let mut buf = DataBuffer::with_capacity(bytes.len());
+        buf.push(&bytes);
+        Ok(buf)
     }
 }
 
@@ -162,5 +227,38 @@ mod test {
         let text = "string serialization";
         let slice_text = buffer.extend_from_str(text);
         assert_eq!(text, buffer.as_str(slice_text));
+
+        let slice_usize = buffer.extend_from_slice(&[1usize, 2]);
+        assert_eq!(buffer.as_slice(slice_usize), &[1, 2]);
+    }
+
+    #[test]
+    fn align() {
+        let mut buffer = DataBuffer::new();
+        buffer.extend(&1u8);
+
+        let ptr = buffer.extend(&2u128);
+        assert_eq!(
+            buffer.as_bytes(ptr).as_ptr() as usize % mem::align_of::<u128>(),
+            0
+        );
+        assert_eq!(*buffer.as_ref(ptr), 2);
+
+        let ptr = buffer.extend_from_slice(&[3u64, 4]);
+        assert_eq!(
+            buffer.as_bytes(ptr).as_ptr() as usize % mem::align_of::<u64>(),
+            0
+        );
+
+        // It is serialized as bytes, and the alignment is kept after deserialization.
+        let bytes = bincode::serialize(&buffer).unwrap();
+        assert_eq!(bytes, bincode::serialize(&buffer.bytes().to_vec()).unwrap());
+
+        let buffer: DataBuffer = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(buffer.as_slice(ptr), &[3, 4]);
+        assert_eq!(
+            buffer.as_bytes(ptr).as_ptr() as usize % mem::align_of::<u64>(),
+            0
+        );
     }
 }
@@ -10,6 +10,9 @@ pub mod hash_value;
 pub mod object_pool;
 pub mod time;
 
+#[cfg(test)]
+pub(crate) mod testing;
+
 pub mod prelude {
     pub use super::data_buf::{DataBuffer, DataBufferPtr};
     pub use super::double_buf::DoubleBuf;
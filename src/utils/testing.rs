@@ -0,0 +1,35 @@
+//! The fixtures shared by unit tests.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Wake, Waker};
+
+/// A waker that counts the times it's woken.
+#[derive(Debug, Default)]
+pub struct Counter(AtomicUsize);
+
+impl Counter {
+    /// Creates a counter, and the waker that wakes it.
+    pub fn waker() -> (Arc<Counter>, Waker) {
+        let counter = Arc::new(Counter::default());
+        (counter.clone(), Waker::from(counter))
+    }
+
+    /// Returns the times it's woken.
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl Wake for Counter {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Returns the path of `name` in the temporary directory. The id of process is appended, so
+/// the tests of concurrent runs never share files.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("crayon-{}-{}", name, std::process::id()))
+}
@@ -0,0 +1,120 @@
+//! Human readable names of video resources, which are used in logs and graphics
+//! debuggers.
+
+use std::fmt;
+
+/// Maximum number of bytes in a label, the longer ones are truncated.
+pub const MAX_LABEL_LEN: usize = 31;
+
+/// A short and copyable label of video resource.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+    len: u8,
+    buf: [u8; MAX_LABEL_LEN],
+}
+
+impl Label {
+    /// Creates a new label, which will be truncated into `MAX_LABEL_LEN` bytes at
+    /// char boundary.
+    pub fn new(name: &str) -> Self {
+        let mut len = name.len().min(MAX_LABEL_LEN);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut buf = [0; MAX_LABEL_LEN];
+        buf[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Label {
+            len: len as u8,
+            buf,
+        }
+    }
+
+    /// Returns the label as string slice.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        unsafe { std::str::from_utf8_unchecked(&self.buf[..self.len as usize]) }
+    }
+}
+
+impl<'a> From<&'a str> for Label {
+    fn from(name: &'a str) -> Self {
+        Label::new(name)
+    }
+}
+
+impl From<String> for Label {
+    fn from(name: String) -> Self {
+        Label::new(&name)
+    }
+}
+
+impl AsRef<str> for Label {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for Label {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl serde::Serialize for Label {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Label {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Label::new(&name))
+    }
+}
+
+/// Formats the handle of resource with its optional label, e.g. `TextureHandle(..) "albedo"`.
+pub(crate) struct Labelled<H: fmt::Debug>(pub H, pub Option<Label>);
+
+impl<H: fmt::Debug> fmt::Display for Labelled<H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1 {
+            Some(label) => write!(f, "{:?} {:?}", self.0, label),
+            None => write!(f, "{:?}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn truncate() {
+        let label = Label::new("albedo");
+        assert_eq!(label.as_str(), "albedo");
+        assert_eq!(format!("{:?}", label), "\"albedo\"");
+
+        let name = "a".repeat(30) + "中";
+        assert_eq!(Label::new(&name).as_str(), "a".repeat(30));
+    }
+
+    #[test]
+    fn serde() {
+        let label = Some(Label::new("albedo"));
+        let bytes = bincode::serialize(&label).unwrap();
+        let v: Option<Label> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(v, label);
+
+        let bytes = bincode::serialize(&"a".repeat(40)).unwrap();
+        let v: Label = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(v.as_str(), "a".repeat(31));
+    }
+}
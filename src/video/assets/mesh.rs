@@ -1,6 +1,7 @@
 //! Immutable or dynamic vertex and index data.
 
 use crate::math::prelude::Aabb3;
+use crate::video::assets::label::Label;
 use crate::video::assets::shader::Attribute;
 use crate::video::errors::{Error, Result};
 use crate::video::MAX_VERTEX_ATTRIBUTES;
@@ -27,6 +28,9 @@ pub struct MeshParams {
     pub sub_mesh_offsets: SmallVec<[usize; 8]>,
     /// Trivial bounding box of vertices.
     pub aabb: Aabb3<f32>,
+    /// Optional label used in logs and graphics debuggers.
+    #[serde(default)]
+    pub label: Option<Label>,
 }
 
 /// Continuous data of vertices and its indices.
@@ -49,6 +53,7 @@ impl Default for MeshParams {
             num_idxes: 0,
             aabb: Aabb3::zero(),
             sub_mesh_offsets: SmallVec::new(),
+            label: None,
         }
     }
 }
@@ -65,8 +70,10 @@ impl MeshParams {
             }
         }
 
-        for v in &self.sub_mesh_offsets {
-            if *v >= self.num_idxes {
+        // The offsets must be strictly increasing, so every sub-mesh has elements.
+        let num = self.num_elements();
+        for (i, v) in self.sub_mesh_offsets.iter().enumerate() {
+            if *v >= num || (i > 0 && *v <= self.sub_mesh_offsets[i - 1]) {
                 return Err(Error::OutOfBounds);
             }
         }
@@ -74,6 +81,24 @@ impl MeshParams {
         Ok(())
     }
 
+    /// Returns true if this mesh has an index buffer. Meshes without indices
+    /// are drawn directly from their vertices.
+    #[inline]
+    pub fn is_indexed(&self) -> bool {
+        self.num_idxes > 0
+    }
+
+    /// The number of elements (indices, or vertices for non-indexed meshes)
+    /// that make up this mesh.
+    #[inline]
+    pub fn num_elements(&self) -> usize {
+        if self.is_indexed() {
+            self.num_idxes
+        } else {
+            self.num_verts
+        }
+    }
+
     #[inline]
     pub fn vertex_buffer_len(&self) -> usize {
         self.num_verts * self.layout.stride() as usize
@@ -86,10 +111,21 @@ impl MeshParams {
 }
 
 /// Mesh index.
+///
+/// For meshes without indices, `SubMesh`, `Ptr` and `All` address vertices
+/// instead of indices.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum MeshIndex {
+    /// Draws the sub-mesh at index.
     SubMesh(usize),
+    /// Draws elements in range `[from, from + len)`.
     Ptr(usize, usize),
+    /// Draws vertices in range `[from, from + len)`, ignoring the index buffer.
+    Vertices(usize, usize),
+    /// Draws indices in range `[from, from + len)`, with the base vertex added
+    /// to each index before fetching vertices.
+    BaseVertex(usize, usize, usize),
+    /// Draws the whole mesh.
     All,
 }
 
@@ -349,6 +385,27 @@ mod test {
         assert_eq!(layout.element(Attribute::Normal), None);
     }
 
+    #[test]
+    fn non_indexed() {
+        let mut params = MeshParams::default();
+        params.num_verts = 6;
+        params.sub_mesh_offsets.push(0);
+        params.sub_mesh_offsets.push(3);
+
+        assert!(!params.is_indexed());
+        assert_eq!(params.num_elements(), 6);
+        assert!(params.validate(None).is_ok());
+
+        params.sub_mesh_offsets.push(6);
+        assert!(params.validate(None).is_err());
+
+        // The offsets must be strictly increasing.
+        params.sub_mesh_offsets = [0, 3, 3].iter().cloned().collect();
+        assert!(params.validate(None).is_err());
+        params.sub_mesh_offsets = [3, 0].iter().cloned().collect();
+        assert!(params.validate(None).is_err());
+    }
+
     #[test]
     fn rewrite() {
         let layout = VertexLayout::build()
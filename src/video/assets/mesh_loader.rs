@@ -1,15 +1,55 @@
 use bincode;
+use smallvec::SmallVec;
 use std::io::Cursor;
 use std::sync::Arc;
 
 use crate::errors::*;
+use crate::math::prelude::Aabb3;
+use crate::res::header::Format;
 use crate::res::utils::prelude::ResourceLoader;
 use crate::utils::double_buf::DoubleBuf;
 
 use super::super::backends::frame::{Command, Frame};
 use super::mesh::*;
 
-pub const MAGIC: [u8; 8] = [b'V', b'M', b'S', b'H', b' ', 0, 0, 1];
+pub const FORMAT: Format = Format {
+    tag: *b"VMSH",
+    version: 2,
+    migrations: &[v1],
+};
+
+/// The mesh parameters of version 1, which has no label.
+#[derive(Deserialize)]
+struct MeshParamsV1 {
+    hint: MeshHint,
+    layout: VertexLayout,
+    index_format: IndexFormat,
+    primitive: MeshPrimitive,
+    num_verts: usize,
+    num_idxes: usize,
+    sub_mesh_offsets: SmallVec<[usize; 8]>,
+    aabb: Aabb3<f32>,
+}
+
+fn v1(payload: &[u8]) -> Result<Vec<u8>> {
+    let mut file = Cursor::new(payload);
+    let v: MeshParamsV1 = bincode::deserialize_from(&mut file)?;
+    let params = MeshParams {
+        hint: v.hint,
+        layout: v.layout,
+        index_format: v.index_format,
+        primitive: v.primitive,
+        num_verts: v.num_verts,
+        num_idxes: v.num_idxes,
+        sub_mesh_offsets: v.sub_mesh_offsets,
+        aabb: v.aabb,
+        label: None,
+    };
+
+    let mut bytes = bincode::serialize(&params)?;
+    bytes.extend_from_slice(&payload[file.position() as usize..]);
+    Ok(bytes)
+}
 
 #[derive(Clone)]
 pub struct MeshLoader {
@@ -28,11 +68,8 @@ impl ResourceLoader for MeshLoader {
     type Resource = MeshParams;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if bytes[0..8] != MAGIC[..] {
-            bail!("[MeshLoader] MAGIC number not match.");
-        }
-
-        let mut file = Cursor::new(&bytes[8..]);
+        let payload = FORMAT.read(bytes)?;
+        let mut file = Cursor::new(&payload[..]);
         let params: MeshParams = bincode::deserialize_from(&mut file)?;
         let data = bincode::deserialize_from(&mut file)?;
 
@@ -52,9 +89,73 @@ impl ResourceLoader for MeshLoader {
         Ok(item.0)
     }
 
+    fn reload(
+        &self,
+        handle: Self::Handle,
+        resource: &mut Self::Resource,
+        item: Self::Intermediate,
+    ) -> Result<()> {
+        info!("[MeshLoader] reload {:?}.", handle);
+        item.0.validate(item.1.as_ref())?;
+
+        // Recreates the backend object with the same handle, which happens before any
+        // draw calls of this frame.
+        let mut frame = self.frames.write();
+        frame.cmds.push(Command::DeleteMesh(handle));
+        frame.cmds.push(Command::CreateMesh(Box::new((
+            handle,
+            item.0.clone(),
+            item.1,
+        ))));
+
+        *resource = item.0;
+        Ok(())
+    }
+
     fn delete(&self, handle: Self::Handle, _: Self::Resource) {
         info!("[MeshLoader] delete {:?}.", handle);
         let cmd = Command::DeleteMesh(handle);
         self.frames.write().cmds.push(cmd);
     }
+
+    fn size(&self, params: &Self::Resource) -> usize {
+        params.vertex_buffer_len() + params.index_buffer_len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn migrate() {
+        let mut params = MeshParams::default();
+        params.num_verts = 3;
+        params.label = Some("quad".into());
+
+        let mut payload = bincode::serialize(&params).unwrap();
+        payload.extend_from_slice(&[1, 2, 3]);
+        let bytes = FORMAT.read(&FORMAT.write(&payload)).unwrap().into_owned();
+        assert_eq!(bytes, payload);
+
+        // The version 1 has no label, which is serialized as a trailing `None`.
+        params.label = None;
+        let mut payload = bincode::serialize(&params).unwrap();
+        assert_eq!(payload.pop(), Some(0));
+        payload.extend_from_slice(&[1, 2, 3]);
+
+        let v1 = Format {
+            version: 1,
+            migrations: &[],
+            ..FORMAT
+        };
+
+        let bytes = v1.write(&payload);
+        let bytes = FORMAT.read(&bytes).unwrap();
+        let mut file = Cursor::new(&bytes[..]);
+        let v: MeshParams = bincode::deserialize_from(&mut file).unwrap();
+        assert_eq!(v.num_verts, 3);
+        assert!(v.label.is_none());
+        assert_eq!(&bytes[file.position() as usize..], &[1, 2, 3]);
+    }
 }
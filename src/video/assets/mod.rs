@@ -1,3 +1,5 @@
+pub mod label;
+pub mod sampler;
 pub mod shader;
 pub mod surface;
 pub mod texture;
@@ -7,8 +9,12 @@ pub mod mesh;
 pub mod mesh_loader;
 
 pub mod prelude {
+    pub use super::label::Label;
+
     pub use super::surface::{SurfaceHandle, SurfaceParams, SurfaceScissor, SurfaceViewport};
 
+    pub use super::sampler::{SamplerHandle, SamplerParams};
+
     pub use super::shader::{
         Attribute, AttributeLayout, AttributeLayoutBuilder, BlendFactor, BlendValue, Comparison,
         CullFace, Equation, FrontFaceOrder, RenderState, ShaderHandle, ShaderParams,
@@ -0,0 +1,62 @@
+//! Sampling parameters which could be shared between textures.
+
+use crate::video::assets::shader::Comparison;
+use crate::video::assets::texture::{TextureFilter, TextureWrap};
+
+impl_handle!(SamplerHandle);
+
+/// The setup parameters of sampler object. A sampler object stores the sampling
+/// parameters for a texture access inside of a shader, it overrides the wrap and
+/// filter states baked into the texture it is bound with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SamplerParams {
+    /// Sets the wrap parameter for texture coordinate s (horizontal).
+    pub wrap_s: TextureWrap,
+    /// Sets the wrap parameter for texture coordinate t (vertical).
+    pub wrap_t: TextureWrap,
+    /// Specify how the texture is used whenever the pixel being textured maps to an
+    /// area greater than one texture element.
+    pub min_filter: TextureFilter,
+    /// Specify how the texture is used whenever the pixel being textured maps to an
+    /// area less than or equal to one texture element.
+    pub mag_filter: TextureFilter,
+    /// Specify how the mipmap levels are selected, mipmaps will not be sampled if `None`.
+    pub mip_filter: Option<TextureFilter>,
+    /// Bias added to the computed level-of-detail before sampling. This is ignored on
+    /// OpenGL ES and WebGL.
+    pub lod_bias: f32,
+    /// Compares the sampled depth value against the reference (the third texture
+    /// coordinate) with this function if specified. This is used with depth render
+    /// textures to implement shadow maps.
+    pub comparison: Option<Comparison>,
+}
+
+impl Default for SamplerParams {
+    fn default() -> Self {
+        SamplerParams {
+            wrap_s: TextureWrap::Clamp,
+            wrap_t: TextureWrap::Clamp,
+            min_filter: TextureFilter::Linear,
+            mag_filter: TextureFilter::Linear,
+            mip_filter: None,
+            lod_bias: 0.0,
+            comparison: None,
+        }
+    }
+}
+
+impl SamplerParams {
+    /// Sets the wrap parameter of both axes.
+    #[inline]
+    pub fn set_wrap(&mut self, wrap: TextureWrap) {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+    }
+
+    /// Sets both the minifying and magnification filters.
+    #[inline]
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        self.min_filter = filter;
+        self.mag_filter = filter;
+    }
+}
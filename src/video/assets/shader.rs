@@ -5,7 +5,9 @@ use std::str::FromStr;
 
 use crate::math::prelude::{Matrix2, Matrix3, Matrix4, Vector2, Vector3, Vector4};
 use crate::utils::prelude::{FastHashMap, HashValue};
+use crate::video::assets::label::Label;
 use crate::video::assets::mesh::VertexLayout;
+use crate::video::assets::sampler::SamplerHandle;
 use crate::video::assets::texture::{RenderTextureHandle, TextureHandle};
 use crate::video::errors::{Error, Result};
 use crate::video::{MAX_UNIFORM_VARIABLES, MAX_VERTEX_ATTRIBUTES};
@@ -19,6 +21,8 @@ pub struct ShaderParams {
     pub attributes: AttributeLayout,
     pub uniforms: UniformVariableLayout,
     pub state: RenderState,
+    /// Optional label used in logs and graphics debuggers.
+    pub label: Option<Label>,
 }
 
 impl ShaderParams {
@@ -302,6 +306,8 @@ impl Default for RenderState {
 pub enum UniformVariableType {
     Texture,
     RenderTexture,
+    /// Depth render texture with comparison, which is `sampler2DShadow` in GLSL.
+    ShadowTexture,
     I32,
     F32,
     Vector2f,
@@ -318,6 +324,13 @@ pub enum UniformVariableType {
 pub enum UniformVariable {
     Texture(TextureHandle),
     RenderTexture(RenderTextureHandle),
+    /// Texture sampled with the states of a sampler object instead of its own.
+    SampledTexture(TextureHandle, SamplerHandle),
+    /// Render texture sampled with the states of a sampler object instead of its own.
+    SampledRenderTexture(RenderTextureHandle, SamplerHandle),
+    /// Depth render texture sampled with comparison, the `comparison` of render texture
+    /// must be specified.
+    ShadowTexture(RenderTextureHandle),
     I32(i32),
     F32(f32),
     Vector2f([f32; 2]),
@@ -333,6 +346,9 @@ impl UniformVariable {
         match *self {
             UniformVariable::RenderTexture(_) => UniformVariableType::RenderTexture,
             UniformVariable::Texture(_) => UniformVariableType::Texture,
+            UniformVariable::SampledRenderTexture(_, _) => UniformVariableType::RenderTexture,
+            UniformVariable::SampledTexture(_, _) => UniformVariableType::Texture,
+            UniformVariable::ShadowTexture(_) => UniformVariableType::ShadowTexture,
             UniformVariable::I32(_) => UniformVariableType::I32,
             UniformVariable::F32(_) => UniformVariableType::F32,
             UniformVariable::Vector2f(_) => UniformVariableType::Vector2f,
@@ -343,6 +359,29 @@ impl UniformVariable {
             UniformVariable::Matrix4f(_, _) => UniformVariableType::Matrix4f,
         }
     }
+
+    /// Checks if the variable could be bound to the uniform of type `tp`, `comparison` tells
+    /// if the sampler object bound alongside compares depth. Render textures sampled with
+    /// comparison are bound to `ShadowTexture` uniforms, and comparison samplers are
+    /// rejected by the others.
+    pub fn is_compatible(&self, tp: UniformVariableType, comparison: bool) -> bool {
+        match *self {
+            UniformVariable::SampledRenderTexture(_, _) if comparison => {
+                tp == UniformVariableType::ShadowTexture
+            }
+            _ if comparison => false,
+            _ => tp == self.variable_type(),
+        }
+    }
+
+    /// Gets the sampler object bound alongside the texture if available.
+    pub fn sampler(&self) -> Option<SamplerHandle> {
+        match *self {
+            UniformVariable::SampledTexture(_, sampler) => Some(sampler),
+            UniformVariable::SampledRenderTexture(_, sampler) => Some(sampler),
+            _ => None,
+        }
+    }
 }
 
 impl Into<UniformVariable> for TextureHandle {
@@ -357,6 +396,18 @@ impl Into<UniformVariable> for RenderTextureHandle {
     }
 }
 
+impl Into<UniformVariable> for (TextureHandle, SamplerHandle) {
+    fn into(self) -> UniformVariable {
+        UniformVariable::SampledTexture(self.0, self.1)
+    }
+}
+
+impl Into<UniformVariable> for (RenderTextureHandle, SamplerHandle) {
+    fn into(self) -> UniformVariable {
+        UniformVariable::SampledRenderTexture(self.0, self.1)
+    }
+}
+
 impl Into<UniformVariable> for i32 {
     fn into(self) -> UniformVariable {
         UniformVariable::I32(self)
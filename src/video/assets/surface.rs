@@ -3,6 +3,7 @@
 
 use crate::math::prelude::{Color, Vector2};
 
+use crate::video::assets::label::Label;
 use crate::video::assets::texture::RenderTextureHandle;
 use crate::video::errors::{Error, Result};
 use crate::video::MAX_FRAMEBUFFER_ATTACHMENTS;
@@ -18,6 +19,8 @@ pub struct SurfaceParams {
     pub(crate) clear_color: Option<Color<f32>>,
     pub(crate) clear_depth: Option<f32>,
     pub(crate) clear_stencil: Option<i32>,
+    /// Optional label used in logs and graphics debuggers.
+    pub label: Option<Label>,
 }
 
 impl Default for SurfaceParams {
@@ -28,6 +31,7 @@ impl Default for SurfaceParams {
             clear_color: Some(Color::black()),
             clear_depth: Some(1.0),
             clear_stencil: None,
+            label: None,
         }
     }
 }
@@ -1,8 +1,13 @@
 //! Immutable or dynamic 2D texture. A texture is a container of one or more images. It
 //! can be the source of a texture access from a Shader.
 use crate::math::prelude::Vector2;
+use crate::video::assets::label::Label;
+use crate::video::assets::shader::Comparison;
 use crate::video::errors::{Error, Result};
 
+pub mod convert;
+pub mod decompress;
+
 impl_handle!(TextureHandle);
 
 /// The parameters of a texture object.
@@ -18,6 +23,9 @@ pub struct TextureParams {
     pub format: TextureFormat,
     /// Sets the dimensions of texture.
     pub dimensions: Vector2<u32>,
+    /// Optional label used in logs and graphics debuggers.
+    #[serde(default)]
+    pub label: Option<Label>,
 }
 
 impl Default for TextureParams {
@@ -28,6 +36,7 @@ impl Default for TextureParams {
             filter: TextureFilter::Linear,
             hint: TextureHint::Immutable,
             dimensions: Vector2::new(0, 0),
+            label: None,
         }
     }
 }
@@ -64,6 +73,13 @@ pub struct RenderTextureParams {
     pub filter: TextureFilter,
     pub dimensions: Vector2<u32>,
     pub sampler: bool,
+    /// Compares the sampled depth value against the reference (the third texture
+    /// coordinate) with this function if specified, which enables hardware PCF with
+    /// linear filter. It is only available for samplable depth formats, and the
+    /// render texture should be bound as `UniformVariable::ShadowTexture`.
+    pub comparison: Option<Comparison>,
+    /// Optional label used in logs and graphics debuggers.
+    pub label: Option<Label>,
 }
 
 impl Default for RenderTextureParams {
@@ -74,7 +90,25 @@ impl Default for RenderTextureParams {
             filter: TextureFilter::Linear,
             dimensions: Vector2::new(0, 0),
             sampler: true,
+            comparison: None,
+            label: None,
+        }
+    }
+}
+
+impl RenderTextureParams {
+    pub fn validate(&self) -> Result<()> {
+        if self.comparison.is_some() {
+            if !self.sampler {
+                return Err(Error::SampleRenderBuffer);
+            }
+
+            if self.format.is_color() {
+                return Err(Error::CompareColorTexture);
+            }
         }
+
+        Ok(())
     }
 }
 
@@ -262,4 +296,82 @@ impl TextureFormat {
             _ => false,
         }
     }
+
+    /// Returns the name of texture compression family, which is also used as the tag
+    /// of texture variants in manifest.
+    pub fn compression(self) -> Option<&'static str> {
+        match self {
+            TextureFormat::Etc2RGB4BPP | TextureFormat::Etc2RGBA8BPP => Some("etc2"),
+            TextureFormat::PvrtcRGB2BPP
+            | TextureFormat::PvrtcRGB4BPP
+            | TextureFormat::PvrtcRGBA2BPP
+            | TextureFormat::PvrtcRGBA4BPP => Some("pvrtc"),
+            TextureFormat::S3tcDxt1RGB4BPP | TextureFormat::S3tcDxt5RGBA8BPP => Some("s3tc"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEPTHS: [RenderTextureFormat; 4] = [
+        RenderTextureFormat::Depth16,
+        RenderTextureFormat::Depth24,
+        RenderTextureFormat::Depth32,
+        RenderTextureFormat::Depth24Stencil8,
+    ];
+
+    const COLORS: [RenderTextureFormat; 3] = [
+        RenderTextureFormat::RGB8,
+        RenderTextureFormat::RGBA4,
+        RenderTextureFormat::RGBA8,
+    ];
+
+    const COMPARISONS: [Comparison; 8] = [
+        Comparison::Never,
+        Comparison::Less,
+        Comparison::LessOrEqual,
+        Comparison::Greater,
+        Comparison::GreaterOrEqual,
+        Comparison::Equal,
+        Comparison::NotEqual,
+        Comparison::Always,
+    ];
+
+    #[test]
+    fn comparison() {
+        let mut params = RenderTextureParams::default();
+        params.sampler = true;
+
+        for &format in DEPTHS.iter().chain(COLORS.iter()) {
+            params.format = format;
+            params.comparison = None;
+            assert!(params.validate().is_ok());
+
+            for &comparison in &COMPARISONS {
+                params.comparison = Some(comparison);
+                match params.validate() {
+                    Ok(_) => assert!(!format.is_color()),
+                    Err(Error::CompareColorTexture) => assert!(format.is_color()),
+                    Err(err) => panic!("unexpected {}", err),
+                }
+            }
+        }
+
+        // Render buffers without sampler could not be compared.
+        params.sampler = false;
+        for &format in &DEPTHS {
+            params.format = format;
+            params.comparison = Some(Comparison::LessOrEqual);
+            match params.validate() {
+                Err(Error::SampleRenderBuffer) => {}
+                _ => panic!("comparison of render buffer is accepted"),
+            }
+
+            params.comparison = None;
+            assert!(params.validate().is_ok());
+        }
+    }
 }
@@ -0,0 +1,426 @@
+//! CPU-side conversions of uncompressed texture data.
+//!
+//! Pixels are decoded into normalized RGBA floats and encoded back into the target
+//! format. Missing channels are decoded as zero, except alpha which is one. Packed
+//! formats (RGB565, RGBA4, RGBA5551 and RGBA1010102) are laid out in native endian
+//! as required by the corresponding GL pixel types.
+
+use byteorder::{ByteOrder, NativeEndian};
+
+use crate::math::prelude::Vector2;
+use crate::video::errors::{Error, Result};
+
+use super::TextureFormat;
+
+/// The source of a channel when swizzling.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Swizzle {
+    R,
+    G,
+    B,
+    A,
+    Zero,
+    One,
+}
+
+/// Converts the texture data from `src` format into `dst` format.
+pub fn convert(
+    src: TextureFormat,
+    dst: TextureFormat,
+    dimensions: Vector2<u32>,
+    bytes: &[u8],
+) -> Result<Vec<u8>> {
+    validate(src, dimensions, bytes)?;
+    validate_format(dst)?;
+
+    if src == dst {
+        return Ok(bytes.to_vec());
+    }
+
+    let (src_stride, dst_stride) = (stride(src), stride(dst));
+    let mut out = vec![0; dst.size(dimensions) as usize];
+    for (from, to) in bytes.chunks(src_stride).zip(out.chunks_mut(dst_stride)) {
+        encode(dst, decode(src, from), to);
+    }
+
+    Ok(out)
+}
+
+/// Multiplies the color channels by alpha in place. Formats without alpha channel
+/// are left untouched.
+pub fn premultiply_alpha(
+    format: TextureFormat,
+    dimensions: Vector2<u32>,
+    bytes: &mut [u8],
+) -> Result<()> {
+    validate(format, dimensions, bytes)?;
+
+    if format.components() < 4 {
+        return Ok(());
+    }
+
+    for v in bytes.chunks_mut(stride(format)) {
+        let mut rgba = decode(format, v);
+        rgba[0] *= rgba[3];
+        rgba[1] *= rgba[3];
+        rgba[2] *= rgba[3];
+        encode(format, rgba, v);
+    }
+
+    Ok(())
+}
+
+/// Flips the texture data upside down in place.
+pub fn flip_vertically(
+    format: TextureFormat,
+    dimensions: Vector2<u32>,
+    bytes: &mut [u8],
+) -> Result<()> {
+    validate(format, dimensions, bytes)?;
+
+    let row = format.size(Vector2::new(dimensions.x, 1)) as usize;
+    let rows = dimensions.y as usize;
+    for i in 0..rows / 2 {
+        let (head, tail) = bytes.split_at_mut((rows - i - 1) * row);
+        head[i * row..(i + 1) * row].swap_with_slice(&mut tail[..row]);
+    }
+
+    Ok(())
+}
+
+/// Rearranges the channels in place. The n-th channel of the result is taken from
+/// `swizzle[n]`, entries beyond the components of `format` are ignored.
+pub fn swizzle(
+    format: TextureFormat,
+    dimensions: Vector2<u32>,
+    bytes: &mut [u8],
+    swizzle: [Swizzle; 4],
+) -> Result<()> {
+    validate(format, dimensions, bytes)?;
+
+    for v in bytes.chunks_mut(stride(format)) {
+        let src = decode(format, v);
+        let mut dst = [0.0; 4];
+        for (i, channel) in swizzle.iter().enumerate() {
+            dst[i] = match *channel {
+                Swizzle::R => src[0],
+                Swizzle::G => src[1],
+                Swizzle::B => src[2],
+                Swizzle::A => src[3],
+                Swizzle::Zero => 0.0,
+                Swizzle::One => 1.0,
+            };
+        }
+
+        encode(format, dst, v);
+    }
+
+    Ok(())
+}
+
+fn validate_format(format: TextureFormat) -> Result<()> {
+    if format.compressed() {
+        return Err(Error::TextureFormatUnsupported(format));
+    }
+
+    Ok(())
+}
+
+fn validate(format: TextureFormat, dimensions: Vector2<u32>, bytes: &[u8]) -> Result<()> {
+    validate_format(format)?;
+
+    if bytes.len() != format.size(dimensions) as usize {
+        return Err(Error::OutOfBounds);
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn stride(format: TextureFormat) -> usize {
+    format.size(Vector2::new(1, 1)) as usize
+}
+
+#[inline]
+fn unorm(v: u32, bits: u32) -> f32 {
+    v as f32 / ((1 << bits) - 1) as f32
+}
+
+#[inline]
+fn to_unorm(v: f32, bits: u32) -> u32 {
+    let max = ((1 << bits) - 1) as f32;
+    (v.clamp(0.0, 1.0) * max + 0.5) as u32
+}
+
+fn decode(format: TextureFormat, bytes: &[u8]) -> [f32; 4] {
+    let mut rgba = [0.0, 0.0, 0.0, 1.0];
+
+    match format {
+        TextureFormat::R8 | TextureFormat::RG8 | TextureFormat::RGB8 | TextureFormat::RGBA8 => {
+            for (i, v) in bytes.iter().enumerate() {
+                rgba[i] = unorm(u32::from(*v), 8);
+            }
+        }
+        TextureFormat::RGB565 => {
+            let v = u32::from(NativeEndian::read_u16(bytes));
+            rgba[0] = unorm(v >> 11, 5);
+            rgba[1] = unorm((v >> 5) & 0x3f, 6);
+            rgba[2] = unorm(v & 0x1f, 5);
+        }
+        TextureFormat::RGBA4 => {
+            let v = u32::from(NativeEndian::read_u16(bytes));
+            rgba[0] = unorm(v >> 12, 4);
+            rgba[1] = unorm((v >> 8) & 0xf, 4);
+            rgba[2] = unorm((v >> 4) & 0xf, 4);
+            rgba[3] = unorm(v & 0xf, 4);
+        }
+        TextureFormat::RGBA5551 => {
+            let v = u32::from(NativeEndian::read_u16(bytes));
+            rgba[0] = unorm(v >> 11, 5);
+            rgba[1] = unorm((v >> 6) & 0x1f, 5);
+            rgba[2] = unorm((v >> 1) & 0x1f, 5);
+            rgba[3] = unorm(v & 0x1, 1);
+        }
+        TextureFormat::RGBA1010102 => {
+            let v = NativeEndian::read_u32(bytes);
+            rgba[0] = unorm(v & 0x3ff, 10);
+            rgba[1] = unorm((v >> 10) & 0x3ff, 10);
+            rgba[2] = unorm((v >> 20) & 0x3ff, 10);
+            rgba[3] = unorm(v >> 30, 2);
+        }
+        TextureFormat::R16F
+        | TextureFormat::RG16F
+        | TextureFormat::RGB16F
+        | TextureFormat::RGBA16F => {
+            for (i, v) in bytes.chunks(2).enumerate() {
+                rgba[i] = f16_to_f32(NativeEndian::read_u16(v));
+            }
+        }
+        TextureFormat::R32F
+        | TextureFormat::RG32F
+        | TextureFormat::RGB32F
+        | TextureFormat::RGBA32F => {
+            for (i, v) in bytes.chunks(4).enumerate() {
+                rgba[i] = NativeEndian::read_f32(v);
+            }
+        }
+        _ => unreachable!(),
+    }
+
+    rgba
+}
+
+fn encode(format: TextureFormat, rgba: [f32; 4], bytes: &mut [u8]) {
+    match format {
+        TextureFormat::R8 | TextureFormat::RG8 | TextureFormat::RGB8 | TextureFormat::RGBA8 => {
+            for (i, v) in bytes.iter_mut().enumerate() {
+                *v = to_unorm(rgba[i], 8) as u8;
+            }
+        }
+        TextureFormat::RGB565 => {
+            let v =
+                (to_unorm(rgba[0], 5) << 11) | (to_unorm(rgba[1], 6) << 5) | to_unorm(rgba[2], 5);
+            NativeEndian::write_u16(bytes, v as u16);
+        }
+        TextureFormat::RGBA4 => {
+            let v = (to_unorm(rgba[0], 4) << 12)
+                | (to_unorm(rgba[1], 4) << 8)
+                | (to_unorm(rgba[2], 4) << 4)
+                | to_unorm(rgba[3], 4);
+            NativeEndian::write_u16(bytes, v as u16);
+        }
+        TextureFormat::RGBA5551 => {
+            let v = (to_unorm(rgba[0], 5) << 11)
+                | (to_unorm(rgba[1], 5) << 6)
+                | (to_unorm(rgba[2], 5) << 1)
+                | to_unorm(rgba[3], 1);
+            NativeEndian::write_u16(bytes, v as u16);
+        }
+        TextureFormat::RGBA1010102 => {
+            let v = to_unorm(rgba[0], 10)
+                | (to_unorm(rgba[1], 10) << 10)
+                | (to_unorm(rgba[2], 10) << 20)
+                | (to_unorm(rgba[3], 2) << 30);
+            NativeEndian::write_u32(bytes, v);
+        }
+        TextureFormat::R16F
+        | TextureFormat::RG16F
+        | TextureFormat::RGB16F
+        | TextureFormat::RGBA16F => {
+            for (i, v) in bytes.chunks_mut(2).enumerate() {
+                NativeEndian::write_u16(v, f32_to_f16(rgba[i]));
+            }
+        }
+        TextureFormat::R32F
+        | TextureFormat::RG32F
+        | TextureFormat::RGB32F
+        | TextureFormat::RGBA32F => {
+            for (i, v) in bytes.chunks_mut(4).enumerate() {
+                NativeEndian::write_f32(v, rgba[i]);
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Converts a IEEE 754 half-precision float into single-precision.
+pub fn f16_to_f32(v: u16) -> f32 {
+    let sign = u32::from(v & 0x8000) << 16;
+    let exp = u32::from((v >> 10) & 0x1f);
+    let man = u32::from(v & 0x3ff);
+
+    let bits = if exp == 0 {
+        if man == 0 {
+            sign
+        } else {
+            // Normalizes the subnormal number.
+            let (mut e, mut m) = (127 - 15 + 1, man);
+            while m & 0x400 == 0 {
+                m <<= 1;
+                e -= 1;
+            }
+
+            sign | (e << 23) | ((m & 0x3ff) << 13)
+        }
+    } else if exp == 0x1f {
+        sign | 0x7f80_0000 | (man << 13)
+    } else {
+        sign | ((exp + 127 - 15) << 23) | (man << 13)
+    };
+
+    f32::from_bits(bits)
+}
+
+/// Converts a single-precision float into IEEE 754 half-precision, rounding to the
+/// nearest even.
+pub fn f32_to_f16(v: f32) -> u16 {
+    let x = v.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xff) as i32;
+    let man = x & 0x7f_ffff;
+
+    if exp == 0xff {
+        let nan = if man != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    let (half, rem, halfway) = if e <= 0 {
+        if e < -10 {
+            return sign;
+        }
+
+        let m = man | 0x80_0000;
+        let shift = (14 - e) as u32;
+        (m >> shift, m & ((1 << shift) - 1), 1 << (shift - 1))
+    } else {
+        (((e as u32) << 10) | (man >> 13), man & 0x1fff, 0x1000)
+    };
+
+    // Rounds to nearest even, a carry into the exponent is still correct.
+    let half = if rem > halfway || (rem == halfway && half & 1 == 1) {
+        half + 1
+    } else {
+        half
+    };
+
+    sign | half as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn half() {
+        for &v in &[
+            0.0,
+            -0.0,
+            1.0,
+            -2.5,
+            0.333_251_95,
+            65504.0,
+            6.103_515_6e-5,
+            5.960_464_5e-8,
+        ] {
+            assert_eq!(f16_to_f32(f32_to_f16(v)), v);
+        }
+
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(65536.0), 0x7c00);
+        assert!(f16_to_f32(f32_to_f16(::std::f32::NAN)).is_nan());
+    }
+
+    #[test]
+    fn convert_formats() {
+        let dimensions = Vector2::new(2, 1);
+        let rgba = [255, 0, 0, 255, 0, 255, 0, 0];
+
+        let rgb565 = convert(
+            TextureFormat::RGBA8,
+            TextureFormat::RGB565,
+            dimensions,
+            &rgba,
+        );
+        let rgb565 = rgb565.unwrap();
+        assert_eq!(NativeEndian::read_u16(&rgb565[0..2]), 0xf800);
+        assert_eq!(NativeEndian::read_u16(&rgb565[2..4]), 0x07e0);
+
+        let rgb = convert(
+            TextureFormat::RGB565,
+            TextureFormat::RGB8,
+            dimensions,
+            &rgb565,
+        );
+        assert_eq!(rgb.unwrap(), vec![255, 0, 0, 0, 255, 0]);
+
+        let rgba16f = convert(
+            TextureFormat::RGBA8,
+            TextureFormat::RGBA16F,
+            dimensions,
+            &rgba,
+        );
+        let back = convert(
+            TextureFormat::RGBA16F,
+            TextureFormat::RGBA8,
+            dimensions,
+            &rgba16f.unwrap(),
+        );
+        assert_eq!(back.unwrap(), rgba.to_vec());
+
+        assert!(convert(
+            TextureFormat::RGBA8,
+            TextureFormat::RGB8,
+            dimensions,
+            &rgba[1..]
+        )
+        .is_err());
+        assert!(convert(
+            TextureFormat::RGBA8,
+            TextureFormat::Etc2RGB4BPP,
+            dimensions,
+            &rgba
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn premultiply_flip_and_swizzle() {
+        let dimensions = Vector2::new(1, 2);
+        let mut bytes = [255, 128, 0, 128, 10, 20, 30, 255];
+
+        premultiply_alpha(TextureFormat::RGBA8, dimensions, &mut bytes).unwrap();
+        assert_eq!(bytes, [128, 64, 0, 128, 10, 20, 30, 255]);
+
+        flip_vertically(TextureFormat::RGBA8, dimensions, &mut bytes).unwrap();
+        assert_eq!(bytes, [10, 20, 30, 255, 128, 64, 0, 128]);
+
+        let bgra = [Swizzle::B, Swizzle::G, Swizzle::R, Swizzle::One];
+        swizzle(TextureFormat::RGBA8, dimensions, &mut bytes, bgra).unwrap();
+        assert_eq!(bytes, [30, 20, 10, 255, 0, 64, 128, 255]);
+    }
+}
@@ -0,0 +1,431 @@
+//! Software decoders of compressed texture formats, which are used as fallback on
+//! devices that do not support the compression natively.
+//!
+//! ETC2 (RGB8 and RGBA8 with EAC alpha) and S3TC (DXT1 and DXT5) are supported, all
+//! of them are decoded into RGBA8.
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::math::prelude::Vector2;
+use crate::video::errors::{Error, Result};
+
+use super::TextureFormat;
+
+/// Checks if the texture format could be decoded into RGBA8 on the CPU.
+pub fn is_decompressible(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::Etc2RGB4BPP
+            | TextureFormat::Etc2RGBA8BPP
+            | TextureFormat::S3tcDxt1RGB4BPP
+            | TextureFormat::S3tcDxt5RGBA8BPP
+    )
+}
+
+/// Decodes the compressed texture data into RGBA8.
+pub fn decompress(
+    format: TextureFormat,
+    dimensions: Vector2<u32>,
+    bytes: &[u8],
+) -> Result<Vec<u8>> {
+    let block_size = match format {
+        TextureFormat::Etc2RGB4BPP | TextureFormat::S3tcDxt1RGB4BPP => 8,
+        TextureFormat::Etc2RGBA8BPP | TextureFormat::S3tcDxt5RGBA8BPP => 16,
+        _ => return Err(Error::TextureFormatUnsupported(format)),
+    };
+
+    let (w, h) = (dimensions.x as usize, dimensions.y as usize);
+    let (bw, bh) = (w.div_ceil(4), h.div_ceil(4));
+    if bytes.len() < bw * bh * block_size {
+        return Err(Error::OutOfBounds);
+    }
+
+    let mut out = vec![0; w * h * 4];
+    let mut texels = [[0u8; 4]; 16];
+
+    for (i, block) in bytes.chunks(block_size).take(bw * bh).enumerate() {
+        match format {
+            TextureFormat::Etc2RGB4BPP => etc2_rgb(block, &mut texels),
+            TextureFormat::Etc2RGBA8BPP => {
+                etc2_rgb(&block[8..], &mut texels);
+                eac_alpha(&block[..8], &mut texels);
+            }
+            TextureFormat::S3tcDxt1RGB4BPP => dxt_rgb(block, true, &mut texels),
+            TextureFormat::S3tcDxt5RGBA8BPP => {
+                dxt_rgb(&block[8..], false, &mut texels);
+                dxt_alpha(&block[..8], &mut texels);
+            }
+            _ => unreachable!(),
+        }
+
+        let (bx, by) = ((i % bw) * 4, (i / bw) * 4);
+        for y in 0..4.min(h - by) {
+            for x in 0..4.min(w - bx) {
+                let offset = ((by + y) * w + bx + x) * 4;
+                out[offset..offset + 4].copy_from_slice(&texels[y * 4 + x]);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+#[inline]
+fn clamp(v: i32) -> u8 {
+    v.clamp(0, 255) as u8
+}
+
+#[inline]
+fn extend(v: u32, bits: u32) -> i32 {
+    ((v << (8 - bits)) | (v >> (2 * bits - 8))) as i32
+}
+
+fn rgb565(v: u16) -> [i32; 3] {
+    let v = u32::from(v);
+    [
+        extend(v >> 11, 5),
+        extend((v >> 5) & 0x3f, 6),
+        extend(v & 0x1f, 5),
+    ]
+}
+
+/// Decodes the color block of S3TC, texels are stored in row-major order.
+fn dxt_rgb(block: &[u8], dxt1: bool, texels: &mut [[u8; 4]; 16]) {
+    let (v0, v1) = (
+        LittleEndian::read_u16(block),
+        LittleEndian::read_u16(&block[2..]),
+    );
+    let (c0, c1) = (rgb565(v0), rgb565(v1));
+
+    let mut palette = [[0u8; 4]; 4];
+    for i in 0..3 {
+        palette[0][i] = c0[i] as u8;
+        palette[1][i] = c1[i] as u8;
+
+        if !dxt1 || v0 > v1 {
+            palette[2][i] = ((2 * c0[i] + c1[i]) / 3) as u8;
+            palette[3][i] = ((c0[i] + 2 * c1[i]) / 3) as u8;
+        } else {
+            palette[2][i] = ((c0[i] + c1[i]) / 2) as u8;
+            palette[3][i] = 0;
+        }
+    }
+
+    for v in &mut palette {
+        v[3] = 255;
+    }
+
+    let indices = LittleEndian::read_u32(&block[4..]);
+    for (i, v) in texels.iter_mut().enumerate() {
+        *v = palette[((indices >> (2 * i)) & 0x3) as usize];
+    }
+}
+
+/// Decodes the alpha block of DXT5.
+fn dxt_alpha(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    let (a0, a1) = (u32::from(block[0]), u32::from(block[1]));
+
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 255];
+    if a0 > a1 {
+        for (i, v) in palette.iter_mut().enumerate().skip(2) {
+            let i = i as u32;
+            *v = ((8 - i) * a0 + (i - 1) * a1) / 7;
+        }
+    } else {
+        for (i, v) in palette.iter_mut().enumerate().take(6).skip(2) {
+            let i = i as u32;
+            *v = ((6 - i) * a0 + (i - 1) * a1) / 5;
+        }
+    }
+
+    let indices = LittleEndian::read_uint(&block[2..], 6);
+    for (i, v) in texels.iter_mut().enumerate() {
+        v[3] = palette[((indices >> (3 * i)) & 0x7) as usize] as u8;
+    }
+}
+
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+#[inline]
+fn bits(v: u64, from: u32, len: u32) -> u32 {
+    ((v >> from) & ((1 << len) - 1)) as u32
+}
+
+/// Decodes the ETC2 RGB block, texels are stored in row-major order.
+fn etc2_rgb(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    let v = BigEndian::read_u64(block);
+
+    // Pixel indices are stored in column-major order, with the most significant
+    // bits in the upper half.
+    let index = |x: usize, y: usize| {
+        let i = (x * 4 + y) as u32;
+        (bits(v, 16 + i, 1) << 1 | bits(v, i, 1)) as usize
+    };
+
+    let mut write = |x: usize, y: usize, rgb: [i32; 3]| {
+        texels[y * 4 + x] = [clamp(rgb[0]), clamp(rgb[1]), clamp(rgb[2]), 255];
+    };
+
+    let diff = bits(v, 33, 1) == 1;
+    let (r, g, b) = (
+        bits(v, 59, 5) as i32,
+        bits(v, 51, 5) as i32,
+        bits(v, 43, 5) as i32,
+    );
+    let signed = |from| ((bits(v, from, 3) as i32) << 29) >> 29;
+    let (dr, dg, db) = (signed(56), signed(48), signed(40));
+
+    if diff && !(0..32).contains(&(r + dr)) {
+        // T mode.
+        let c0 = [
+            extend(bits(v, 59, 2) << 2 | bits(v, 56, 2), 4),
+            extend(bits(v, 52, 4), 4),
+            extend(bits(v, 48, 4), 4),
+        ];
+
+        let c1 = [
+            extend(bits(v, 44, 4), 4),
+            extend(bits(v, 40, 4), 4),
+            extend(bits(v, 36, 4), 4),
+        ];
+
+        let d = ETC_DISTANCES[(bits(v, 34, 2) << 1 | bits(v, 32, 1)) as usize];
+        let paint = [
+            c0,
+            [c1[0] + d, c1[1] + d, c1[2] + d],
+            c1,
+            [c1[0] - d, c1[1] - d, c1[2] - d],
+        ];
+
+        for y in 0..4 {
+            for x in 0..4 {
+                write(x, y, paint[index(x, y)]);
+            }
+        }
+    } else if diff && !(0..32).contains(&(g + dg)) {
+        // H mode.
+        let c0 = [
+            bits(v, 59, 4),
+            bits(v, 56, 3) << 1 | bits(v, 52, 1),
+            bits(v, 51, 1) << 3 | bits(v, 47, 3),
+        ];
+
+        let c1 = [bits(v, 43, 4), bits(v, 39, 4), bits(v, 35, 4)];
+
+        let order = |c: [u32; 3]| c[0] << 8 | c[1] << 4 | c[2];
+        let ge = (order(c0) >= order(c1)) as u32;
+        let d = ETC_DISTANCES[(bits(v, 34, 1) << 2 | bits(v, 32, 1) << 1 | ge) as usize];
+
+        let c0 = [extend(c0[0], 4), extend(c0[1], 4), extend(c0[2], 4)];
+        let c1 = [extend(c1[0], 4), extend(c1[1], 4), extend(c1[2], 4)];
+        let paint = [
+            [c0[0] + d, c0[1] + d, c0[2] + d],
+            [c0[0] - d, c0[1] - d, c0[2] - d],
+            [c1[0] + d, c1[1] + d, c1[2] + d],
+            [c1[0] - d, c1[1] - d, c1[2] - d],
+        ];
+
+        for y in 0..4 {
+            for x in 0..4 {
+                write(x, y, paint[index(x, y)]);
+            }
+        }
+    } else if diff && !(0..32).contains(&(b + db)) {
+        // Planar mode.
+        let o = [
+            extend(bits(v, 57, 6), 6),
+            extend(bits(v, 56, 1) << 6 | bits(v, 49, 6), 7),
+            extend(
+                bits(v, 48, 1) << 5 | bits(v, 43, 2) << 3 | bits(v, 39, 3),
+                6,
+            ),
+        ];
+
+        let h = [
+            extend(bits(v, 34, 5) << 1 | bits(v, 32, 1), 6),
+            extend(bits(v, 25, 7), 7),
+            extend(bits(v, 19, 6), 6),
+        ];
+
+        let vv = [
+            extend(bits(v, 13, 6), 6),
+            extend(bits(v, 6, 7), 7),
+            extend(bits(v, 0, 6), 6),
+        ];
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let (xi, yi) = (x as i32, y as i32);
+                let mut rgb = [0; 3];
+                for c in 0..3 {
+                    rgb[c] = (xi * (h[c] - o[c]) + yi * (vv[c] - o[c]) + 4 * o[c] + 2) >> 2;
+                }
+
+                write(x, y, rgb);
+            }
+        }
+    } else {
+        // Individual or differential mode, which are compatible with ETC1.
+        let (c0, c1) = if diff {
+            (
+                [
+                    extend(r as u32, 5),
+                    extend(g as u32, 5),
+                    extend(b as u32, 5),
+                ],
+                [
+                    extend((r + dr) as u32, 5),
+                    extend((g + dg) as u32, 5),
+                    extend((b + db) as u32, 5),
+                ],
+            )
+        } else {
+            (
+                [
+                    extend(bits(v, 60, 4), 4),
+                    extend(bits(v, 52, 4), 4),
+                    extend(bits(v, 44, 4), 4),
+                ],
+                [
+                    extend(bits(v, 56, 4), 4),
+                    extend(bits(v, 48, 4), 4),
+                    extend(bits(v, 40, 4), 4),
+                ],
+            )
+        };
+
+        let tables = [bits(v, 37, 3) as usize, bits(v, 34, 3) as usize];
+        let flip = bits(v, 32, 1) == 1;
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let second = if flip { y >= 2 } else { x >= 2 };
+                let (base, table) = if second {
+                    (c1, ETC_MODIFIERS[tables[1]])
+                } else {
+                    (c0, ETC_MODIFIERS[tables[0]])
+                };
+
+                let modifier = match index(x, y) {
+                    0 => table[0],
+                    1 => table[1],
+                    2 => -table[0],
+                    _ => -table[1],
+                };
+
+                write(
+                    x,
+                    y,
+                    [base[0] + modifier, base[1] + modifier, base[2] + modifier],
+                );
+            }
+        }
+    }
+}
+
+/// Decodes the EAC alpha block of ETC2 RGBA8.
+fn eac_alpha(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    let v = BigEndian::read_u64(block);
+    let base = bits(v, 56, 8) as i32;
+    let multiplier = bits(v, 52, 4) as i32;
+    let table = EAC_MODIFIERS[bits(v, 48, 4) as usize];
+
+    for x in 0..4 {
+        for y in 0..4 {
+            let i = (x * 4 + y) as u32;
+            let modifier = table[bits(v, 45 - 3 * i, 3) as usize];
+            texels[y * 4 + x][3] = clamp(base + modifier * multiplier);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dxt1() {
+        // Red and blue endpoints, all the texels refer to the second one.
+        let block = [0x00, 0xf8, 0x1f, 0x00, 0x55, 0x55, 0x55, 0x55];
+        let rgba = decompress(TextureFormat::S3tcDxt1RGB4BPP, Vector2::new(4, 4), &block);
+        let rgba = rgba.unwrap();
+
+        assert_eq!(rgba.len(), 64);
+        for v in rgba.chunks(4) {
+            assert_eq!(v, [0, 0, 255, 255]);
+        }
+
+        // Partial blocks are clipped.
+        let rgba = decompress(TextureFormat::S3tcDxt1RGB4BPP, Vector2::new(2, 3), &block);
+        assert_eq!(rgba.unwrap().len(), 24);
+    }
+
+    #[test]
+    fn dxt5() {
+        let mut block = [0u8; 16];
+        // Alpha endpoints 255 and 0, all the texels refer to the first one.
+        block[0] = 255;
+        // White color endpoints.
+        block[8..12].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+
+        let rgba = decompress(TextureFormat::S3tcDxt5RGBA8BPP, Vector2::new(4, 4), &block);
+        for v in rgba.unwrap().chunks(4) {
+            assert_eq!(v, [255, 255, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn etc2() {
+        // Individual mode, with R1 = 15, R2 = 0 and all the indices refer to +2.
+        let block = [0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let rgba = decompress(TextureFormat::Etc2RGB4BPP, Vector2::new(4, 4), &block).unwrap();
+        assert_eq!(&rgba[0..4], [255, 2, 2, 255]);
+        assert_eq!(&rgba[8..12], [2, 2, 2, 255]);
+
+        // Differential mode, with base color (16, 8, 8) and no delta.
+        let block = [0x80, 0x40, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00];
+        let rgba = decompress(TextureFormat::Etc2RGB4BPP, Vector2::new(4, 4), &block).unwrap();
+        assert_eq!(&rgba[0..4], [134, 68, 68, 255]);
+        assert_eq!(&rgba[60..64], [134, 68, 68, 255]);
+
+        // EAC alpha with base 128 and zero multiplier.
+        let mut block = [0u8; 16];
+        block[0] = 128;
+        block[8..16].copy_from_slice(&[0xf0, 0, 0, 0, 0, 0, 0, 0]);
+        let rgba = decompress(TextureFormat::Etc2RGBA8BPP, Vector2::new(4, 4), &block).unwrap();
+        assert_eq!(&rgba[0..4], [255, 2, 2, 128]);
+
+        assert!(decompress(TextureFormat::Etc2RGB4BPP, Vector2::new(8, 4), &block[..8]).is_err());
+        assert!(decompress(TextureFormat::RGBA8, Vector2::new(1, 1), &[0; 4]).is_err());
+    }
+}
@@ -3,22 +3,60 @@ use std::io::Cursor;
 use std::sync::Arc;
 
 use crate::errors::*;
+use crate::math::prelude::Vector2;
+use crate::res::header::Format;
 use crate::res::utils::prelude::ResourceLoader;
 use crate::utils::double_buf::DoubleBuf;
 
 use super::super::backends::frame::{Command, Frame};
+use super::texture::decompress;
 use super::texture::*;
 
-pub const MAGIC: [u8; 8] = [b'V', b'T', b'E', b'X', b' ', 0, 0, 1];
+pub const FORMAT: Format = Format {
+    tag: *b"VTEX",
+    version: 2,
+    migrations: &[v1],
+};
+
+/// The texture parameters of version 1, which has no label.
+#[derive(Deserialize)]
+struct TextureParamsV1 {
+    hint: TextureHint,
+    wrap: TextureWrap,
+    filter: TextureFilter,
+    format: TextureFormat,
+    dimensions: Vector2<u32>,
+}
+
+fn v1(payload: &[u8]) -> Result<Vec<u8>> {
+    let mut file = Cursor::new(payload);
+    let v: TextureParamsV1 = bincode::deserialize_from(&mut file)?;
+    let params = TextureParams {
+        hint: v.hint,
+        wrap: v.wrap,
+        filter: v.filter,
+        format: v.format,
+        dimensions: v.dimensions,
+        label: None,
+    };
+
+    let mut bytes = bincode::serialize(&params)?;
+    bytes.extend_from_slice(&payload[file.position() as usize..]);
+    Ok(bytes)
+}
 
 #[derive(Clone)]
 pub struct TextureLoader {
     frames: Arc<DoubleBuf<Frame>>,
+    unsupported: Arc<Vec<TextureFormat>>,
 }
 
 impl TextureLoader {
-    pub(crate) fn new(frames: Arc<DoubleBuf<Frame>>) -> Self {
-        TextureLoader { frames }
+    pub(crate) fn new(frames: Arc<DoubleBuf<Frame>>, unsupported: Vec<TextureFormat>) -> Self {
+        TextureLoader {
+            frames,
+            unsupported: Arc::new(unsupported),
+        }
     }
 }
 
@@ -28,29 +66,54 @@ impl ResourceLoader for TextureLoader {
     type Resource = TextureParams;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if bytes[0..8] != MAGIC[..] {
-            bail!("[TextureLoader] MAGIC number not match.");
-        }
-        let mut file = Cursor::new(&bytes[8..]);
-        let params: TextureParams = bincode::deserialize_from(&mut file)?;
-        let data = bincode::deserialize_from(&mut file)?;
+        let payload = FORMAT.read(bytes)?;
+        let mut file = Cursor::new(&payload[..]);
+        let mut params: TextureParams = bincode::deserialize_from(&mut file)?;
+        let mut data = bincode::deserialize_from(&mut file)?;
         info!(
             "[TextureLoader] load {:?} ({}x{} - {:?}).",
             handle, params.dimensions.x, params.dimensions.y, params.format
         );
 
+        // Decodes on the worker threads of `sched`, instead of stalling the main thread
+        // while the texture is being created.
+        fallback(&self.unsupported, &mut params, Some(&mut data))?;
         Ok((params, Some(data)))
     }
 
     fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
         info!("[TextureLoader] create {:?}.", handle);
 
-        item.0.validate(item.1.as_ref())?;
+        let (params, data) = item;
+        params.validate(data.as_ref())?;
 
-        let cmd = Command::CreateTexture(Box::new((handle, item.0, item.1)));
+        let cmd = Command::CreateTexture(Box::new((handle, params, data)));
         self.frames.write().cmds.push(cmd);
 
-        Ok(item.0)
+        Ok(params)
+    }
+
+    fn reload(
+        &self,
+        handle: Self::Handle,
+        resource: &mut Self::Resource,
+        item: Self::Intermediate,
+    ) -> Result<()> {
+        info!("[TextureLoader] reload {:?}.", handle);
+
+        let (params, data) = item;
+        params.validate(data.as_ref())?;
+
+        // Recreates the backend object with the same handle, which happens before any
+        // draw calls of this frame.
+        let mut frame = self.frames.write();
+        frame.cmds.push(Command::DeleteTexture(handle));
+        frame
+            .cmds
+            .push(Command::CreateTexture(Box::new((handle, params, data))));
+
+        *resource = params;
+        Ok(())
     }
 
     fn delete(&self, handle: Self::Handle, _: Self::Resource) {
@@ -59,4 +122,44 @@ impl ResourceLoader for TextureLoader {
         let cmd = Command::DeleteTexture(handle);
         self.frames.write().cmds.push(cmd);
     }
+
+    fn size(&self, params: &Self::Resource) -> usize {
+        params.format.size(params.dimensions) as usize
+    }
+}
+
+/// Decodes the compressed texture into RGBA8 if its format is not supported by the
+/// device.
+pub(crate) fn fallback(
+    unsupported: &[TextureFormat],
+    params: &mut TextureParams,
+    data: Option<&mut TextureData>,
+) -> Result<()> {
+    if !unsupported.contains(&params.format) {
+        return Ok(());
+    }
+
+    if !decompress::is_decompressible(params.format) {
+        bail!(
+            "[TextureLoader] {:?} is not supported by device and can not be decoded.",
+            params.format
+        );
+    }
+
+    warn!(
+        "[TextureLoader] decodes {:?} to RGBA8 on the CPU.",
+        params.format
+    );
+
+    if let Some(data) = data {
+        let mut dimensions = params.dimensions;
+        for v in &mut data.bytes {
+            *v = decompress::decompress(params.format, dimensions, v)?.into_boxed_slice();
+            dimensions.x = (dimensions.x / 2).max(1);
+            dimensions.y = (dimensions.y / 2).max(1);
+        }
+    }
+
+    params.format = TextureFormat::RGBA8;
+    Ok(())
 }
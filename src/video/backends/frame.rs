@@ -1,8 +1,11 @@
+use std::fmt;
+
 use crate::errors::*;
 use crate::math::prelude::{Aabb2, Vector2};
 use crate::utils::prelude::{DataBuffer, DataBufferPtr, HashValue};
 
 use super::super::assets::prelude::*;
+use super::super::leaks::VideoResource;
 use super::Visitor;
 
 type VarsPtr = DataBufferPtr<[(HashValue<str>, UniformVariable)]>;
@@ -19,6 +22,7 @@ pub enum Command {
     DeleteSurface(SurfaceHandle),
 
     CreateShader(Box<(ShaderHandle, ShaderParams, String, String)>),
+    UpdateShader(Box<(ShaderHandle, ShaderParams, String, String)>),
     DeleteShader(ShaderHandle),
 
     CreateTexture(Box<(TextureHandle, TextureParams, Option<TextureData>)>),
@@ -28,6 +32,9 @@ pub enum Command {
     CreateRenderTexture(Box<(RenderTextureHandle, RenderTextureParams)>),
     DeleteRenderTexture(RenderTextureHandle),
 
+    CreateSampler(Box<(SamplerHandle, SamplerParams)>),
+    DeleteSampler(SamplerHandle),
+
     CreateMesh(Box<(MeshHandle, MeshParams, Option<MeshData>)>),
     UpdateVertexBuffer(MeshHandle, usize, BytesPtr),
     UpdateIndexBuffer(MeshHandle, usize, BytesPtr),
@@ -38,6 +45,9 @@ pub enum Command {
 pub struct Frame {
     pub cmds: Vec<Command>,
     pub bufs: DataBuffer,
+    /// The shaders that are recompiled successfully while dispatching, with their new
+    /// parameters.
+    pub shaders: Vec<(ShaderHandle, ShaderParams)>,
 }
 
 unsafe impl Send for Frame {}
@@ -49,6 +59,7 @@ impl Frame {
         Frame {
             cmds: Vec::with_capacity(16),
             bufs: DataBuffer::with_capacity(capacity),
+            shaders: Vec::new(),
         }
     }
 
@@ -56,9 +67,11 @@ impl Frame {
     pub fn clear(&mut self) {
         self.cmds.clear();
         self.bufs.clear();
+        self.shaders.clear();
     }
 
-    /// Dispatch frame tasks and draw calls to the backend context.
+    /// Dispatch frame tasks and draw calls to the backend context. The command being
+    /// dispatched, with the handles it touches, is attached to the errors.
     pub fn dispatch(
         &mut self,
         visitor: &mut Visitor,
@@ -68,80 +81,69 @@ impl Frame {
             visitor.advance()?;
 
             let (mut dc, mut tris) = (0, 0);
+            let mut surface = None;
+
             for v in self.cmds.drain(..) {
-                match v {
-                    Command::Bind(surface) => {
-                        visitor.bind(surface, dimensions)?;
+                let site = Site::new(&v, surface);
+
+                let result = match v {
+                    Command::Bind(handle) => {
+                        surface = Some(handle);
+                        visitor.bind(handle, dimensions)
                     }
 
                     Command::Draw(shader, mesh, mesh_index, ptr) => {
                         let vars = self.bufs.as_slice(ptr);
                         dc += 1;
-                        tris += visitor.draw(shader, mesh, mesh_index, vars)?;
-                    }
-
-                    Command::UpdateScissor(scissor) => {
-                        visitor.update_surface_scissor(scissor)?;
-                    }
-
-                    Command::UpdateViewport(view) => {
-                        visitor.update_surface_viewport(view)?;
-                    }
-
-                    Command::CreateSurface(v) => {
-                        visitor.create_surface(v.0, v.1)?;
-                    }
-
-                    Command::DeleteSurface(handle) => {
-                        visitor.delete_surface(handle)?;
-                    }
-
-                    Command::CreateShader(v) => {
-                        visitor.create_shader(v.0, v.1, &v.2, &v.3)?;
+                        visitor
+                            .draw(shader, mesh, mesh_index, vars)
+                            .map(|v| tris += v)
                     }
 
-                    Command::DeleteShader(handle) => {
-                        visitor.delete_shader(handle)?;
-                    }
-
-                    Command::CreateTexture(v) => {
-                        visitor.create_texture(v.0, v.1, v.2)?;
+                    Command::UpdateScissor(scissor) => visitor.update_surface_scissor(scissor),
+                    Command::UpdateViewport(view) => visitor.update_surface_viewport(view),
+                    Command::CreateSurface(v) => visitor.create_surface(v.0, v.1),
+                    Command::DeleteSurface(handle) => visitor.delete_surface(handle),
+                    Command::CreateShader(v) => visitor.create_shader(v.0, v.1, &v.2, &v.3),
+                    Command::UpdateShader(v) => {
+                        let (handle, params) = (v.0, v.1.clone());
+                        let shaders = &mut self.shaders;
+                        visitor.update_shader(v.0, v.1, &v.2, &v.3).map(|updated| {
+                            if updated {
+                                shaders.push((handle, params));
+                            }
+                        })
                     }
+                    Command::DeleteShader(handle) => visitor.delete_shader(handle),
+                    Command::CreateTexture(v) => visitor.create_texture(v.0, v.1, v.2),
 
                     Command::UpdateTexture(handle, area, ptr) => {
                         let data = self.bufs.as_slice(ptr);
-                        visitor.update_texture(handle, area, data)?;
-                    }
-
-                    Command::DeleteTexture(handle) => {
-                        visitor.delete_texture(handle)?;
-                    }
-
-                    Command::CreateRenderTexture(v) => {
-                        visitor.create_render_texture(v.0, v.1)?;
-                    }
-
-                    Command::DeleteRenderTexture(handle) => {
-                        visitor.delete_render_texture(handle)?;
+                        visitor.update_texture(handle, area, data)
                     }
 
-                    Command::CreateMesh(v) => {
-                        visitor.create_mesh(v.0, v.1, v.2)?;
-                    }
+                    Command::DeleteTexture(handle) => visitor.delete_texture(handle),
+                    Command::CreateRenderTexture(v) => visitor.create_render_texture(v.0, v.1),
+                    Command::DeleteRenderTexture(handle) => visitor.delete_render_texture(handle),
+                    Command::CreateSampler(v) => visitor.create_sampler(v.0, v.1),
+                    Command::DeleteSampler(handle) => visitor.delete_sampler(handle),
+                    Command::CreateMesh(v) => visitor.create_mesh(v.0, v.1, v.2),
 
                     Command::UpdateVertexBuffer(handle, offset, ptr) => {
                         let data = self.bufs.as_slice(ptr);
-                        visitor.update_vertex_buffer(handle, offset, data)?;
+                        visitor.update_vertex_buffer(handle, offset, data)
                     }
 
                     Command::UpdateIndexBuffer(handle, offset, ptr) => {
                         let data = self.bufs.as_slice(ptr);
-                        visitor.update_index_buffer(handle, offset, data)?;
+                        visitor.update_index_buffer(handle, offset, data)
                     }
 
-                    Command::DeleteMesh(handle) => {
-                        visitor.delete_mesh(handle)?;
-                    }
+                    Command::DeleteMesh(handle) => visitor.delete_mesh(handle),
+                };
+
+                if let Err(err) = result {
+                    bail!("[Frame] Failed to dispatch {}. {}", site, err);
                 }
             }
 
@@ -151,3 +153,136 @@ impl Frame {
         }
     }
 }
+
+/// The handles touched by a command, which are attached to the errors of dispatching.
+#[derive(Debug, Clone, Copy)]
+enum Site {
+    Draw(Option<SurfaceHandle>, ShaderHandle, MeshHandle, MeshIndex),
+    Surface(&'static str, Option<SurfaceHandle>),
+    Resource(&'static str, VideoResource),
+}
+
+impl Site {
+    fn new(cmd: &Command, surface: Option<SurfaceHandle>) -> Self {
+        match *cmd {
+            Command::Bind(handle) => Site::Resource("binding", handle.into()),
+            Command::Draw(shader, mesh, index, _) => Site::Draw(surface, shader, mesh, index),
+            Command::UpdateScissor(_) => Site::Surface("scissor update", surface),
+            Command::UpdateViewport(_) => Site::Surface("viewport update", surface),
+            Command::CreateSurface(ref v) => Site::Resource("creation", v.0.into()),
+            Command::DeleteSurface(handle) => Site::Resource("deletion", handle.into()),
+            Command::CreateShader(ref v) => Site::Resource("creation", v.0.into()),
+            Command::UpdateShader(ref v) => Site::Resource("update", v.0.into()),
+            Command::DeleteShader(handle) => Site::Resource("deletion", handle.into()),
+            Command::CreateTexture(ref v) => Site::Resource("creation", v.0.into()),
+            Command::UpdateTexture(handle, _, _) => Site::Resource("update", handle.into()),
+            Command::DeleteTexture(handle) => Site::Resource("deletion", handle.into()),
+            Command::CreateRenderTexture(ref v) => Site::Resource("creation", v.0.into()),
+            Command::DeleteRenderTexture(handle) => Site::Resource("deletion", handle.into()),
+            Command::CreateSampler(ref v) => Site::Resource("creation", v.0.into()),
+            Command::DeleteSampler(handle) => Site::Resource("deletion", handle.into()),
+            Command::CreateMesh(ref v) => Site::Resource("creation", v.0.into()),
+            Command::UpdateVertexBuffer(handle, _, _) => Site::Resource("update", handle.into()),
+            Command::UpdateIndexBuffer(handle, _, _) => Site::Resource("update", handle.into()),
+            Command::DeleteMesh(handle) => Site::Resource("deletion", handle.into()),
+        }
+    }
+}
+
+impl fmt::Display for Site {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Site::Draw(surface, shader, mesh, index) => {
+                write!(f, "draw of {:?} ({:?}) with {:?}", mesh, index, shader)?;
+                match surface {
+                    Some(surface) => write!(f, " on {:?}", surface),
+                    None => write!(f, " without surface"),
+                }
+            }
+            Site::Surface(name, Some(surface)) => write!(f, "{} of {:?}", name, surface),
+            Site::Surface(name, None) => write!(f, "{} without surface", name),
+            Site::Resource(name, resource) => write!(f, "{} of {:?}", name, resource),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::headless::HeadlessVisitor;
+    use super::*;
+    use crate::utils::handle::HandleLike;
+
+    #[test]
+    fn update_shader() {
+        let mut params = ShaderParams::default();
+        params.label = Some("v2".into());
+
+        let handle = ShaderHandle::default();
+        let mut frame = Frame::with_capacity(0);
+        let cmd = (handle, params, String::new(), String::new());
+        frame.cmds.push(Command::UpdateShader(Box::new(cmd)));
+
+        let mut visitor = HeadlessVisitor::new();
+        frame.dispatch(&mut visitor, Vector2::new(1, 1)).unwrap();
+        assert_eq!(frame.shaders.len(), 1);
+        assert_eq!(frame.shaders[0].0, handle);
+        assert_eq!(frame.shaders[0].1.label, Some("v2".into()));
+
+        frame.clear();
+        assert!(frame.shaders.is_empty());
+    }
+
+    #[test]
+    fn shadow() {
+        let uniforms = UniformVariableLayout::build()
+            .with("shadow", UniformVariableType::ShadowTexture)
+            .with("color", UniformVariableType::RenderTexture)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.uniforms = uniforms;
+
+        let mut sampler = SamplerParams::default();
+        sampler.comparison = Some(Comparison::LessOrEqual);
+
+        let (shader, rt) = (ShaderHandle::default(), RenderTextureHandle::default());
+        let (linear, compare) = (SamplerHandle::default(), SamplerHandle::new(1, 0));
+
+        let mut visitor = HeadlessVisitor::new();
+        let mut frame = Frame::with_capacity(0);
+        let cmd = (shader, params, String::new(), String::new());
+        frame.cmds.push(Command::CreateShader(Box::new(cmd)));
+        let cmd = (linear, SamplerParams::default());
+        frame.cmds.push(Command::CreateSampler(Box::new(cmd)));
+        let cmd = (compare, sampler);
+        frame.cmds.push(Command::CreateSampler(Box::new(cmd)));
+        frame.dispatch(&mut visitor, Vector2::new(1, 1)).unwrap();
+
+        let mut draw = |field: &str, variable: UniformVariable| {
+            let mut frame = Frame::with_capacity(64);
+            let ptr = frame
+                .bufs
+                .extend_from_slice(&[(HashValue::from(field), variable)]);
+            let cmd = Command::Draw(shader, MeshHandle::default(), MeshIndex::All, ptr);
+            frame.cmds.push(cmd);
+            frame.dispatch(&mut visitor, Vector2::new(1, 1))
+        };
+
+        // The render texture sampled with comparison is bound to `sampler2DShadow` only.
+        let v = UniformVariable::SampledRenderTexture(rt, compare);
+        assert!(draw("shadow", v).is_ok());
+        assert!(draw("color", v).is_err());
+
+        let v = UniformVariable::SampledRenderTexture(rt, linear);
+        assert!(draw("shadow", v).is_err());
+        assert!(draw("color", v).is_ok());
+
+        assert!(draw("shadow", UniformVariable::ShadowTexture(rt)).is_ok());
+        let v = UniformVariable::SampledTexture(TextureHandle::default(), compare);
+        assert!(draw("color", v).is_err());
+
+        // The samplers that have been deleted are rejected.
+        let v = UniformVariable::SampledRenderTexture(rt, SamplerHandle::new(2, 0));
+        assert!(draw("color", v).is_err());
+    }
+}
@@ -193,6 +193,27 @@ impl From<TextureWrap> for GLenum {
     }
 }
 
+impl From<TextureFilter> for GLenum {
+    fn from(filter: TextureFilter) -> Self {
+        match filter {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+/// Combines the minifying filter with the filter used to select mipmap levels.
+pub fn min_filter(filter: TextureFilter, mip: Option<TextureFilter>) -> GLenum {
+    match (filter, mip) {
+        (TextureFilter::Nearest, None) => gl::NEAREST,
+        (TextureFilter::Linear, None) => gl::LINEAR,
+        (TextureFilter::Nearest, Some(TextureFilter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+        (TextureFilter::Nearest, Some(TextureFilter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+        (TextureFilter::Linear, Some(TextureFilter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+        (TextureFilter::Linear, Some(TextureFilter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
 impl From<RenderTextureFormat> for (GLenum, GLenum, GLenum) {
     fn from(format: RenderTextureFormat) -> Self {
         match format {
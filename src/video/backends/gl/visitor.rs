@@ -1,4 +1,7 @@
 use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::c_void;
+use std::{ptr, slice};
 
 use gl;
 use gl::types::*;
@@ -9,6 +12,7 @@ use crate::math::prelude::{Aabb2, Color, Vector2};
 use crate::utils::hash::{FastHashMap, FastHashSet};
 use crate::utils::hash_value::HashValue;
 
+use super::super::super::assets::label::Labelled;
 use super::super::super::assets::prelude::*;
 use super::super::utils::DataVec;
 use super::super::{UniformVar, Visitor};
@@ -74,7 +78,7 @@ impl GLShaderData {
 struct GLMeshData {
     handle: MeshHandle,
     vbo: GLuint,
-    ibo: GLuint,
+    ibo: Option<GLuint>,
     params: MeshParams,
 }
 
@@ -93,6 +97,13 @@ struct GLRenderTextureData {
     params: RenderTextureParams,
 }
 
+#[derive(Debug, Copy, Clone)]
+struct GLSamplerData {
+    handle: SamplerHandle,
+    params: SamplerParams,
+    id: GLuint,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Sampler {
     RenderTexture(RenderTextureHandle),
@@ -110,6 +121,7 @@ struct GLMutableState {
     binded_vao: Option<(ShaderHandle, MeshHandle)>,
     binded_texture_index: usize,
     binded_textures: SmallVec<[Option<Sampler>; 8]>,
+    binded_samplers: SmallVec<[Option<SamplerHandle>; 8]>,
 }
 
 pub struct GLVisitor {
@@ -120,6 +132,7 @@ pub struct GLVisitor {
     meshes: DataVec<GLMeshData>,
     textures: DataVec<GLTextureData>,
     render_textures: DataVec<GLRenderTextureData>,
+    samplers: DataVec<GLSamplerData>,
 }
 
 impl GLVisitor {
@@ -142,6 +155,7 @@ impl GLVisitor {
             binded_vao: None,
             binded_texture_index: 0,
             binded_textures: SmallVec::new(),
+            binded_samplers: SmallVec::new(),
         };
 
         let mut visitor = GLVisitor {
@@ -152,14 +166,20 @@ impl GLVisitor {
             meshes: DataVec::new(),
             textures: DataVec::new(),
             render_textures: DataVec::new(),
+            samplers: DataVec::new(),
         };
 
         Self::reset_render_state(&mut visitor.state)?;
+        debug_output(&visitor.capabilities);
         Ok(visitor)
     }
 }
 
 impl Visitor for GLVisitor {
+    fn is_texture_format_supported(&self, format: TextureFormat) -> bool {
+        format.is_support(&self.capabilities)
+    }
+
     unsafe fn advance(&mut self) -> Result<()> {
         self.state.cleared_surfaces.clear();
         self.state.binded_surface = None;
@@ -178,6 +198,8 @@ impl Visitor for GLVisitor {
             dimensions: None,
         };
 
+        let name = Labelled(handle, params.label);
+
         if params.colors[0].is_some() || params.depth_stencil.is_some() {
             let mut id = 0;
             gl::GenFramebuffers(1, &mut id);
@@ -195,17 +217,11 @@ impl Visitor for GLVisitor {
                         .ok_or_else(|| format_err!("RenderTexture handle {:?} is invalid.", v))?;
 
                     if !rt.params.format.is_color() {
-                        bail!(
-                            "Incompitable(mismatch format) attachments of SurfaceObject {:?}",
-                            id
-                        );
+                        bail!("Incompitable(mismatch format) attachments of {}", name);
                     }
 
                     if dimensions.is_some() && dimensions != Some(rt.params.dimensions) {
-                        bail!(
-                            "Incompitable(mismatch dimensons) attachments of SurfaceObject {:?}",
-                            id
-                        );
+                        bail!("Incompitable(mismatch dimensons) attachments of {}", name);
                     }
 
                     dimensions = Some(rt.params.dimensions);
@@ -220,17 +236,11 @@ impl Visitor for GLVisitor {
                     .ok_or_else(|| format_err!("RenderTexture handle {:?} is invalid.", v))?;
 
                 if rt.params.format.is_color() {
-                    bail!(
-                        "Incompitable(mismatch format) attachments of SurfaceObject {:?}",
-                        id
-                    );
+                    bail!("Incompitable(mismatch format) attachments of {}", name);
                 }
 
                 if dimensions.is_some() && dimensions != Some(rt.params.dimensions) {
-                    bail!(
-                        "Incompitable(mismatch dimensions) attachments of SurfaceObject {:?}",
-                        id
-                    );
+                    bail!("Incompitable(mismatch dimensions) attachments of {}", name);
                 }
 
                 dimensions = Some(rt.params.dimensions);
@@ -244,29 +254,33 @@ impl Visitor for GLVisitor {
 
                 match status {
                     gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => {
-                        bail!("[GL] Surface is incomplete. Not all framebuffer attachment points \
+                        bail!("[GL] {} is incomplete. Not all framebuffer attachment points \
                         are framebuffer attachment complete. This means that at least one attachment point with a \
                         renderbuffer or texture attached has its attached object no longer in existence or has an \
                         attached image with a width or height of zero, or the color attachment point has a non-color-renderable \
                         image attached, or the depth attachment point has a non-depth-renderable image attached, or \
-                        the stencil attachment point has a non-stencil-renderable image attached. ");
+                        the stencil attachment point has a non-stencil-renderable image attached. ", name);
                     }
 
                     gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => {
-                        bail!("[GL] Surface is incomplete. No images are attached to the framebuffer.");
+                        bail!(
+                            "[GL] {} is incomplete. No images are attached to the framebuffer.",
+                            name
+                        );
                     }
 
                     gl::FRAMEBUFFER_UNSUPPORTED => {
-                        bail!("[GL] Surface is incomplete. The combination of internal formats \
-                        of the attached images violates an implementation-dependent set of restrictions. ");
+                        bail!("[GL] {} is incomplete. The combination of internal formats \
+                        of the attached images violates an implementation-dependent set of restrictions. ", name);
                     }
 
                     _ => {
-                        bail!("[GL] Surface is incomplete.");
+                        bail!("[GL] {} is incomplete.", name);
                     }
                 }
             }
 
+            object_label(gl::FRAMEBUFFER, id, params.label);
             data.id = Some(id);
             data.dimensions = dimensions;
         };
@@ -301,9 +315,13 @@ impl Visitor for GLVisitor {
         vs: &str,
         fs: &str,
     ) -> Result<()> {
-        let vs = Self::compile(gl::VERTEX_SHADER, vs)?;
-        let fs = Self::compile(gl::FRAGMENT_SHADER, fs)?;
-        let id = Self::link(&[vs, fs])?;
+        let name = Labelled(handle, params.label);
+        let vs = Self::compile(gl::VERTEX_SHADER, vs)
+            .map_err(|err| format_err!("Failed to compile vertex shader of {}. {}", name, err))?;
+        let fs = Self::compile(gl::FRAGMENT_SHADER, fs)
+            .map_err(|err| format_err!("Failed to compile fragment shader of {}. {}", name, err))?;
+        let id =
+            Self::link(&[vs, fs]).map_err(|err| format_err!("Failed to link {}. {}", name, err))?;
 
         gl::DetachShader(id, vs);
         gl::DeleteShader(vs);
@@ -319,49 +337,61 @@ impl Visitor for GLVisitor {
             attributes: RefCell::new(FastHashMap::default()),
         };
 
-        for (name, _, _) in shader.params.attributes.iter() {
-            let name: &'static str = name.into();
-            let location = shader.attribute_location(name)?;
+        for (attr, _, _) in shader.params.attributes.iter() {
+            let attr: &'static str = attr.into();
+            let location = shader.attribute_location(attr)?;
             if location == -1 {
                 gl::DeleteProgram(id);
-                bail!("Attribute({:?}) is undefined in shader sources.", name);
+                bail!("Attribute({:?}) is undefined in sources of {}.", attr, name);
             }
         }
 
-        for &(ref name, _) in shader.params.uniforms.iter() {
-            let location = shader.uniform_location(name)?;
+        for &(ref field, _) in shader.params.uniforms.iter() {
+            let location = shader.uniform_location(field)?;
             if location == -1 {
                 gl::DeleteProgram(id);
-                bail!("Uniform({:?}) is undefined in shader sources.", name);
+                bail!("Uniform({:?}) is undefined in sources of {}.", field, name);
             }
         }
 
+        object_label(gl::PROGRAM, id, shader.params.label);
         self.shaders.create(handle, shader);
         Ok(())
     }
 
-    unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()> {
-        let shader = self
+    unsafe fn update_shader(
+        &mut self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: &str,
+        fs: &str,
+    ) -> Result<bool> {
+        let prev = self
             .shaders
             .free(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        // Removes deprecated `VertexArrayObject`s.
-        self.state.vaos.retain(|&(h, _), vao| {
-            if h == shader.handle {
-                gl::DeleteVertexArrays(1, vao as *mut u32);
-                false
-            } else {
-                true
-            }
-        });
-
-        if self.state.binded_shader == Some(handle) {
-            self.state.binded_shader = None;
+        // Keeps the previous program if the new one failed to compile.
+        if let Err(err) = self.create_shader(handle, params, vs, fs) {
+            warn!(
+                "Failed to recompile {:?}, keeps the previous program. {}",
+                handle, err
+            );
+            self.shaders.create(handle, prev);
+            return Ok(false);
         }
 
-        gl::DeleteProgram(shader.id);
-        check()
+        Self::release_shader(&mut self.state, prev)?;
+        Ok(true)
+    }
+
+    unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()> {
+        let shader = self
+            .shaders
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        Self::release_shader(&mut self.state, shader)
     }
 
     unsafe fn create_texture(
@@ -370,11 +400,11 @@ impl Visitor for GLVisitor {
         params: TextureParams,
         data: Option<TextureData>,
     ) -> Result<()> {
-        // Maybe we should implements some software decoder for common texture compression format.
         if !params.format.is_support(&self.capabilities) {
             bail!(
-                "The GL Context does not support the texture format {:?}.",
-                params.format
+                "The GL Context does not support the texture format {:?} of {}.",
+                params.format,
+                Labelled(handle, params.label)
             );
         }
 
@@ -437,6 +467,11 @@ impl Visitor for GLVisitor {
             }
         }
 
+        if params.label.is_some() {
+            Self::bind_texture(&mut self.state, Some(Sampler::Texture(handle)), 0, id)?;
+            object_label(gl::TEXTURE, id, params.label);
+        }
+
         check()?;
 
         self.textures.create(
@@ -463,19 +498,20 @@ impl Visitor for GLVisitor {
             .get(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
+        let name = Labelled(handle, texture.params.label);
         if texture.params.hint == TextureHint::Immutable {
-            bail!("Trying to update immutable texture.");
+            bail!("Trying to update immutable texture {}.", name);
         }
 
         if texture.params.format.compressed() {
-            bail!("Trying to update compressed texture.");
+            bail!("Trying to update compressed texture {}.", name);
         }
 
         if data.len() > texture.params.format.size(area.dim()) as usize
             || area.min.x >= texture.params.dimensions.x
             || area.min.y >= texture.params.dimensions.y
         {
-            bail!("Trying to update texture data out of bounds.");
+            bail!("Trying to update texture data of {} out of bounds.", name);
         }
 
         let (internal_format, format, pixel_type) =
@@ -550,6 +586,13 @@ impl Visitor for GLVisitor {
             Self::bind_texture(&mut self.state, Some(Sampler::RenderTexture(handle)), 0, id)?;
             Self::bind_texture_params(params.wrap, params.filter, 1)?;
 
+            if let Some(comparison) = params.comparison {
+                let func: GLenum = comparison.into();
+                let mode = gl::COMPARE_REF_TO_TEXTURE as GLint;
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_MODE, mode);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_FUNC, func as GLint);
+            }
+
             let (internal_format, format, pixel_type) = params.format.into();
             gl::TexImage2D(
                 gl::TEXTURE_2D,
@@ -563,6 +606,7 @@ impl Visitor for GLVisitor {
                 ::std::ptr::null(),
             );
 
+            object_label(gl::TEXTURE, id, params.label);
             id
         } else {
             let mut id = 0;
@@ -577,6 +621,8 @@ impl Visitor for GLVisitor {
                 params.dimensions.x as GLint,
                 params.dimensions.y as GLint,
             );
+
+            object_label(gl::RENDERBUFFER, id, params.label);
             id
         };
 
@@ -609,6 +655,63 @@ impl Visitor for GLVisitor {
         check()
     }
 
+    unsafe fn create_sampler(
+        &mut self,
+        handle: SamplerHandle,
+        params: SamplerParams,
+    ) -> Result<()> {
+        if !gl::GenSamplers::is_loaded() {
+            bail!("Sampler object is not supported.");
+        }
+
+        let mut id = 0;
+        gl::GenSamplers(1, &mut id);
+
+        let wrap_s: GLenum = params.wrap_s.into();
+        let wrap_t: GLenum = params.wrap_t.into();
+        let min_filter = types::min_filter(params.min_filter, params.mip_filter);
+        let mag_filter: GLenum = params.mag_filter.into();
+
+        gl::SamplerParameteri(id, gl::TEXTURE_WRAP_S, wrap_s as GLint);
+        gl::SamplerParameteri(id, gl::TEXTURE_WRAP_T, wrap_t as GLint);
+        gl::SamplerParameteri(id, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+        gl::SamplerParameteri(id, gl::TEXTURE_MAG_FILTER, mag_filter as GLint);
+
+        // LOD bias is not a sampler parameter in OpenGL ES.
+        if let Version::GL(_, _) = self.capabilities.version {
+            gl::SamplerParameterf(id, gl::TEXTURE_LOD_BIAS, params.lod_bias);
+        }
+
+        if let Some(comparison) = params.comparison {
+            let func: GLenum = comparison.into();
+            let mode = gl::COMPARE_REF_TO_TEXTURE as GLint;
+            gl::SamplerParameteri(id, gl::TEXTURE_COMPARE_MODE, mode);
+            gl::SamplerParameteri(id, gl::TEXTURE_COMPARE_FUNC, func as GLint);
+        }
+
+        check()?;
+        self.samplers
+            .create(handle, GLSamplerData { handle, params, id });
+
+        Ok(())
+    }
+
+    unsafe fn delete_sampler(&mut self, handle: SamplerHandle) -> Result<()> {
+        let sampler = self
+            .samplers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        for v in self.state.binded_samplers.iter_mut() {
+            if *v == Some(handle) {
+                *v = None;
+            }
+        }
+
+        gl::DeleteSamplers(1, &sampler.id);
+        check()
+    }
+
     unsafe fn create_mesh(
         &mut self,
         handle: MeshHandle,
@@ -622,12 +725,23 @@ impl Visitor for GLVisitor {
             data.as_ref().map(|v| v.vptr.as_ref()),
         )?;
 
-        let ibo = self.create_buffer(
-            gl::ELEMENT_ARRAY_BUFFER,
-            params.hint,
-            params.index_buffer_len(),
-            data.as_ref().map(|v| v.iptr.as_ref()),
-        )?;
+        let ibo = if params.is_indexed() {
+            let ibo = self.create_buffer(
+                gl::ELEMENT_ARRAY_BUFFER,
+                params.hint,
+                params.index_buffer_len(),
+                data.as_ref().map(|v| v.iptr.as_ref()),
+            )?;
+
+            Some(ibo)
+        } else {
+            None
+        };
+
+        object_label(gl::BUFFER, vbo, params.label);
+        if let Some(ibo) = ibo {
+            object_label(gl::BUFFER, ibo, params.label);
+        }
 
         self.meshes.create(
             handle,
@@ -655,7 +769,10 @@ impl Visitor for GLVisitor {
                 .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
             if mesh.params.hint == MeshHint::Immutable {
-                bail!("Trying to update immutable buffer");
+                bail!(
+                    "Trying to update immutable buffer of {}.",
+                    Labelled(handle, mesh.params.label)
+                );
             }
 
             mesh.vbo
@@ -678,10 +795,18 @@ impl Visitor for GLVisitor {
                 .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
             if mesh.params.hint == MeshHint::Immutable {
-                bail!("Trying to update immutable buffer");
+                bail!(
+                    "Trying to update immutable buffer of {}.",
+                    Labelled(handle, mesh.params.label)
+                );
             }
 
-            mesh.ibo
+            mesh.ibo.ok_or_else(|| {
+                format_err!(
+                    "{} does not have indices.",
+                    Labelled(handle, mesh.params.label)
+                )
+            })?
         };
 
         Self::update_buffer(gl::ELEMENT_ARRAY_BUFFER, ibo, offset, data)?;
@@ -705,7 +830,10 @@ impl Visitor for GLVisitor {
         });
 
         gl::DeleteBuffers(1, &mesh.vbo);
-        gl::DeleteBuffers(1, &mesh.ibo);
+        if let Some(ibo) = mesh.ibo {
+            gl::DeleteBuffers(1, &ibo);
+        }
+
         check()
     }
 
@@ -777,22 +905,39 @@ impl Visitor for GLVisitor {
 
         Self::bind_shader(&mut self.state, &shader)?;
 
+        let samplers = &self.samplers;
         let mut index = 0usize;
         for &(field, variable) in uniforms {
             if let Some(tp) = shader.params.uniforms.variable_type(field) {
-                if tp != variable.variable_type() {
-                    let name = shader.params.uniforms.variable_name(field).unwrap();
+                let name = shader.params.uniforms.variable_name(field).unwrap();
+                let sampler = match variable.sampler() {
+                    Some(v) => match samplers.get(v) {
+                        Some(sampler) => Some(sampler),
+                        None => bail!("The sampler {:?} of uniform {} is invalid.", v, name),
+                    },
+                    None => None,
+                };
+
+                let comparison = sampler.map(|v| v.params.comparison.is_some());
+                if !variable.is_compatible(tp, comparison == Some(true)) {
                     bail!(
-                        "The uniform {} needs a {:?} instead of {:?}.",
+                        "The uniform {} of {} needs a {:?} instead of {:?}{}.",
                         name,
+                        Labelled(shader.handle, shader.params.label),
                         tp,
                         variable.variable_type(),
+                        if comparison == Some(true) {
+                            " with comparison"
+                        } else {
+                            ""
+                        },
                     );
                 }
 
                 let location = shader.hash_uniform_location(field).unwrap();
                 match variable {
-                    UniformVariable::Texture(handle) => {
+                    UniformVariable::Texture(handle)
+                    | UniformVariable::SampledTexture(handle, _) => {
                         let v = UniformVariable::I32(index as i32);
                         Self::bind_uniform_variable(location, &v)?;
 
@@ -807,15 +952,34 @@ impl Visitor for GLVisitor {
                             Self::bind_texture(&mut self.state, None, index, 0)?;
                         }
 
+                        Self::bind_sampler(&mut self.state, sampler, index)?;
                         index += 1;
                     }
-                    UniformVariable::RenderTexture(handle) => {
+                    UniformVariable::RenderTexture(handle)
+                    | UniformVariable::SampledRenderTexture(handle, _)
+                    | UniformVariable::ShadowTexture(handle) => {
                         let v = UniformVariable::I32(index as i32);
                         Self::bind_uniform_variable(location, &v)?;
 
                         if let Some(texture) = self.render_textures.get(handle) {
                             if !texture.params.sampler {
-                                bail!("The render buffer does not have a sampler.");
+                                bail!(
+                                    "The render buffer {} does not have a sampler.",
+                                    Labelled(handle, texture.params.label)
+                                );
+                            }
+
+                            // The comparison of sampler object overrides the one of texture.
+                            let shadow = tp == UniformVariableType::ShadowTexture;
+                            let comparison =
+                                comparison.unwrap_or_else(|| texture.params.comparison.is_some());
+
+                            if shadow != comparison {
+                                bail!(
+                                    "The depth comparison of {} does not match {:?}.",
+                                    Labelled(handle, texture.params.label),
+                                    tp
+                                );
                             }
 
                             Self::bind_texture(
@@ -828,6 +992,7 @@ impl Visitor for GLVisitor {
                             Self::bind_texture(&mut self.state, None, index, 0)?;
                         }
 
+                        Self::bind_sampler(&mut self.state, sampler, index)?;
                         index += 1;
                     }
                     _ => {
@@ -835,7 +1000,11 @@ impl Visitor for GLVisitor {
                     }
                 }
             } else {
-                bail!("Undefined uniform field {:?}.", field);
+                bail!(
+                    "Undefined uniform field {:?} of {}.",
+                    field,
+                    Labelled(shader.handle, shader.params.label)
+                );
             }
         }
 
@@ -843,39 +1012,87 @@ impl Visitor for GLVisitor {
             // Bind vertex buffer and vertex array object.
             Self::bind_mesh(&mut self.state, &shader, &mesh)?;
 
-            let (from, len) = match mesh_index {
-                MeshIndex::Ptr(from, len) => {
-                    if (from + len) > mesh.params.num_idxes {
-                        bail!("MeshIndex is out of bounds");
+            let name = Labelled(mesh.handle, mesh.params.label);
+
+            let num = mesh.params.num_elements();
+            let (from, len, base) = match mesh_index {
+                MeshIndex::Ptr(from, len) => (from, len, None),
+                MeshIndex::Vertices(from, len) => {
+                    let end = from.checked_add(len);
+                    if end.map_or(true, |v| v > mesh.params.num_verts) {
+                        bail!("MeshIndex is out of bounds of {}.", name);
                     }
 
-                    ((from * mesh.params.index_format.stride()), len)
+                    gl::DrawArrays(mesh.params.primitive.into(), from as i32, len as i32);
+                    check()?;
+                    return Ok(mesh.params.primitive.assemble(len as u32));
+                }
+                MeshIndex::BaseVertex(from, len, base) => {
+                    if mesh.ibo.is_none() {
+                        bail!("{} does not have indices.", name);
+                    }
+
+                    if base >= mesh.params.num_verts {
+                        bail!("MeshIndex is out of bounds of {}.", name);
+                    }
+
+                    (from, len, Some(base))
                 }
                 MeshIndex::SubMesh(index) => {
-                    let num = mesh.params.sub_mesh_offsets.len();
-                    let from = mesh
+                    let from =
+                        *mesh.params.sub_mesh_offsets.get(index).ok_or_else(|| {
+                            format_err!("MeshIndex is out of bounds of {}.", name)
+                        })?;
+
+                    let to = mesh
                         .params
                         .sub_mesh_offsets
-                        .get(index)
-                        .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
+                        .get(index + 1)
+                        .cloned()
+                        .unwrap_or(num);
 
-                    let to = if index == (num - 1) {
-                        mesh.params.num_idxes
-                    } else {
-                        mesh.params.sub_mesh_offsets[index + 1]
-                    };
-
-                    ((from * mesh.params.index_format.stride()), (to - from))
+                    // The offsets are strictly increasing, see `MeshParams::validate`.
+                    (from, to - from, None)
                 }
-                MeshIndex::All => (0, mesh.params.num_idxes),
+                MeshIndex::All => (0, num, None),
             };
 
-            gl::DrawElements(
-                mesh.params.primitive.into(),
-                len as i32,
-                mesh.params.index_format.into(),
-                from as *const u32 as *const ::std::os::raw::c_void,
-            );
+            // The ranges are supplied by callers, which might overflow.
+            if from.checked_add(len).map_or(true, |v| v > num) {
+                bail!("MeshIndex is out of bounds of {}.", name);
+            }
+
+            if mesh.ibo.is_none() {
+                gl::DrawArrays(mesh.params.primitive.into(), from as i32, len as i32);
+                check()?;
+                return Ok(mesh.params.primitive.assemble(len as u32));
+            }
+
+            let primitive = mesh.params.primitive.into();
+            let format = mesh.params.index_format.into();
+            let ptr = (from * mesh.params.index_format.stride()) as *const u32
+                as *const ::std::os::raw::c_void;
+
+            match base {
+                Some(base) if gl::DrawElementsBaseVertex::is_loaded() => {
+                    gl::DrawElementsBaseVertex(primitive, len as i32, format, ptr, base as i32);
+                }
+                Some(base) => {
+                    // Emulates base vertex by offsetting the attribute pointers temporarily.
+                    // They are always restored, otherwise the later draws of this mesh
+                    // would read from the offset.
+                    let rsp = Self::bind_vertex_attributes(shader, mesh, base).and_then(|_| {
+                        gl::DrawElements(primitive, len as i32, format, ptr);
+                        check()
+                    });
+
+                    Self::bind_vertex_attributes(shader, mesh, 0)?;
+                    rsp?;
+                }
+                None => {
+                    gl::DrawElements(primitive, len as i32, format, ptr);
+                }
+            }
 
             check()?;
             Ok(mesh.params.primitive.assemble(len as u32))
@@ -919,6 +1136,9 @@ impl GLVisitor {
         match *variable {
             UniformVariable::Texture(_) => unreachable!(),
             UniformVariable::RenderTexture(_) => unreachable!(),
+            UniformVariable::SampledTexture(_, _) => unreachable!(),
+            UniformVariable::SampledRenderTexture(_, _) => unreachable!(),
+            UniformVariable::ShadowTexture(_) => unreachable!(),
             UniformVariable::I32(v) => gl::Uniform1i(location, v),
             UniformVariable::F32(v) => gl::Uniform1f(location, v),
             UniformVariable::Vector2f(v) => gl::Uniform2f(location, v[0], v[1]),
@@ -964,6 +1184,43 @@ impl GLVisitor {
         check()
     }
 
+    unsafe fn release_shader(state: &mut GLMutableState, shader: GLShaderData) -> Result<()> {
+        // Removes deprecated `VertexArrayObject`s.
+        state.vaos.retain(|&(h, _), vao| {
+            if h == shader.handle {
+                gl::DeleteVertexArrays(1, vao as *mut u32);
+                false
+            } else {
+                true
+            }
+        });
+
+        if state.binded_shader == Some(shader.handle) {
+            state.binded_shader = None;
+        }
+
+        gl::DeleteProgram(shader.id);
+        check()
+    }
+
+    unsafe fn bind_sampler(
+        state: &mut GLMutableState,
+        sampler: Option<&GLSamplerData>,
+        index: usize,
+    ) -> Result<()> {
+        if state.binded_samplers.len() <= index {
+            state.binded_samplers.resize(index + 1, None);
+        }
+
+        let handle = sampler.map(|v| v.handle);
+        if state.binded_samplers[index] != handle {
+            state.binded_samplers[index] = handle;
+            gl::BindSampler(index as GLuint, sampler.map(|v| v.id).unwrap_or(0));
+        }
+
+        check()
+    }
+
     unsafe fn bind_mesh(
         state: &mut GLMutableState,
         shader: &GLShaderData,
@@ -980,39 +1237,7 @@ impl GLVisitor {
                 let mut vao = 0;
                 gl::GenVertexArrays(1, &mut vao);
                 gl::BindVertexArray(vao);
-                gl::BindBuffer(gl::ARRAY_BUFFER, mesh.vbo);
-
-                for (name, size, required) in shader.params.attributes.iter() {
-                    if let Some(element) = mesh.params.layout.element(name) {
-                        if element.size < size {
-                            bail!(
-                                "Vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
-                                name,
-                                element.size,
-                                size
-                            );
-                        }
-
-                        let offset = mesh.params.layout.offset(name).unwrap();
-                        let stride = mesh.params.layout.stride();
-
-                        let location = shader.attribute_location(name.into())?;
-                        gl::EnableVertexAttribArray(location as GLuint);
-                        gl::VertexAttribPointer(
-                            location as GLuint,
-                            GLsizei::from(element.size),
-                            element.format.into(),
-                            element.normalized as u8,
-                            GLsizei::from(stride),
-                            offset as *const u8 as *const ::std::os::raw::c_void,
-                        );
-                    } else if required {
-                        bail!(
-                            "Can't find attribute {:?} description in vertex buffer.",
-                            name
-                        );
-                    }
-                }
+                Self::bind_vertex_attributes(shader, mesh, 0)?;
 
                 check()?;
                 state.vaos.insert(k, vao);
@@ -1021,9 +1246,57 @@ impl GLVisitor {
             state.binded_vao = Some(k);
         }
 
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo);
+        if let Some(ibo) = mesh.ibo {
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo);
+        }
+
         Ok(())
     }
+
+    /// Specifies the vertex attribute pointers of currently binded vertex array object,
+    /// starting at the `base` vertex.
+    unsafe fn bind_vertex_attributes(
+        shader: &GLShaderData,
+        mesh: &GLMeshData,
+        base: usize,
+    ) -> Result<()> {
+        gl::BindBuffer(gl::ARRAY_BUFFER, mesh.vbo);
+
+        for (name, size, required) in shader.params.attributes.iter() {
+            if let Some(element) = mesh.params.layout.element(name) {
+                if element.size < size {
+                    bail!(
+                        "Vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
+                        name,
+                        element.size,
+                        size
+                    );
+                }
+
+                let stride = mesh.params.layout.stride();
+                let offset =
+                    mesh.params.layout.offset(name).unwrap() as usize + base * stride as usize;
+
+                let location = shader.attribute_location(name.into())?;
+                gl::EnableVertexAttribArray(location as GLuint);
+                gl::VertexAttribPointer(
+                    location as GLuint,
+                    GLsizei::from(element.size),
+                    element.format.into(),
+                    element.normalized as u8,
+                    GLsizei::from(stride),
+                    offset as *const u8 as *const ::std::os::raw::c_void,
+                );
+            } else if required {
+                bail!(
+                    "Can't find attribute {:?} description in vertex buffer.",
+                    name
+                );
+            }
+        }
+
+        check()
+    }
 }
 
 impl GLVisitor {
@@ -1528,26 +1801,115 @@ unsafe fn check_capabilities(caps: &Capabilities) -> Result<()> {
     Ok(())
 }
 
-unsafe fn check() -> Result<()> {
-    match gl::GetError() {
-        gl::NO_ERROR => Ok(()),
+/// Labels the GL object with KHR_debug if available, which is useful in graphics debuggers.
+unsafe fn object_label(identifier: GLenum, id: GLuint, label: Option<Label>) {
+    if let Some(label) = label {
+        if gl::ObjectLabel::is_loaded() {
+            let label = label.as_str();
+            gl::ObjectLabel(
+                identifier,
+                id,
+                label.len() as GLsizei,
+                label.as_ptr() as *const GLchar,
+            );
+        }
+    }
+}
+
+thread_local! {
+    static DEBUG_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Installs the `glDebugMessageCallback` if the context is created in debug mode, so
+/// the messages of driver are routed through `log`.
+unsafe fn debug_output(caps: &Capabilities) {
+    if !caps.debug {
+        return;
+    }
 
-        gl::INVALID_ENUM => {
-            bail!("[GL] An unacceptable value is specified for an enumerated argument.")
+    if !gl::DebugMessageCallback::is_loaded() {
+        warn!("[GL] The debug context does not support KHR_debug.");
+        return;
+    }
+
+    gl::Enable(gl::DEBUG_OUTPUT);
+    // Makes sure that the messages are reported by the thread that issues the commands.
+    gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+    gl::DebugMessageCallback(debug_message, ptr::null());
+    info!("[GL] Debug output is enabled.");
+}
+
+extern "system" fn debug_message(
+    source: GLenum,
+    tp: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _: *mut c_void,
+) {
+    let message = unsafe {
+        if length < 0 {
+            CStr::from_ptr(message).to_string_lossy()
+        } else {
+            String::from_utf8_lossy(slice::from_raw_parts(message as *const u8, length as usize))
         }
+    };
+
+    let source = match source {
+        gl::DEBUG_SOURCE_API => "API",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "WindowSystem",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "ShaderCompiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "ThirdParty",
+        gl::DEBUG_SOURCE_APPLICATION => "Application",
+        _ => "Other",
+    };
+
+    let kind = match tp {
+        gl::DEBUG_TYPE_ERROR => "Error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "DeprecatedBehavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "UndefinedBehavior",
+        gl::DEBUG_TYPE_PORTABILITY => "Portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "Performance",
+        gl::DEBUG_TYPE_MARKER => "Marker",
+        _ => "Other",
+    };
+
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => error!("[GL] {} {} ({}): {}", source, kind, id, message),
+        gl::DEBUG_SEVERITY_MEDIUM => warn!("[GL] {} {} ({}): {}", source, kind, id, message),
+        gl::DEBUG_SEVERITY_LOW => info!("[GL] {} {} ({}): {}", source, kind, id, message),
+        _ => debug!("[GL] {} {} ({}): {}", source, kind, id, message),
+    }
 
-        gl::INVALID_VALUE => bail!("[GL] A numeric argument is out of range."),
+    // Keeps the message of error, which will be attached to the error of `check`.
+    if tp == gl::DEBUG_TYPE_ERROR {
+        DEBUG_ERROR.with(|v| *v.borrow_mut() = Some(message.into_owned()));
+    }
+}
 
-        gl::INVALID_OPERATION => {
-            bail!("[GL] The specified operation is not allowed in the current state.")
+unsafe fn check() -> Result<()> {
+    let description = match gl::GetError() {
+        gl::NO_ERROR => {
+            DEBUG_ERROR.with(|v| v.borrow_mut().take());
+            return Ok(());
         }
 
-        gl::INVALID_FRAMEBUFFER_OPERATION => bail!(
-            r"[GL] The command is trying to render to or read from the framebufferwhile the \
+        gl::INVALID_ENUM => "An unacceptable value is specified for an enumerated argument.",
+        gl::INVALID_VALUE => "A numeric argument is out of range.",
+        gl::INVALID_OPERATION => "The specified operation is not allowed in the current state.",
+
+        gl::INVALID_FRAMEBUFFER_OPERATION => {
+            r"The command is trying to render to or read from the framebufferwhile the \
             currently bound framebuffer is not framebuffer complete."
-        ),
+        }
+
+        gl::OUT_OF_MEMORY => "There is not enough memory left to execute the command.",
+        _ => "Oops, Unknown OpenGL error.",
+    };
 
-        gl::OUT_OF_MEMORY => bail!("[GL] There is not enough memory left to execute the command."),
-        _ => bail!("[GL] Oops, Unknown OpenGL error."),
+    match DEBUG_ERROR.with(|v| v.borrow_mut().take()) {
+        Some(message) => bail!("[GL] {} {}", description, message),
+        None => bail!("[GL] {}", description),
     }
 }
@@ -3,16 +3,27 @@ use super::{UniformVar, Visitor};
 
 use crate::errors::*;
 use crate::math::prelude::{Aabb2, Vector2};
-
-pub struct HeadlessVisitor {}
+use crate::utils::hash::FastHashMap;
+
+/// A visitor that draws nothing. It keeps the parameters of shaders and samplers, so the
+/// uniforms of draw calls are checked the same as GL backends.
+#[derive(Default)]
+pub struct HeadlessVisitor {
+    shaders: FastHashMap<ShaderHandle, ShaderParams>,
+    samplers: FastHashMap<SamplerHandle, SamplerParams>,
+}
 
 impl HeadlessVisitor {
     pub fn new() -> Self {
-        HeadlessVisitor {}
+        Default::default()
     }
 }
 
 impl Visitor for HeadlessVisitor {
+    fn is_texture_format_supported(&self, _: TextureFormat) -> bool {
+        true
+    }
+
     unsafe fn create_surface(&mut self, _: SurfaceHandle, _: SurfaceParams) -> Result<()> {
         Ok(())
     }
@@ -23,15 +34,28 @@ impl Visitor for HeadlessVisitor {
 
     unsafe fn create_shader(
         &mut self,
-        _: ShaderHandle,
-        _: ShaderParams,
+        handle: ShaderHandle,
+        params: ShaderParams,
         _: &str,
         _: &str,
     ) -> Result<()> {
+        self.shaders.insert(handle, params);
         Ok(())
     }
 
-    unsafe fn delete_shader(&mut self, _: ShaderHandle) -> Result<()> {
+    unsafe fn update_shader(
+        &mut self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        _: &str,
+        _: &str,
+    ) -> Result<bool> {
+        self.shaders.insert(handle, params);
+        Ok(true)
+    }
+
+    unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()> {
+        self.shaders.remove(&handle);
         Ok(())
     }
 
@@ -64,6 +88,20 @@ impl Visitor for HeadlessVisitor {
         Ok(())
     }
 
+    unsafe fn create_sampler(
+        &mut self,
+        handle: SamplerHandle,
+        params: SamplerParams,
+    ) -> Result<()> {
+        self.samplers.insert(handle, params);
+        Ok(())
+    }
+
+    unsafe fn delete_sampler(&mut self, handle: SamplerHandle) -> Result<()> {
+        self.samplers.remove(&handle);
+        Ok(())
+    }
+
     unsafe fn create_mesh(
         &mut self,
         _: MeshHandle,
@@ -91,11 +129,42 @@ impl Visitor for HeadlessVisitor {
 
     unsafe fn draw(
         &mut self,
-        _: ShaderHandle,
+        shader: ShaderHandle,
         _: MeshHandle,
         _: MeshIndex,
-        _: &[UniformVar],
+        uniforms: &[UniformVar],
     ) -> Result<u32> {
+        let params = self
+            .shaders
+            .get(&shader)
+            .ok_or_else(|| format_err!("{:?} is invalid.", shader))?;
+
+        for &(field, variable) in uniforms {
+            let tp = params.uniforms.variable_type(field).ok_or_else(|| {
+                format_err!("Undefined uniform field {:?} of {:?}.", field, shader)
+            })?;
+
+            let name = params.uniforms.variable_name(field).unwrap();
+            let comparison = match variable.sampler() {
+                Some(v) => match self.samplers.get(&v) {
+                    Some(sampler) => sampler.comparison.is_some(),
+                    None => bail!("The sampler {:?} of uniform {} is invalid.", v, name),
+                },
+                None => false,
+            };
+
+            if !variable.is_compatible(tp, comparison) {
+                bail!(
+                    "The uniform {} of {:?} needs a {:?} instead of {:?}{}.",
+                    name,
+                    shader,
+                    tp,
+                    variable.variable_type(),
+                    if comparison { " with comparison" } else { "" },
+                );
+            }
+        }
+
         Ok(0)
     }
 
@@ -14,6 +14,9 @@ use crate::utils::hash_value::HashValue;
 pub type UniformVar = (HashValue<str>, UniformVariable);
 
 pub trait Visitor {
+    /// Checks if the texture format could be uploaded to the device directly.
+    fn is_texture_format_supported(&self, format: TextureFormat) -> bool;
+
     unsafe fn create_surface(&mut self, handle: SurfaceHandle, params: SurfaceParams)
         -> Result<()>;
 
@@ -27,6 +30,17 @@ pub trait Visitor {
         fs: &str,
     ) -> Result<()>;
 
+    /// Recompiles the shader in place with the same handle. The previous program should
+    /// be kept if the new sources failed to compile, and false is returned instead of an
+    /// error, so the rest of frame is still dispatched.
+    unsafe fn update_shader(
+        &mut self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: &str,
+        fs: &str,
+    ) -> Result<bool>;
+
     unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()>;
 
     unsafe fn create_texture(
@@ -53,6 +67,11 @@ pub trait Visitor {
 
     unsafe fn delete_render_texture(&mut self, handle: RenderTextureHandle) -> Result<()>;
 
+    unsafe fn create_sampler(&mut self, handle: SamplerHandle, params: SamplerParams)
+        -> Result<()>;
+
+    unsafe fn delete_sampler(&mut self, handle: SamplerHandle) -> Result<()>;
+
     unsafe fn create_mesh(
         &mut self,
         handle: MeshHandle,
@@ -99,6 +99,27 @@ impl From<TextureWrap> for u32 {
     }
 }
 
+impl From<TextureFilter> for u32 {
+    fn from(filter: TextureFilter) -> Self {
+        match filter {
+            TextureFilter::Nearest => WebGL::NEAREST,
+            TextureFilter::Linear => WebGL::LINEAR,
+        }
+    }
+}
+
+/// Combines the minifying filter with the filter used to select mipmap levels.
+pub fn min_filter(filter: TextureFilter, mip: Option<TextureFilter>) -> u32 {
+    match (filter, mip) {
+        (TextureFilter::Nearest, None) => WebGL::NEAREST,
+        (TextureFilter::Linear, None) => WebGL::LINEAR,
+        (TextureFilter::Nearest, Some(TextureFilter::Nearest)) => WebGL::NEAREST_MIPMAP_NEAREST,
+        (TextureFilter::Nearest, Some(TextureFilter::Linear)) => WebGL::NEAREST_MIPMAP_LINEAR,
+        (TextureFilter::Linear, Some(TextureFilter::Nearest)) => WebGL::LINEAR_MIPMAP_NEAREST,
+        (TextureFilter::Linear, Some(TextureFilter::Linear)) => WebGL::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
 impl From<TextureFormat> for (u32, u32, u32) {
     fn from(format: TextureFormat) -> Self {
         // FIXME
@@ -3,7 +3,7 @@ use std::cell::RefCell;
 use smallvec::SmallVec;
 use web_sys::{
     self, HtmlCanvasElement, WebGlBuffer, WebGlFramebuffer, WebGlProgram, WebGlRenderbuffer,
-    WebGlShader, WebGlTexture, WebGlUniformLocation, WebGlVertexArrayObject,
+    WebGlSampler, WebGlShader, WebGlTexture, WebGlUniformLocation, WebGlVertexArrayObject,
 };
 
 use wasm_bindgen::JsCast;
@@ -13,11 +13,13 @@ use crate::errors::*;
 use crate::math::prelude::*;
 use crate::utils::hash::{FastHashMap, FastHashSet};
 use crate::utils::hash_value::HashValue;
+use crate::video::assets::label::Labelled;
 use crate::video::assets::prelude::*;
 
 use super::super::utils::DataVec;
 use super::super::{UniformVar, Visitor};
 use super::capabilities::Capabilities;
+use super::types;
 
 #[derive(Debug, Clone)]
 struct GLSurfaceData {
@@ -101,6 +103,13 @@ struct GLRenderTextureData {
     params: RenderTextureParams,
 }
 
+#[derive(Debug, Clone)]
+struct GLSamplerData {
+    handle: SamplerHandle,
+    params: SamplerParams,
+    id: WebGlSampler,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Sampler {
     RenderTexture(RenderTextureHandle),
@@ -111,7 +120,7 @@ enum Sampler {
 struct GLMeshData {
     handle: MeshHandle,
     vbo: WebGlBuffer,
-    ibo: WebGlBuffer,
+    ibo: Option<WebGlBuffer>,
     params: MeshParams,
 }
 
@@ -125,6 +134,7 @@ struct WebGLState {
     binded_shader: Option<ShaderHandle>,
     binded_texture_index: usize,
     binded_textures: SmallVec<[Option<Sampler>; 8]>,
+    binded_samplers: SmallVec<[Option<SamplerHandle>; 8]>,
     binded_vao: Option<(ShaderHandle, MeshHandle)>,
 }
 
@@ -138,6 +148,7 @@ pub struct WebGLVisitor {
     meshes: DataVec<GLMeshData>,
     textures: DataVec<GLTextureData>,
     render_textures: DataVec<GLRenderTextureData>,
+    samplers: DataVec<GLSamplerData>,
 }
 
 impl WebGLVisitor {
@@ -169,6 +180,7 @@ impl WebGLVisitor {
             binded_shader: None,
             binded_texture_index: 0,
             binded_textures: SmallVec::new(),
+            binded_samplers: SmallVec::new(),
             vaos: FastHashMap::default(),
             binded_vao: None,
         };
@@ -184,11 +196,16 @@ impl WebGLVisitor {
             textures: DataVec::new(),
             render_textures: DataVec::new(),
             meshes: DataVec::new(),
+            samplers: DataVec::new(),
         })
     }
 }
 
 impl Visitor for WebGLVisitor {
+    fn is_texture_format_supported(&self, format: TextureFormat) -> bool {
+        self.capabilities.support_texture_format(format)
+    }
+
     unsafe fn advance(&mut self) -> Result<()> {
         self.state.cleared_surfaces.clear();
         self.state.binded_surface = None;
@@ -207,6 +224,7 @@ impl Visitor for WebGLVisitor {
             params: params,
         };
 
+        let name = Labelled(handle, params.label);
         if params.colors[0].is_some() || params.depth_stencil.is_some() {
             let id = self.ctx.create_framebuffer().unwrap();
             self.ctx.bind_framebuffer(WebGL::FRAMEBUFFER, Some(&id));
@@ -221,11 +239,11 @@ impl Visitor for WebGLVisitor {
                         .ok_or_else(|| format_err!("RenderTexture handle {:?} is invalid.", v))?;
 
                     if !rt.params.format.is_color() {
-                        bail!("Incompitable(mismatch format) attachments of SurfaceObject.");
+                        bail!("Incompitable(mismatch format) attachments of {}.", name);
                     }
 
                     if dimensions.is_some() && dimensions != Some(rt.params.dimensions) {
-                        bail!("Incompitable(mismatch dimensons) attachments of SurfaceObject.");
+                        bail!("Incompitable(mismatch dimensons) attachments of {}.", name);
                     }
 
                     dimensions = Some(rt.params.dimensions);
@@ -240,11 +258,11 @@ impl Visitor for WebGLVisitor {
                     .ok_or_else(|| format_err!("RenderTexture handle {:?} is invalid.", v))?;
 
                 if rt.params.format.is_color() {
-                    bail!("Incompitable(mismatch format) attachments of SurfaceObject.");
+                    bail!("Incompitable(mismatch format) attachments of {}.", name);
                 }
 
                 if dimensions.is_some() && dimensions != Some(rt.params.dimensions) {
-                    bail!("Incompitable(mismatch dimensions) attachments of SurfaceObject.");
+                    bail!("Incompitable(mismatch dimensions) attachments of {}.", name);
                 }
 
                 dimensions = Some(rt.params.dimensions);
@@ -258,25 +276,28 @@ impl Visitor for WebGLVisitor {
 
                 match status {
                     WebGL::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => {
-                        bail!("[GL] Surface is incomplete. Not all framebuffer attachment points \
+                        bail!("[GL] {} is incomplete. Not all framebuffer attachment points \
                         are framebuffer attachment complete. This means that at least one attachment point with a \
                         renderbuffer or texture attached has its attached object no longer in existence or has an \
                         attached image with a width or height of zero, or the color attachment point has a non-color-renderable \
                         image attached, or the depth attachment point has a non-depth-renderable image attached, or \
-                        the stencil attachment point has a non-stencil-renderable image attached.");
+                        the stencil attachment point has a non-stencil-renderable image attached.", name);
                     }
 
                     WebGL::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => {
-                        bail!("[GL] Surface is incomplete. No images are attached to the framebuffer.");
+                        bail!(
+                            "[GL] {} is incomplete. No images are attached to the framebuffer.",
+                            name
+                        );
                     }
 
                     WebGL::FRAMEBUFFER_UNSUPPORTED => {
-                        bail!("[GL] Surface is incomplete. The combination of internal formats \
-                        of the attached images violates an implementation-dependent set of restrictions.");
+                        bail!("[GL] {} is incomplete. The combination of internal formats \
+                        of the attached images violates an implementation-dependent set of restrictions.", name);
                     }
 
                     _ => {
-                        bail!("[GL] Surface is incomplete.");
+                        bail!("[GL] {} is incomplete.", name);
                     }
                 }
             }
@@ -314,9 +335,13 @@ impl Visitor for WebGLVisitor {
         vs: &str,
         fs: &str,
     ) -> Result<()> {
-        let vs = Self::compile(&self.ctx, WebGL::VERTEX_SHADER, vs)?;
-        let fs = Self::compile(&self.ctx, WebGL::FRAGMENT_SHADER, fs)?;
-        let id = Self::link(&self.ctx, &[vs, fs])?;
+        let name = Labelled(handle, params.label);
+        let vs = Self::compile(&self.ctx, WebGL::VERTEX_SHADER, vs)
+            .map_err(|err| format_err!("Failed to compile vertex shader of {}. {}", name, err))?;
+        let fs = Self::compile(&self.ctx, WebGL::FRAGMENT_SHADER, fs)
+            .map_err(|err| format_err!("Failed to compile fragment shader of {}. {}", name, err))?;
+        let id = Self::link(&self.ctx, &[vs, fs])
+            .map_err(|err| format_err!("Failed to link {}. {}", name, err))?;
 
         let shader = GLShaderData {
             handle: handle,
@@ -326,18 +351,18 @@ impl Visitor for WebGLVisitor {
             attributes: RefCell::new(FastHashMap::default()),
         };
 
-        for (name, _, _) in shader.params.attributes.iter() {
-            let name: &'static str = name.into();
-            if let Err(err) = shader.attribute_location(&self.ctx, name) {
+        for (attr, _, _) in shader.params.attributes.iter() {
+            let attr: &'static str = attr.into();
+            if let Err(err) = shader.attribute_location(&self.ctx, attr) {
                 self.ctx.delete_program(Some(&shader.id));
-                bail!(err);
+                bail!("{} {}", err, name);
             }
         }
 
-        for &(ref name, _) in shader.params.uniforms.iter() {
-            if let Err(err) = shader.uniform_location(&self.ctx, name) {
+        for &(ref field, _) in shader.params.uniforms.iter() {
+            if let Err(err) = shader.uniform_location(&self.ctx, field) {
                 self.ctx.delete_program(Some(&shader.id));
-                bail!(err);
+                bail!("{} {}", err, name);
             }
         }
 
@@ -345,31 +370,39 @@ impl Visitor for WebGLVisitor {
         Ok(())
     }
 
-    unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()> {
-        let shader = self
+    unsafe fn update_shader(
+        &mut self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: &str,
+        fs: &str,
+    ) -> Result<bool> {
+        let prev = self
             .shaders
             .free(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        // Removes deprecated `VertexArrayObject`s.
-        {
-            let ctx = &self.ctx;
-            self.state.vaos.retain(|&(h, _), vao| {
-                if h == shader.handle {
-                    ctx.delete_vertex_array(Some(&vao));
-                    false
-                } else {
-                    true
-                }
-            });
+        // Keeps the previous program if the new one failed to compile.
+        if let Err(err) = self.create_shader(handle, params, vs, fs) {
+            warn!(
+                "Failed to recompile {:?}, keeps the previous program. {}",
+                handle, err
+            );
+            self.shaders.create(handle, prev);
+            return Ok(false);
         }
 
-        if self.state.binded_shader == Some(handle) {
-            self.state.binded_shader = None;
-        }
+        Self::release_shader(&self.ctx, &mut self.state, prev)?;
+        Ok(true)
+    }
 
-        self.ctx.delete_program(Some(&shader.id));
-        check(&self.ctx)
+    unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()> {
+        let shader = self
+            .shaders
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        Self::release_shader(&self.ctx, &mut self.state, shader)
     }
 
     unsafe fn create_texture(
@@ -380,8 +413,9 @@ impl Visitor for WebGLVisitor {
     ) -> Result<()> {
         if !self.capabilities.support_texture_format(params.format) {
             bail!(
-                "The GL Context does not support the texture format {:?}.",
-                params.format
+                "The GL Context does not support the texture format {:?} of {}.",
+                params.format,
+                Labelled(handle, params.label)
             );
         }
 
@@ -471,19 +505,20 @@ impl Visitor for WebGLVisitor {
             .get(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
+        let name = Labelled(handle, texture.params.label);
         if texture.params.hint == TextureHint::Immutable {
-            bail!("Trying to update immutable texture.");
+            bail!("Trying to update immutable texture {}.", name);
         }
 
         if texture.params.format.compressed() {
-            bail!("Trying to update compressed texture.");
+            bail!("Trying to update compressed texture {}.", name);
         }
 
         if data.len() > texture.params.format.size(area.dim()) as usize
             || area.min.x >= texture.params.dimensions.x
             || area.min.y >= texture.params.dimensions.y
         {
-            bail!("Trying to update texture data out of bounds.");
+            bail!("Trying to update texture data of {} out of bounds.", name);
         }
 
         let (internal_format, format, pixel_type) = texture.params.format.into();
@@ -565,6 +600,18 @@ impl Visitor for WebGLVisitor {
             )?;
             Self::bind_texture_params(&self.ctx, params.wrap, params.filter, 1)?;
 
+            if let Some(comparison) = params.comparison {
+                let func: u32 = comparison.into();
+                let mode = WebGL::COMPARE_REF_TO_TEXTURE as i32;
+                self.ctx
+                    .tex_parameteri(WebGL::TEXTURE_2D, WebGL::TEXTURE_COMPARE_MODE, mode);
+                self.ctx.tex_parameteri(
+                    WebGL::TEXTURE_2D,
+                    WebGL::TEXTURE_COMPARE_FUNC,
+                    func as i32,
+                );
+            }
+
             let (internal_format, format, pixel_type) = params.format.into();
             self.ctx
                 .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
@@ -633,6 +680,62 @@ impl Visitor for WebGLVisitor {
         check(&self.ctx)
     }
 
+    unsafe fn create_sampler(
+        &mut self,
+        handle: SamplerHandle,
+        params: SamplerParams,
+    ) -> Result<()> {
+        let id = self
+            .ctx
+            .create_sampler()
+            .ok_or_else(|| format_err!("Failed to create sampler object."))?;
+
+        let wrap_s: u32 = params.wrap_s.into();
+        let wrap_t: u32 = params.wrap_t.into();
+        let min_filter = types::min_filter(params.min_filter, params.mip_filter);
+        let mag_filter: u32 = params.mag_filter.into();
+
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_WRAP_S, wrap_s as i32);
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_WRAP_T, wrap_t as i32);
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_MIN_FILTER, min_filter as i32);
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_MAG_FILTER, mag_filter as i32);
+
+        // WebGL does NOT support TEXTURE_LOD_BIAS.
+        if let Some(comparison) = params.comparison {
+            let func: u32 = comparison.into();
+            let mode = WebGL::COMPARE_REF_TO_TEXTURE as i32;
+            self.ctx
+                .sampler_parameteri(&id, WebGL::TEXTURE_COMPARE_MODE, mode);
+            self.ctx
+                .sampler_parameteri(&id, WebGL::TEXTURE_COMPARE_FUNC, func as i32);
+        }
+
+        check(&self.ctx)?;
+        self.samplers
+            .create(handle, GLSamplerData { handle, params, id });
+        Ok(())
+    }
+
+    unsafe fn delete_sampler(&mut self, handle: SamplerHandle) -> Result<()> {
+        let sampler = self
+            .samplers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        for v in self.state.binded_samplers.iter_mut() {
+            if *v == Some(handle) {
+                *v = None;
+            }
+        }
+
+        self.ctx.delete_sampler(Some(&sampler.id));
+        check(&self.ctx)
+    }
+
     unsafe fn create_mesh(
         &mut self,
         handle: MeshHandle,
@@ -647,13 +750,19 @@ impl Visitor for WebGLVisitor {
             data.as_ref().map(|v| v.vptr.as_ref()),
         )?;
 
-        let ibo = Self::create_buffer(
-            &self.ctx,
-            WebGL::ELEMENT_ARRAY_BUFFER,
-            params.hint,
-            params.index_buffer_len(),
-            data.as_ref().map(|v| v.iptr.as_ref()),
-        )?;
+        let ibo = if params.is_indexed() {
+            let ibo = Self::create_buffer(
+                &self.ctx,
+                WebGL::ELEMENT_ARRAY_BUFFER,
+                params.hint,
+                params.index_buffer_len(),
+                data.as_ref().map(|v| v.iptr.as_ref()),
+            )?;
+
+            Some(ibo)
+        } else {
+            None
+        };
 
         self.meshes.create(
             handle,
@@ -680,7 +789,10 @@ impl Visitor for WebGLVisitor {
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
         if mesh.params.hint == MeshHint::Immutable {
-            bail!("Trying to update immutable buffer");
+            bail!(
+                "Trying to update immutable buffer of {}.",
+                Labelled(handle, mesh.params.label)
+            );
         }
 
         Self::update_buffer(&self.ctx, WebGL::ARRAY_BUFFER, &mesh.vbo, offset, data)
@@ -698,16 +810,20 @@ impl Visitor for WebGLVisitor {
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
         if mesh.params.hint == MeshHint::Immutable {
-            bail!("Trying to update immutable buffer");
+            bail!(
+                "Trying to update immutable buffer of {}.",
+                Labelled(handle, mesh.params.label)
+            );
         }
 
-        Self::update_buffer(
-            &self.ctx,
-            WebGL::ELEMENT_ARRAY_BUFFER,
-            &mesh.ibo,
-            offset,
-            data,
-        )
+        let ibo = mesh.ibo.as_ref().ok_or_else(|| {
+            format_err!(
+                "{} does not have indices.",
+                Labelled(handle, mesh.params.label)
+            )
+        })?;
+
+        Self::update_buffer(&self.ctx, WebGL::ELEMENT_ARRAY_BUFFER, ibo, offset, data)
     }
 
     unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
@@ -730,7 +846,7 @@ impl Visitor for WebGLVisitor {
         }
 
         self.ctx.delete_buffer(Some(&mesh.vbo));
-        self.ctx.delete_buffer(Some(&mesh.ibo));
+        self.ctx.delete_buffer(mesh.ibo.as_ref());
         check(&self.ctx)
     }
 
@@ -795,22 +911,39 @@ impl Visitor for WebGLVisitor {
 
         Self::bind_shader(&self.ctx, &mut self.state, &shader)?;
 
+        let samplers = &self.samplers;
         let mut index = 0usize;
         for &(field, variable) in uniforms {
             if let Some(tp) = shader.params.uniforms.variable_type(field) {
-                if tp != variable.variable_type() {
-                    let name = shader.params.uniforms.variable_name(field).unwrap();
+                let name = shader.params.uniforms.variable_name(field).unwrap();
+                let sampler = match variable.sampler() {
+                    Some(v) => match samplers.get(v) {
+                        Some(sampler) => Some(sampler),
+                        None => bail!("The sampler {:?} of uniform {} is invalid.", v, name),
+                    },
+                    None => None,
+                };
+
+                let comparison = sampler.map(|v| v.params.comparison.is_some());
+                if !variable.is_compatible(tp, comparison == Some(true)) {
                     bail!(
-                        "The uniform {} needs a {:?} instead of {:?}.",
+                        "The uniform {} of {} needs a {:?} instead of {:?}{}.",
                         name,
+                        Labelled(shader.handle, shader.params.label),
                         tp,
                         variable.variable_type(),
+                        if comparison == Some(true) {
+                            " with comparison"
+                        } else {
+                            ""
+                        },
                     );
                 }
 
                 let location = shader.hash_uniform_location(field).unwrap();
                 match variable {
-                    UniformVariable::Texture(handle) => {
+                    UniformVariable::Texture(handle)
+                    | UniformVariable::SampledTexture(handle, _) => {
                         let v = UniformVariable::I32(index as i32);
                         Self::bind_uniform_variable(&self.ctx, &location, &v)?;
 
@@ -826,13 +959,29 @@ impl Visitor for WebGLVisitor {
                             Self::bind_texture(&self.ctx, &mut self.state, None, index, None)?;
                         }
 
+                        Self::bind_sampler(&self.ctx, &mut self.state, sampler, index)?;
                         index += 1;
                     }
-                    UniformVariable::RenderTexture(handle) => {
+                    UniformVariable::RenderTexture(handle)
+                    | UniformVariable::SampledRenderTexture(handle, _)
+                    | UniformVariable::ShadowTexture(handle) => {
                         let v = UniformVariable::I32(index as i32);
                         Self::bind_uniform_variable(&self.ctx, &location, &v)?;
 
                         if let Some(texture) = self.render_textures.get(handle) {
+                            // The comparison of sampler object overrides the one of texture.
+                            let shadow = tp == UniformVariableType::ShadowTexture;
+                            let comparison =
+                                comparison.unwrap_or_else(|| texture.params.comparison.is_some());
+
+                            if shadow != comparison {
+                                bail!(
+                                    "The depth comparison of {} does not match {:?}.",
+                                    Labelled(handle, texture.params.label),
+                                    tp
+                                );
+                            }
+
                             match texture.id {
                                 GLRenderTexture::T(ref w) => {
                                     Self::bind_texture(
@@ -844,13 +993,17 @@ impl Visitor for WebGLVisitor {
                                     )?;
                                 }
                                 _ => {
-                                    bail!("The render buffer does not have a sampler.");
+                                    bail!(
+                                        "The render buffer {} does not have a sampler.",
+                                        Labelled(handle, texture.params.label)
+                                    );
                                 }
                             }
                         } else {
                             Self::bind_texture(&self.ctx, &mut self.state, None, index, None)?;
                         }
 
+                        Self::bind_sampler(&self.ctx, &mut self.state, sampler, index)?;
                         index += 1;
                     }
                     _ => {
@@ -858,7 +1011,11 @@ impl Visitor for WebGLVisitor {
                     }
                 }
             } else {
-                bail!("Undefined uniform field {:?}.", field);
+                bail!(
+                    "Undefined uniform field {:?} of {}.",
+                    field,
+                    Labelled(shader.handle, shader.params.label)
+                );
             }
         }
 
@@ -866,41 +1023,89 @@ impl Visitor for WebGLVisitor {
             // Bind vertex buffer and vertex array object.
             Self::bind_mesh(&self.ctx, &mut self.state, &shader, &mesh)?;
 
-            let (from, len) = match mesh_index {
-                MeshIndex::Ptr(from, len) => {
-                    if (from + len) > mesh.params.num_idxes {
-                        bail!("MeshIndex is out of bounds");
+            let name = Labelled(mesh.handle, mesh.params.label);
+
+            let num = mesh.params.num_elements();
+            let (from, len, base) = match mesh_index {
+                MeshIndex::Ptr(from, len) => (from, len, None),
+                MeshIndex::Vertices(from, len) => {
+                    let end = from.checked_add(len);
+                    if end.map_or(true, |v| v > mesh.params.num_verts) {
+                        bail!("MeshIndex is out of bounds of {}.", name);
+                    }
+
+                    self.ctx
+                        .draw_arrays(mesh.params.primitive.into(), from as i32, len as i32);
+                    check(&self.ctx)?;
+                    return Ok(mesh.params.primitive.assemble(len as u32));
+                }
+                MeshIndex::BaseVertex(from, len, base) => {
+                    if mesh.ibo.is_none() {
+                        bail!("{} does not have indices.", name);
                     }
 
-                    ((from * mesh.params.index_format.stride()), len)
+                    if base >= mesh.params.num_verts {
+                        bail!("MeshIndex is out of bounds of {}.", name);
+                    }
+
+                    (from, len, Some(base))
                 }
                 MeshIndex::SubMesh(index) => {
-                    let num = mesh.params.sub_mesh_offsets.len();
-                    let from = mesh
+                    let from =
+                        *mesh.params.sub_mesh_offsets.get(index).ok_or_else(|| {
+                            format_err!("MeshIndex is out of bounds of {}.", name)
+                        })?;
+
+                    let to = mesh
                         .params
                         .sub_mesh_offsets
-                        .get(index)
-                        .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
+                        .get(index + 1)
+                        .cloned()
+                        .unwrap_or(num);
 
-                    let to = if index == (num - 1) {
-                        mesh.params.num_idxes
-                    } else {
-                        mesh.params.sub_mesh_offsets[index + 1]
-                    };
-
-                    ((from * mesh.params.index_format.stride()), (to - from))
+                    // The offsets are strictly increasing, see `MeshParams::validate`.
+                    (from, to - from, None)
                 }
-                MeshIndex::All => (0, mesh.params.num_idxes),
+                MeshIndex::All => (0, num, None),
             };
 
-            self.ctx.draw_elements_with_i32(
-                mesh.params.primitive.into(),
-                len as i32,
-                mesh.params.index_format.into(),
-                from as i32,
-            );
+            // The ranges are supplied by callers, which might overflow.
+            if from.checked_add(len).map_or(true, |v| v > num) {
+                bail!("MeshIndex is out of bounds of {}.", name);
+            }
+
+            if mesh.ibo.is_none() {
+                self.ctx
+                    .draw_arrays(mesh.params.primitive.into(), from as i32, len as i32);
+                check(&self.ctx)?;
+                return Ok(mesh.params.primitive.assemble(len as u32));
+            }
+
+            let ctx = &self.ctx;
+            let draw = || {
+                ctx.draw_elements_with_i32(
+                    mesh.params.primitive.into(),
+                    len as i32,
+                    mesh.params.index_format.into(),
+                    (from * mesh.params.index_format.stride()) as i32,
+                );
+
+                check(ctx)
+            };
+
+            // WebGL2 does not have base vertex draws, emulates it by offsetting the attribute
+            // pointers temporarily. They are always restored, otherwise the later draws of
+            // this mesh would read from the offset.
+            match base {
+                Some(base) => {
+                    let rsp =
+                        Self::bind_vertex_attributes(ctx, shader, mesh, base).and_then(|_| draw());
+                    Self::bind_vertex_attributes(ctx, shader, mesh, 0)?;
+                    rsp?;
+                }
+                None => draw()?,
+            }
 
-            check(&self.ctx)?;
             Ok(mesh.params.primitive.assemble(len as u32))
         } else {
             Ok(0)
@@ -1051,41 +1256,7 @@ impl WebGLVisitor {
             } else {
                 let vao = ctx.create_vertex_array().unwrap();
                 ctx.bind_vertex_array(Some(&vao));
-                ctx.bind_buffer(WebGL::ARRAY_BUFFER, Some(&mesh.vbo));
-
-                for (name, size, required) in shader.params.attributes.iter() {
-                    if let Some(element) = mesh.params.layout.element(name) {
-                        if element.size < size {
-                            bail!(
-                                "Vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
-                                name,
-                                element.size,
-                                size
-                            );
-                        }
-
-                        let offset = mesh.params.layout.offset(name).unwrap();
-                        let stride = mesh.params.layout.stride();
-
-                        let location = shader.attribute_location(ctx, name.into())?;
-                        ctx.enable_vertex_attrib_array(location as u32);
-                        ctx.vertex_attrib_pointer_with_i32(
-                            location as u32,
-                            element.size as i32,
-                            element.format.into(),
-                            element.normalized,
-                            stride as i32,
-                            offset as i32,
-                        );
-                    } else {
-                        if required {
-                            bail!(
-                                "Can't find attribute {:?} description in vertex buffer.",
-                                name
-                            );
-                        }
-                    }
-                }
+                Self::bind_vertex_attributes(ctx, shader, mesh, 0)?;
 
                 check(ctx)?;
                 state.vaos.insert(k, vao);
@@ -1094,10 +1265,61 @@ impl WebGLVisitor {
             state.binded_vao = Some(k);
         }
 
-        ctx.bind_buffer(WebGL::ELEMENT_ARRAY_BUFFER, Some(&mesh.ibo));
+        if let Some(ibo) = mesh.ibo.as_ref() {
+            ctx.bind_buffer(WebGL::ELEMENT_ARRAY_BUFFER, Some(ibo));
+        }
+
         Ok(())
     }
 
+    /// Specifies the vertex attribute pointers of currently binded vertex array object,
+    /// starting at the `base` vertex.
+    unsafe fn bind_vertex_attributes(
+        ctx: &WebGL,
+        shader: &GLShaderData,
+        mesh: &GLMeshData,
+        base: usize,
+    ) -> Result<()> {
+        ctx.bind_buffer(WebGL::ARRAY_BUFFER, Some(&mesh.vbo));
+
+        for (name, size, required) in shader.params.attributes.iter() {
+            if let Some(element) = mesh.params.layout.element(name) {
+                if element.size < size {
+                    bail!(
+                        "Vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
+                        name,
+                        element.size,
+                        size
+                    );
+                }
+
+                let stride = mesh.params.layout.stride();
+                let offset =
+                    mesh.params.layout.offset(name).unwrap() as usize + base * stride as usize;
+
+                let location = shader.attribute_location(ctx, name.into())?;
+                ctx.enable_vertex_attrib_array(location as u32);
+                ctx.vertex_attrib_pointer_with_i32(
+                    location as u32,
+                    element.size as i32,
+                    element.format.into(),
+                    element.normalized,
+                    stride as i32,
+                    offset as i32,
+                );
+            } else {
+                if required {
+                    bail!(
+                        "Can't find attribute {:?} description in vertex buffer.",
+                        name
+                    );
+                }
+            }
+        }
+
+        check(ctx)
+    }
+
     unsafe fn bind_uniform_variable(
         ctx: &WebGL,
         location: &WebGlUniformLocation,
@@ -1106,6 +1328,9 @@ impl WebGLVisitor {
         match *variable {
             UniformVariable::Texture(_) => unreachable!(),
             UniformVariable::RenderTexture(_) => unreachable!(),
+            UniformVariable::SampledTexture(_, _) => unreachable!(),
+            UniformVariable::SampledRenderTexture(_, _) => unreachable!(),
+            UniformVariable::ShadowTexture(_) => unreachable!(),
             UniformVariable::I32(v) => ctx.uniform1i(Some(&location), v),
             UniformVariable::F32(v) => ctx.uniform1f(Some(&location), v),
             UniformVariable::Vector2f(v) => ctx.uniform2f(Some(&location), v[0], v[1]),
@@ -1414,6 +1639,48 @@ impl WebGLVisitor {
         check(ctx)
     }
 
+    unsafe fn release_shader(
+        ctx: &WebGL,
+        state: &mut WebGLState,
+        shader: GLShaderData,
+    ) -> Result<()> {
+        // Removes deprecated `VertexArrayObject`s.
+        state.vaos.retain(|&(h, _), vao| {
+            if h == shader.handle {
+                ctx.delete_vertex_array(Some(&vao));
+                false
+            } else {
+                true
+            }
+        });
+
+        if state.binded_shader == Some(shader.handle) {
+            state.binded_shader = None;
+        }
+
+        ctx.delete_program(Some(&shader.id));
+        check(ctx)
+    }
+
+    unsafe fn bind_sampler(
+        ctx: &WebGL,
+        state: &mut WebGLState,
+        sampler: Option<&GLSamplerData>,
+        index: usize,
+    ) -> Result<()> {
+        if state.binded_samplers.len() <= index {
+            state.binded_samplers.resize(index + 1, None);
+        }
+
+        let handle = sampler.map(|v| v.handle);
+        if state.binded_samplers[index] != handle {
+            state.binded_samplers[index] = handle;
+            ctx.bind_sampler(index as u32, sampler.map(|v| &v.id));
+        }
+
+        check(ctx)
+    }
+
     unsafe fn bind_texture_params(
         ctx: &WebGL,
         wrap: TextureWrap,
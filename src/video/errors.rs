@@ -1,3 +1,5 @@
+use super::assets::texture::TextureFormat;
+
 #[derive(Debug, Fail)]
 pub enum Error {
     #[fail(display = "Failed to create shader, errors: \n{}.", _0)]
@@ -10,6 +12,10 @@ pub enum Error {
     UpdateImmutableBuffer,
     #[fail(display = "Can NOT sample render buffer.")]
     SampleRenderBuffer,
+    #[fail(display = "Can NOT compare samples of color texture.")]
+    CompareColorTexture,
+    #[fail(display = "Texture format {:?} is not supported.", _0)]
+    TextureFormatUnsupported(TextureFormat),
     #[fail(display = "Failed to create surface, errors:\n{}\n", _0)]
     SurfaceInvalid(String),
     #[fail(display = "Attribute({}) is undefined.", _0)]
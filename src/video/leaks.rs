@@ -0,0 +1,93 @@
+//! Tracks the creation sites of video resources, which are reported if the resources
+//! are still alive when the video system is discarded.
+
+use std::fmt;
+use std::panic::Location;
+use std::sync::Mutex;
+
+use crate::utils::prelude::FastHashMap;
+
+use super::assets::prelude::*;
+
+/// The handle of any kind of video resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VideoResource {
+    Surface(SurfaceHandle),
+    Shader(ShaderHandle),
+    Mesh(MeshHandle),
+    Texture(TextureHandle),
+    RenderTexture(RenderTextureHandle),
+    Sampler(SamplerHandle),
+}
+
+macro_rules! impl_from_handle {
+    ($handle:ident, $variant:ident) => {
+        impl From<$handle> for VideoResource {
+            fn from(handle: $handle) -> Self {
+                VideoResource::$variant(handle)
+            }
+        }
+    };
+}
+
+impl_from_handle!(SurfaceHandle, Surface);
+impl_from_handle!(ShaderHandle, Shader);
+impl_from_handle!(MeshHandle, Mesh);
+impl_from_handle!(TextureHandle, Texture);
+impl_from_handle!(RenderTextureHandle, RenderTexture);
+impl_from_handle!(SamplerHandle, Sampler);
+
+/// A video resource that is still alive.
+#[derive(Debug, Clone, Copy)]
+pub struct VideoLeak {
+    pub resource: VideoResource,
+    /// The location where the resource is created, it might be `None` if the resource
+    /// is created before the tracking starts.
+    pub site: Option<&'static Location<'static>>,
+    /// The optional label of resource.
+    pub label: Option<Label>,
+}
+
+impl fmt::Display for VideoLeak {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.resource)?;
+
+        if let Some(label) = self.label {
+            write!(f, " {:?}", label)?;
+        }
+
+        match self.site {
+            Some(site) => write!(f, " created at {}", site),
+            None => write!(f, " created at unknown site"),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct LeakTracker {
+    sites: Mutex<FastHashMap<VideoResource, &'static Location<'static>>>,
+}
+
+impl LeakTracker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records the creation site of resource. The first site is kept if the resource
+    /// is shared by multiple creations.
+    pub fn track(&self, resource: VideoResource, site: &'static Location<'static>) {
+        self.sites.lock().unwrap().entry(resource).or_insert(site);
+    }
+
+    pub fn untrack(&self, resource: VideoResource) {
+        self.sites.lock().unwrap().remove(&resource);
+    }
+
+    pub fn leak(&self, resource: VideoResource, label: Option<Label>) -> VideoLeak {
+        VideoLeak {
+            resource,
+            site: self.sites.lock().unwrap().get(&resource).cloned(),
+            label,
+        }
+    }
+}
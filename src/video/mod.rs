@@ -74,6 +74,10 @@
 //! keeps a use-counting internally. It will not be freed really, before all the users deletes
 //! its `Handle`.
 //!
+//! The sites where resources are created are tracked, and the resources which are still
+//! alive are reported when the video system is discarded. You can also check them with
+//! `video::leaks` at any time, e.g. after the teardown of a scene.
+//!
 //! ### Surface Object
 //!
 //! Surface object plays as the `Layer` role we mentioned above, all the commands we submitted
@@ -160,8 +164,22 @@
 //! video::delete_texture(texture);
 //! ```
 //!
+//! #### Sampler Object
+//!
+//! The wrap and filter states are baked into the texture object when it is created. A
+//! sampler object could be bound alongside a texture with `UniformVariable::SampledTexture`
+//! to sample the same texture with different states in different shaders.
+//!
 //! #### Compressed Texture Format
 //!
+//! ETC2, PVRTC and S3TC compressed formats are uploaded to the device directly if they
+//! are supported, which could be checked with `video::is_texture_format_supported`.
+//! Otherwise, ETC2 and S3TC textures are decoded into RGBA8 on the CPU when they are
+//! created, which costs more memory and time.
+//!
+//! Bundles could also ship multiple variants of the same texture in different formats,
+//! and the manifest picks the first one that matches `video::texture_variants`.
+//!
 //! _TODO_: Cube texture.
 //! _TODO_: 3D texture.
 //!
@@ -198,6 +216,7 @@ pub const MAX_UNIFORM_TEXTURE_SLOTS: usize = 8;
 pub mod assets;
 pub mod command;
 pub mod errors;
+pub mod leaks;
 
 mod system;
 
@@ -212,14 +231,16 @@ use uuid::Uuid;
 
 use crate::math::prelude::Aabb2;
 use crate::prelude::CrResult;
-use crate::res::utils::prelude::ResourceState;
+use crate::res::utils::prelude::{GroupMember, ResourceState, UnloadPolicy};
 
 use self::assets::prelude::*;
 use self::errors::*;
 use self::inside::ctx;
+use self::leaks::VideoLeak;
 
 /// Creates an surface with `SurfaceParams`.
 #[inline]
+#[track_caller]
 pub fn create_surface(params: SurfaceParams) -> Result<SurfaceHandle> {
     ctx().create_surface(params)
 }
@@ -245,10 +266,24 @@ pub fn delete_surface(handle: SurfaceHandle) {
 /// Create a shader with initial shaders and render state. It encapusulates all the
 /// informations we need to configurate graphics pipeline before real drawing.
 #[inline]
+#[track_caller]
 pub fn create_shader(params: ShaderParams, vs: String, fs: String) -> Result<ShaderHandle> {
     ctx().create_shader(params, vs, fs)
 }
 
+/// Recompiles the shader with new sources and render state in place, while keeping the
+/// same handle. The previous program and its parameters are kept if the new sources failed
+/// to compile.
+#[inline]
+pub fn update_shader(
+    handle: ShaderHandle,
+    params: ShaderParams,
+    vs: String,
+    fs: String,
+) -> Result<()> {
+    ctx().update_shader(handle, params, vs, fs)
+}
+
 /// Gets the `ShaderParams` if available.
 #[inline]
 pub fn shader(handle: ShaderHandle) -> Option<ShaderParams> {
@@ -269,6 +304,7 @@ pub fn delete_shader(handle: ShaderHandle) {
 
 /// Create a new mesh object.
 #[inline]
+#[track_caller]
 pub fn create_mesh<T>(params: MeshParams, data: T) -> CrResult<MeshHandle>
 where
     T: Into<Option<MeshData>>,
@@ -278,12 +314,14 @@ where
 
 /// Creates a mesh object from file asynchronously.
 #[inline]
+#[track_caller]
 pub fn create_mesh_from<T: AsRef<str>>(url: T) -> CrResult<MeshHandle> {
     ctx().create_mesh_from(url)
 }
 
 /// Creates a mesh object from file asynchronously.
 #[inline]
+#[track_caller]
 pub fn create_mesh_from_uuid(uuid: Uuid) -> CrResult<MeshHandle> {
     ctx().create_mesh_from_uuid(uuid)
 }
@@ -300,6 +338,31 @@ pub fn mesh_state(handle: MeshHandle) -> ResourceState {
     ctx().mesh_state(handle)
 }
 
+/// Returns the error of mesh if it's failed to load.
+#[inline]
+pub fn mesh_error(handle: MeshHandle) -> Option<failure::Error> {
+    ctx().mesh_error(handle)
+}
+
+/// Returns the loaded and total bytes of the mesh file.
+#[inline]
+pub fn mesh_bytes(handle: MeshHandle) -> (u64, u64) {
+    ctx().mesh_bytes(handle)
+}
+
+/// Sets the policy to unload meshes whose last ownerships are dropped, e.g. keeping the
+/// idle meshes in a LRU cache with a budget of video memory.
+#[inline]
+pub fn set_mesh_unload_policy(policy: UnloadPolicy) {
+    ctx().set_mesh_unload_policy(policy)
+}
+
+/// Returns the estimated video memory of all the meshes in bytes.
+#[inline]
+pub fn mesh_memory() -> usize {
+    ctx().mesh_memory()
+}
+
 /// Update a subset of dynamic vertex buffer. Use `offset` specifies the offset
 /// into the buffer object's data store where data replacement will begin, measured
 /// in bytes.
@@ -325,6 +388,7 @@ pub fn delete_mesh(handle: MeshHandle) {
 /// Create texture object. A texture is an image loaded in video memory,
 /// which can be sampled in shaders.
 #[inline]
+#[track_caller]
 pub fn create_texture<T>(params: TextureParams, data: T) -> CrResult<TextureHandle>
 where
     T: Into<Option<TextureData>>,
@@ -334,12 +398,14 @@ where
 
 /// Creates a texture object from file asynchronously.
 #[inline]
+#[track_caller]
 pub fn create_texture_from<T: AsRef<str>>(url: T) -> CrResult<TextureHandle> {
     ctx().create_texture_from(url)
 }
 
 /// Creates a texture object from file asynchronously.
 #[inline]
+#[track_caller]
 pub fn create_texture_from_uuid(uuid: Uuid) -> CrResult<TextureHandle> {
     ctx().create_texture_from_uuid(uuid)
 }
@@ -350,6 +416,31 @@ pub fn texture_state(handle: TextureHandle) -> ResourceState {
     ctx().texture_state(handle)
 }
 
+/// Returns the error of texture if it's failed to load.
+#[inline]
+pub fn texture_error(handle: TextureHandle) -> Option<failure::Error> {
+    ctx().texture_error(handle)
+}
+
+/// Returns the loaded and total bytes of the texture file.
+#[inline]
+pub fn texture_bytes(handle: TextureHandle) -> (u64, u64) {
+    ctx().texture_bytes(handle)
+}
+
+/// Sets the policy to unload textures whose last ownerships are dropped, e.g. keeping the
+/// idle textures in a LRU cache with a budget of video memory.
+#[inline]
+pub fn set_texture_unload_policy(policy: UnloadPolicy) {
+    ctx().set_texture_unload_policy(policy)
+}
+
+/// Returns the estimated video memory of all the textures in bytes.
+#[inline]
+pub fn texture_memory() -> usize {
+    ctx().texture_memory()
+}
+
 /// Update a contiguous subregion of an existing two-dimensional texture object.
 #[inline]
 pub fn update_texture(handle: TextureHandle, area: Aabb2<u32>, data: &[u8]) -> CrResult<()> {
@@ -367,8 +458,29 @@ pub fn texture(handle: TextureHandle)->Option<TextureParams> {
     ctx().texture(handle)
 }
 
+/// Checks if the texture format could be uploaded to the device directly.
+#[inline]
+pub fn is_texture_format_supported(format: TextureFormat) -> bool {
+    ctx().is_texture_format_supported(format)
+}
+
+/// Returns all the video resources that are still alive, with the sites where they
+/// are created. This is useful to check that everything is freed after teardown.
+#[inline]
+pub fn leaks() -> Vec<VideoLeak> {
+    ctx().leaks()
+}
+
+/// Returns the tags of texture variants which could be used by the device, in the
+/// order of preference.
+#[inline]
+pub fn texture_variants() -> Vec<String> {
+    ctx().texture_variants()
+}
+
 /// Create render texture object, which could be attached with a framebuffer.
 #[inline]
+#[track_caller]
 pub fn create_render_texture(params: RenderTextureParams) -> Result<RenderTextureHandle> {
     ctx().create_render_texture(params)
 }
@@ -391,6 +503,68 @@ pub fn delete_render_texture(handle: RenderTextureHandle) {
     ctx().delete_render_texture(handle)
 }
 
+/// Create sampler object, which could be bound alongside a texture to override its
+/// sampling states.
+#[inline]
+#[track_caller]
+pub fn create_sampler(params: SamplerParams) -> Result<SamplerHandle> {
+    ctx().create_sampler(params)
+}
+
+/// Gets the `SamplerParams` if available.
+#[inline]
+pub fn sampler(handle: SamplerHandle) -> Option<SamplerParams> {
+    ctx().sampler(handle)
+}
+
+/// Get the resource state of specified sampler.
+#[inline]
+pub fn sampler_state(handle: SamplerHandle) -> ResourceState {
+    ctx().sampler_state(handle)
+}
+
+/// Delete the sampler object.
+#[inline]
+pub fn delete_sampler(handle: SamplerHandle) {
+    ctx().delete_sampler(handle)
+}
+
+impl GroupMember for MeshHandle {
+    fn state(&self) -> ResourceState {
+        mesh_state(*self)
+    }
+
+    fn bytes(&self) -> (u64, u64) {
+        mesh_bytes(*self)
+    }
+
+    fn error(&self) -> Option<failure::Error> {
+        mesh_error(*self)
+    }
+
+    fn release(&self) {
+        delete_mesh(*self)
+    }
+}
+
+impl GroupMember for TextureHandle {
+    fn state(&self) -> ResourceState {
+        texture_state(*self)
+    }
+
+    fn bytes(&self) -> (u64, u64) {
+        texture_bytes(*self)
+    }
+
+    fn error(&self) -> Option<failure::Error> {
+        texture_error(*self)
+    }
+
+    fn release(&self) {
+        delete_texture(*self)
+    }
+}
+
 pub(crate) mod inside {
     use std::sync::Arc;
 
@@ -437,6 +611,10 @@ pub(crate) mod inside {
             return;
         }
 
+        for v in ctx().leaks() {
+            warn!("[VideoSystem] {} is not deleted.", v);
+        }
+
         drop(Box::from_raw(CTX as *mut VideoSystem));
         CTX = std::ptr::null();
     }
@@ -1,18 +1,20 @@
+use std::panic::Location;
 use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
 use crate::application::prelude::{LifecycleListener, LifecycleListenerHandle};
 use crate::math::prelude::{Aabb2, Vector2};
 use crate::prelude::CrResult;
-use crate::res::utils::prelude::{ResourcePool, ResourceState};
+use crate::res::utils::prelude::{ResourcePool, ResourceState, UnloadPolicy};
 use crate::utils::prelude::{DoubleBuf, ObjectPool};
 
 use super::assets::mesh_loader::MeshLoader;
 use super::assets::prelude::*;
-use super::assets::texture_loader::TextureLoader;
+use super::assets::texture_loader::{self, TextureLoader};
 use super::backends::frame::*;
 use super::backends::{self, Visitor};
 use super::errors::*;
+use super::leaks::{LeakTracker, VideoLeak};
 
 /// The centralized management of video sub-system.
 pub struct VideoSystem {
@@ -20,6 +22,17 @@ pub struct VideoSystem {
     state: Arc<VideoState>,
 }
 
+const COMPRESSED_FORMATS: [TextureFormat; 8] = [
+    TextureFormat::Etc2RGB4BPP,
+    TextureFormat::Etc2RGBA8BPP,
+    TextureFormat::S3tcDxt1RGB4BPP,
+    TextureFormat::S3tcDxt5RGBA8BPP,
+    TextureFormat::PvrtcRGB4BPP,
+    TextureFormat::PvrtcRGB2BPP,
+    TextureFormat::PvrtcRGBA4BPP,
+    TextureFormat::PvrtcRGBA2BPP,
+];
+
 struct VideoState {
     frames: Arc<DoubleBuf<Frame>>,
     surfaces: RwLock<ObjectPool<SurfaceHandle, SurfaceParams>>,
@@ -27,21 +40,36 @@ struct VideoState {
     meshes: RwLock<ResourcePool<MeshHandle, MeshLoader>>,
     textures: RwLock<ResourcePool<TextureHandle, TextureLoader>>,
     render_textures: RwLock<ObjectPool<RenderTextureHandle, RenderTextureParams>>,
+    samplers: RwLock<ObjectPool<SamplerHandle, SamplerParams>>,
+    unsupported: Vec<TextureFormat>,
+    leaks: LeakTracker,
 }
 
 impl VideoState {
-    fn new() -> Self {
+    fn new(visitor: &dyn Visitor) -> Self {
         let frames = Arc::new(DoubleBuf::new(
             Frame::with_capacity(64 * 1024),
             Frame::with_capacity(64 * 1024),
         ));
 
+        let unsupported: Vec<_> = COMPRESSED_FORMATS
+            .iter()
+            .cloned()
+            .filter(|&v| !visitor.is_texture_format_supported(v))
+            .collect();
+
         VideoState {
             surfaces: RwLock::new(ObjectPool::new()),
             shaders: RwLock::new(ObjectPool::new()),
             meshes: RwLock::new(ResourcePool::new(MeshLoader::new(frames.clone()))),
-            textures: RwLock::new(ResourcePool::new(TextureLoader::new(frames.clone()))),
+            textures: RwLock::new(ResourcePool::new(TextureLoader::new(
+                frames.clone(),
+                unsupported.clone(),
+            ))),
             render_textures: RwLock::new(ObjectPool::new()),
+            samplers: RwLock::new(ObjectPool::new()),
+            unsupported,
+            leaks: LeakTracker::new(),
             frames,
         }
     }
@@ -72,10 +100,16 @@ impl LifecycleListener for Lifecycle {
             crate::window::inside::resize(dimensions);
         }
 
-        self.state
-            .frames
-            .write_back_buf()
-            .dispatch(self.visitor.as_mut(), self.last_dimensions)?;
+        let mut frame = self.state.frames.write_back_buf();
+        frame.dispatch(self.visitor.as_mut(), self.last_dimensions)?;
+
+        // Replaces the parameters of shaders once their new programs are bound.
+        let mut shaders = self.state.shaders.write().unwrap();
+        for (handle, params) in frame.shaders.drain(..) {
+            if let Some(v) = shaders.get_mut(handle) {
+                *v = params;
+            }
+        }
 
         Ok(())
     }
@@ -90,8 +124,8 @@ impl Drop for VideoSystem {
 impl VideoSystem {
     /// Create a new `VideoSystem`.
     pub fn new() -> CrResult<Self> {
-        let state = Arc::new(VideoState::new());
         let visitor = backends::new()?;
+        let state = Arc::new(VideoState::new(visitor.as_ref()));
 
         Ok(VideoSystem {
             state: state.clone(),
@@ -105,8 +139,8 @@ impl VideoSystem {
 
     /// Create a headless `VideoSystem`.
     pub fn headless() -> Self {
-        let state = Arc::new(VideoState::new());
         let visitor = backends::new_headless();
+        let state = Arc::new(VideoState::new(visitor.as_ref()));
 
         VideoSystem {
             state: state.clone(),
@@ -121,12 +155,80 @@ impl VideoSystem {
     pub(crate) fn frames(&self) -> Arc<DoubleBuf<Frame>> {
         self.state.frames.clone()
     }
+
+    /// Checks if the texture format could be uploaded to the device directly.
+    /// Compressed textures with unsupported formats are decoded into RGBA8 on the
+    /// CPU when they are loaded, if possible.
+    pub fn is_texture_format_supported(&self, format: TextureFormat) -> bool {
+        !self.state.unsupported.contains(&format)
+    }
+
+    /// Returns the tags of texture variants which could be used by the device, in
+    /// the order of preference.
+    pub fn texture_variants(&self) -> Vec<String> {
+        let mut variants = Vec::new();
+        for &v in &COMPRESSED_FORMATS {
+            if let Some(tag) = v.compression() {
+                let tag = tag.to_owned();
+                if self.is_texture_format_supported(v) && !variants.contains(&tag) {
+                    variants.push(tag);
+                }
+            }
+        }
+
+        variants
+    }
+
+    /// Returns all the video resources that are still alive, with the sites where
+    /// they are created and their labels.
+    pub fn leaks(&self) -> Vec<VideoLeak> {
+        let state = &self.state;
+        let mut leaks = Vec::new();
+
+        let surfaces = state.surfaces.read().unwrap();
+        for v in surfaces.keys() {
+            let label = surfaces.get(v).and_then(|params| params.label);
+            leaks.push(state.leaks.leak(v.into(), label));
+        }
+
+        let shaders = state.shaders.read().unwrap();
+        for v in shaders.keys() {
+            let label = shaders.get(v).and_then(|params| params.label);
+            leaks.push(state.leaks.leak(v.into(), label));
+        }
+
+        let meshes = state.meshes.read().unwrap();
+        for v in meshes.keys() {
+            let label = meshes.resource(v).and_then(|params| params.label);
+            leaks.push(state.leaks.leak(v.into(), label));
+        }
+
+        let textures = state.textures.read().unwrap();
+        for v in textures.keys() {
+            let label = textures.resource(v).and_then(|params| params.label);
+            leaks.push(state.leaks.leak(v.into(), label));
+        }
+
+        let render_textures = state.render_textures.read().unwrap();
+        for v in render_textures.keys() {
+            let label = render_textures.get(v).and_then(|params| params.label);
+            leaks.push(state.leaks.leak(v.into(), label));
+        }
+
+        for v in state.samplers.read().unwrap().keys() {
+            leaks.push(state.leaks.leak(v.into(), None));
+        }
+
+        leaks
+    }
 }
 
 impl VideoSystem {
     /// Creates an surface with `SurfaceParams`.
+    #[track_caller]
     pub fn create_surface(&self, params: SurfaceParams) -> Result<SurfaceHandle> {
         let handle = self.state.surfaces.write().unwrap().create(params);
+        self.state.leaks.track(handle.into(), Location::caller());
 
         {
             let cmd = Command::CreateSurface(Box::new((handle, params)));
@@ -154,6 +256,7 @@ impl VideoSystem {
     /// Deletes surface object.
     pub fn delete_surface(&self, handle: SurfaceHandle) {
         if self.state.surfaces.write().unwrap().free(handle).is_some() {
+            self.state.leaks.untrack(handle.into());
             let cmd = Command::DeleteSurface(handle);
             self.state.frames.write().cmds.push(cmd);
         }
@@ -163,6 +266,7 @@ impl VideoSystem {
 impl VideoSystem {
     /// Create a shader with initial shaders and render state. It encapusulates all the
     /// informations we need to configurate graphics pipeline before real drawing.
+    #[track_caller]
     pub fn create_shader(
         &self,
         params: ShaderParams,
@@ -172,6 +276,7 @@ impl VideoSystem {
         params.validate(&vs, &fs)?;
 
         let handle = self.state.shaders.write().unwrap().create(params.clone());
+        self.state.leaks.track(handle.into(), Location::caller());
 
         {
             let cmd = Command::CreateShader(Box::new((handle, params, vs, fs)));
@@ -181,6 +286,31 @@ impl VideoSystem {
         Ok(handle)
     }
 
+    /// Recompiles the shader with new sources and render state in place, while keeping the
+    /// same handle, e.g. when its source files are modified during development. The previous
+    /// program and its parameters are kept if the new sources failed to compile on device,
+    /// and the new parameters are returned by `shader` once the program is recompiled.
+    ///
+    /// Shader sources are not watched for modifications, it's up to the caller to reload
+    /// them.
+    pub fn update_shader(
+        &self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: String,
+        fs: String,
+    ) -> Result<()> {
+        params.validate(&vs, &fs)?;
+
+        if !self.state.shaders.read().unwrap().contains(handle) {
+            return Err(Error::HandleInvalid(format!("{:?}", handle)));
+        }
+
+        let cmd = Command::UpdateShader(Box::new((handle, params, vs, fs)));
+        self.state.frames.write().cmds.push(cmd);
+        Ok(())
+    }
+
     /// Gets the `ShaderParams` if available.
     #[inline]
     pub fn shader(&self, handle: ShaderHandle) -> Option<ShaderParams> {
@@ -201,6 +331,7 @@ impl VideoSystem {
     #[inline]
     pub fn delete_shader(&self, handle: ShaderHandle) {
         if self.state.shaders.write().unwrap().free(handle).is_some() {
+            self.state.leaks.untrack(handle.into());
             let cmd = Command::DeleteShader(handle);
             self.state.frames.write().cmds.push(cmd);
         }
@@ -210,26 +341,35 @@ impl VideoSystem {
 impl VideoSystem {
     /// Create a new mesh object.
     #[inline]
+    #[track_caller]
     pub fn create_mesh<T>(&self, params: MeshParams, data: T) -> CrResult<MeshHandle>
     where
         T: Into<Option<MeshData>>,
     {
         let mut meshes = self.state.meshes.write().unwrap();
-        meshes.create((params, data.into()))
+        let handle = meshes.create((params, data.into()))?;
+        self.state.leaks.track(handle.into(), Location::caller());
+        Ok(handle)
     }
 
     /// Creates a mesh object from file asynchronously.
     #[inline]
+    #[track_caller]
     pub fn create_mesh_from<T: AsRef<str>>(&self, url: T) -> CrResult<MeshHandle> {
         let mut meshes = self.state.meshes.write().unwrap();
-        meshes.create_from(url)
+        let handle = meshes.create_from(url)?;
+        self.state.leaks.track(handle.into(), Location::caller());
+        Ok(handle)
     }
 
     /// Creates a mesh object from file asynchronously.
     #[inline]
+    #[track_caller]
     pub fn create_mesh_from_uuid(&self, uuid: Uuid) -> CrResult<MeshHandle> {
         let mut meshes = self.state.meshes.write().unwrap();
-        meshes.create_from_uuid(uuid)
+        let handle = meshes.create_from_uuid(uuid)?;
+        self.state.leaks.track(handle.into(), Location::caller());
+        Ok(handle)
     }
 
     /// Gets the `MeshParams` if available.
@@ -244,6 +384,31 @@ impl VideoSystem {
         self.state.meshes.read().unwrap().state(handle)
     }
 
+    /// Returns the error of mesh if it's failed to load.
+    #[inline]
+    pub fn mesh_error(&self, handle: MeshHandle) -> Option<failure::Error> {
+        let meshes = self.state.meshes.read().unwrap();
+        meshes.error(handle).map(|err| format_err!("{}", err))
+    }
+
+    /// Returns the loaded and total bytes of the mesh file.
+    #[inline]
+    pub fn mesh_bytes(&self, handle: MeshHandle) -> (u64, u64) {
+        self.state.meshes.read().unwrap().bytes(handle)
+    }
+
+    /// Sets the policy to unload meshes whose last ownerships are dropped.
+    #[inline]
+    pub fn set_mesh_unload_policy(&self, policy: UnloadPolicy) {
+        self.state.meshes.write().unwrap().set_unload_policy(policy);
+    }
+
+    /// Returns the estimated video memory of all the meshes in bytes.
+    #[inline]
+    pub fn mesh_memory(&self) -> usize {
+        self.state.meshes.read().unwrap().memory()
+    }
+
     /// Update a subset of dynamic vertex buffer. Use `offset` specifies the offset
     /// into the buffer object's data store where data replacement will begin, measured
     /// in bytes.
@@ -289,31 +454,48 @@ impl VideoSystem {
     /// Delete mesh object.
     #[inline]
     pub fn delete_mesh(&self, handle: MeshHandle) {
-        self.state.meshes.write().unwrap().delete(handle);
+        let mut meshes = self.state.meshes.write().unwrap();
+        meshes.delete(handle);
+
+        if !meshes.contains(handle) {
+            self.state.leaks.untrack(handle.into());
+        }
     }
 }
 
 impl VideoSystem {
     /// Create texture object. A texture is an image loaded in video memory,
     /// which can be sampled in shaders.
+    #[track_caller]
     pub fn create_texture<T>(&self, params: TextureParams, data: T) -> CrResult<TextureHandle>
     where
         T: Into<Option<TextureData>>,
     {
+        let (mut params, mut data) = (params, data.into());
+        texture_loader::fallback(&self.state.unsupported, &mut params, data.as_mut())?;
+
         let mut textures = self.state.textures.write().unwrap();
-        textures.create((params, data.into()))
+        let handle = textures.create((params, data))?;
+        self.state.leaks.track(handle.into(), Location::caller());
+        Ok(handle)
     }
 
     /// Creates a texture object from file asynchronously.
+    #[track_caller]
     pub fn create_texture_from<T: AsRef<str>>(&self, url: T) -> CrResult<TextureHandle> {
         let mut textures = self.state.textures.write().unwrap();
-        textures.create_from(url)
+        let handle = textures.create_from(url)?;
+        self.state.leaks.track(handle.into(), Location::caller());
+        Ok(handle)
     }
 
     /// Creates a texture object from file asynchronously.
+    #[track_caller]
     pub fn create_texture_from_uuid(&self, uuid: Uuid) -> CrResult<TextureHandle> {
         let mut textures = self.state.textures.write().unwrap();
-        textures.create_from_uuid(uuid)
+        let handle = textures.create_from_uuid(uuid)?;
+        self.state.leaks.track(handle.into(), Location::caller());
+        Ok(handle)
     }
 
     /// Get the resource state of specified texture.
@@ -322,6 +504,35 @@ impl VideoSystem {
         self.state.textures.read().unwrap().state(handle)
     }
 
+    /// Returns the error of texture if it's failed to load.
+    #[inline]
+    pub fn texture_error(&self, handle: TextureHandle) -> Option<failure::Error> {
+        let textures = self.state.textures.read().unwrap();
+        textures.error(handle).map(|err| format_err!("{}", err))
+    }
+
+    /// Returns the loaded and total bytes of the texture file.
+    #[inline]
+    pub fn texture_bytes(&self, handle: TextureHandle) -> (u64, u64) {
+        self.state.textures.read().unwrap().bytes(handle)
+    }
+
+    /// Sets the policy to unload textures whose last ownerships are dropped.
+    #[inline]
+    pub fn set_texture_unload_policy(&self, policy: UnloadPolicy) {
+        self.state
+            .textures
+            .write()
+            .unwrap()
+            .set_unload_policy(policy);
+    }
+
+    /// Returns the estimated video memory of all the textures in bytes.
+    #[inline]
+    pub fn texture_memory(&self) -> usize {
+        self.state.textures.read().unwrap().memory()
+    }
+
     /// Update a contiguous subregion of an existing two-dimensional texture object.
     pub fn update_texture(
         &self,
@@ -343,7 +554,12 @@ impl VideoSystem {
 
     /// Delete the texture object.
     pub fn delete_texture(&self, handle: TextureHandle) {
-        self.state.textures.write().unwrap().delete(handle);
+        let mut textures = self.state.textures.write().unwrap();
+        textures.delete(handle);
+
+        if !textures.contains(handle) {
+            self.state.leaks.untrack(handle.into());
+        }
     }
     /// Gets the `TextureParams` if available.
     #[inline]
@@ -354,11 +570,15 @@ impl VideoSystem {
 
 impl VideoSystem {
     /// Create render texture object, which could be attached with a framebuffer.
+    #[track_caller]
     pub fn create_render_texture(
         &self,
         params: RenderTextureParams,
     ) -> Result<RenderTextureHandle> {
+        params.validate()?;
+
         let handle = self.state.render_textures.write().unwrap().create(params);
+        self.state.leaks.track(handle.into(), Location::caller());
 
         {
             let cmd = Command::CreateRenderTexture(Box::new((handle, params)));
@@ -398,12 +618,54 @@ impl VideoSystem {
             .free(handle)
             .is_some()
         {
+            self.state.leaks.untrack(handle.into());
             let cmd = Command::DeleteRenderTexture(handle);
             self.state.frames.write().cmds.push(cmd);
         }
     }
 }
 
+impl VideoSystem {
+    /// Create sampler object, which could be bound alongside a texture to override its
+    /// sampling states.
+    #[track_caller]
+    pub fn create_sampler(&self, params: SamplerParams) -> Result<SamplerHandle> {
+        let handle = self.state.samplers.write().unwrap().create(params);
+        self.state.leaks.track(handle.into(), Location::caller());
+
+        {
+            let cmd = Command::CreateSampler(Box::new((handle, params)));
+            self.state.frames.write().cmds.push(cmd);
+        }
+
+        Ok(handle)
+    }
+
+    /// Gets the `SamplerParams` if available.
+    pub fn sampler(&self, handle: SamplerHandle) -> Option<SamplerParams> {
+        self.state.samplers.read().unwrap().get(handle).cloned()
+    }
+
+    /// Get the resource state of specified sampler.
+    #[inline]
+    pub fn sampler_state(&self, handle: SamplerHandle) -> ResourceState {
+        if self.state.samplers.read().unwrap().contains(handle) {
+            ResourceState::Ok
+        } else {
+            ResourceState::NotReady
+        }
+    }
+
+    /// Delete the sampler object.
+    pub fn delete_sampler(&self, handle: SamplerHandle) {
+        if self.state.samplers.write().unwrap().free(handle).is_some() {
+            self.state.leaks.untrack(handle.into());
+            let cmd = Command::DeleteSampler(handle);
+            self.state.frames.write().cmds.push(cmd);
+        }
+    }
+}
+
 fn dimensions_pixels() -> Vector2<u32> {
     let dimensions = crate::window::dimensions();
     let dpr = crate::window::device_pixel_ratio();
@@ -29,7 +29,8 @@ impl GlutinVisitor {
             .with_multisampling(params.multisample as u16)
             .with_gl_profile(glutin::GlProfile::Core)
             .with_gl(glutin::GlRequest::Latest)
-            .with_vsync(params.vsync);
+            .with_vsync(params.vsync)
+            .with_gl_debug_flag(params.debug_context);
 
         let events_loop = glutin::EventsLoop::new();
         let window = glutin::GlWindow::new(builder, context, &events_loop).unwrap();
@@ -27,6 +27,9 @@ pub struct WindowParams {
     pub multisample: u16,
     /// Specifies whether should we have vsync.
     pub vsync: bool,
+    /// Requests a debug context, whose errors and performance warnings are reported
+    /// through `log` by the video backend. It should only be used during development.
+    pub debug_context: bool,
 }
 
 impl Default for WindowParams {
@@ -36,6 +39,7 @@ impl Default for WindowParams {
             size: Vector2::new(640, 320),
             multisample: 2,
             vsync: false,
+            debug_context: false,
         }
     }
 }
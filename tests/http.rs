@@ -0,0 +1,213 @@
+extern crate crayon;
+
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crayon::res::request::{Request, Response};
+use crayon::res::url::Url;
+use crayon::res::vfs::compress::Method;
+use crayon::res::vfs::http::{Http, HttpPack, HttpParams};
+use crayon::res::vfs::pack::PackWriter;
+use crayon::res::vfs::VFS;
+use crayon::sched::prelude::{LatchProbe, LockLatch};
+
+const CONTENT: &[u8] = b"0123456789abcdef";
+
+/// A stub server which serves keep-alive connections, and counts them.
+struct Stub {
+    port: u16,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Stub {
+    fn new(pack: Vec<u8>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let flaky = Arc::new(AtomicUsize::new(0));
+        let pack = Arc::new(pack);
+
+        let counter = connections.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let flaky = flaky.clone();
+                let pack = pack.clone();
+                thread::spawn(move || serve(stream.unwrap(), &flaky, &pack));
+            }
+        });
+
+        Stub { port, connections }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, path)
+    }
+}
+
+fn serve(stream: TcpStream, flaky: &AtomicUsize, pack: &[u8]) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+
+        let path = line.split_whitespace().nth(1).unwrap().to_owned();
+        let mut range = None;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header.trim().is_empty() {
+                break;
+            }
+
+            let lower = header.to_lowercase();
+            if lower.starts_with("range: bytes=") {
+                range = Some(header.trim()["range: bytes=".len()..].to_owned());
+            }
+        }
+
+        let (status, body) = match path.as_str() {
+            "/file" => respond(CONTENT, range),
+            "/norange" => (200, CONTENT.to_vec()),
+            "/pack" => respond(pack, range),
+            "/flaky" if flaky.fetch_add(1, Ordering::SeqCst) == 0 => (503, Vec::new()),
+            "/flaky" => (200, CONTENT.to_vec()),
+            _ => (404, Vec::new()),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\n\r\n",
+            status,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(&body).unwrap();
+    }
+}
+
+fn respond(content: &[u8], range: Option<String>) -> (u16, Vec<u8>) {
+    let range = match range {
+        Some(range) => range,
+        None => return (200, content.to_vec()),
+    };
+
+    let mut iter = range.split('-');
+    let (start, end) = (iter.next().unwrap(), iter.next().unwrap());
+    let (start, end) = if start.is_empty() {
+        let len: usize = end.parse().unwrap();
+        (content.len().saturating_sub(len), content.len())
+    } else {
+        (start.parse().unwrap(), end.parse::<usize>().unwrap() + 1)
+    };
+
+    if start >= content.len() {
+        return (416, Vec::new());
+    }
+
+    (206, content[start..end.min(content.len())].to_vec())
+}
+
+// Waits for the response, the requests are executed on the threads of `Http`.
+fn wait(state: &LockLatch<Response>) -> Response {
+    while !state.is_set() {
+        thread::yield_now();
+    }
+
+    state.take()
+}
+
+fn params() -> HttpParams {
+    let mut params = HttpParams::default();
+    params.retry_delay = Duration::from_millis(1);
+    params
+}
+
+#[test]
+fn fetch() {
+    let stub = Stub::new(Vec::new());
+    let http = Http::with_params(params());
+
+    assert_eq!(&http.fetch(&stub.url("/file"), None).unwrap()[..], CONTENT);
+    assert_eq!(
+        &http.fetch(&stub.url("/file"), Some(2..5)).unwrap()[..],
+        b"234"
+    );
+    assert_eq!(&http.fetch_tail(&stub.url("/file"), 3).unwrap()[..], b"def");
+    assert_eq!(
+        &http.fetch(&stub.url("/norange"), Some(2..5)).unwrap()[..],
+        b"234"
+    );
+    assert_eq!(
+        &http.fetch_tail(&stub.url("/norange"), 3).unwrap()[..],
+        b"def"
+    );
+    assert!(http.fetch(&stub.url("/norange"), Some(10..20)).is_err());
+
+    // Transient failures are retried, and others are not.
+    assert_eq!(&http.fetch(&stub.url("/flaky"), None).unwrap()[..], CONTENT);
+    assert!(http.fetch(&stub.url("/404"), None).is_err());
+
+    // All the requests above share one keep-alive connection.
+    assert_eq!(stub.connections.load(Ordering::SeqCst), 1);
+
+    let state = Request::latch();
+    http.request(&Url::new(stub.url("/file")).unwrap(), state.clone());
+    assert_eq!(&wait(&state).unwrap()[..], CONTENT);
+}
+
+#[test]
+fn chunk() {
+    let stub = Stub::new(Vec::new());
+    let http = Http::with_params(params());
+
+    for path in &["/file", "/norange"] {
+        let url = stub.url(path);
+        assert_eq!(&http.fetch_chunk(&url, 2..5).unwrap()[..], b"234");
+        assert_eq!(&http.fetch_chunk(&url, 12..20).unwrap()[..], b"cdef");
+        assert!(http.fetch_chunk(&url, 16..20).unwrap().is_empty());
+    }
+
+    let state = Request::latch();
+    let url = Url::new(stub.url("/file")).unwrap();
+    http.request_range(&url, 14..32, state.clone());
+    assert_eq!(&wait(&state).unwrap()[..], b"ef");
+}
+
+#[test]
+fn pack() {
+    let mut writer = PackWriter::new(Cursor::new(Vec::new()));
+    writer.add("MANIFEST", b"manifest").unwrap();
+    writer.add("A1B2", CONTENT).unwrap();
+    writer
+        .add_compressed("C3D4", Method::Zstd, CONTENT)
+        .unwrap();
+    let bytes = writer.finish().unwrap().into_inner();
+
+    let stub = Stub::new(bytes);
+    let pack = HttpPack::with_http(stub.url("/pack"), Http::with_params(params()));
+
+    let load = |path: &str| {
+        let state = Request::latch();
+        pack.request(&Url::new(path).unwrap(), state.clone());
+        wait(&state)
+    };
+
+    assert_eq!(&load("pack://resources/A1B2").unwrap()[..], CONTENT);
+    assert_eq!(&load("pack://resources/C3D4").unwrap()[..], CONTENT);
+    assert_eq!(&load("pack://resources/MANIFEST").unwrap()[..], b"manifest");
+    assert!(load("pack://resources/404").is_err());
+
+    let pack = HttpPack::with_http(stub.url("/404"), Http::with_params(params()));
+    let state = Request::latch();
+    pack.request(&Url::new("pack://resources/A1B2").unwrap(), state.clone());
+    assert!(wait(&state).is_err());
+}
@@ -0,0 +1,52 @@
+extern crate crayon;
+
+use crayon::bincode;
+use crayon::prelude::*;
+use crayon::res::manifest::ManifestBuilder;
+use crayon::res::vfs::memory::Memory;
+use crayon::uuid::Uuid;
+use crayon::video::assets::texture_loader;
+
+#[test]
+fn texture() {
+    let uuid = Uuid::from_bytes([1; 16]);
+
+    let mut params = TextureParams::default();
+    params.dimensions = Vector2::new(2, 2);
+    params.label = Some("crate".into());
+    let data = TextureData {
+        bytes: vec![vec![255; 16].into_boxed_slice()],
+    };
+
+    let mut payload = bincode::serialize(&params).unwrap();
+    payload.extend(bincode::serialize(&data).unwrap());
+    let bytes = texture_loader::FORMAT.write(&payload);
+
+    let mut builder = ManifestBuilder::new();
+    builder.add("crate.png", uuid, &bytes);
+
+    let memory = Memory::new();
+    memory.insert_manifest(&builder).unwrap();
+    memory.insert_resource(uuid, bytes);
+
+    let mut setup = Params::default();
+    setup.res.schemas.add("mem", memory);
+    setup.res.dirs.push("mem://res/".into());
+    crayon::application::oneshot_with(setup).unwrap();
+
+    // Loads the texture from memory, as if its loaded from disk.
+    let texture = video::create_texture_from("mem://res/crate.png").unwrap();
+    while video::texture_state(texture) == ResourceState::NotReady {
+        crayon::application::step().unwrap();
+    }
+
+    assert_eq!(video::texture_state(texture), ResourceState::Ok);
+    let v = video::texture(texture).unwrap();
+    assert_eq!(v.dimensions, Vector2::new(2, 2));
+    assert_eq!(v.label, Some("crate".into()));
+
+    assert!(video::create_texture_from("mem://res/unknown.png").is_err());
+
+    video::delete_texture(texture);
+    assert!(video::leaks().is_empty());
+}
@@ -0,0 +1,53 @@
+extern crate crayon;
+
+use crayon::prelude::*;
+use crayon::video::assets::shader::{UniformVariable, UniformVariableType};
+
+#[test]
+fn sampler() {
+    crayon::application::oneshot().unwrap();
+
+    let mut params = SamplerParams::default();
+    params.set_wrap(TextureWrap::Repeat);
+    params.set_filter(TextureFilter::Nearest);
+    params.mip_filter = Some(TextureFilter::Linear);
+
+    let sampler = video::create_sampler(params).unwrap();
+    assert_eq!(video::sampler_state(sampler), ResourceState::Ok);
+    assert_eq!(video::sampler(sampler), Some(params));
+
+    let other = video::create_sampler(SamplerParams::default()).unwrap();
+    assert!(other != sampler);
+    assert_eq!(video::sampler(other), Some(SamplerParams::default()));
+
+    // Binds the sampler alongside textures, which keeps the uniform type of textures.
+    let texture = video::create_texture(TextureParams::default(), None).unwrap();
+    let v: UniformVariable = (texture, sampler).into();
+    assert_eq!(v.variable_type(), UniformVariableType::Texture);
+    assert_eq!(v.sampler(), Some(sampler));
+
+    let v: UniformVariable = texture.into();
+    assert_eq!(v.variable_type(), UniformVariableType::Texture);
+    assert_eq!(v.sampler(), None);
+
+    let mut rt = RenderTextureParams::default();
+    rt.sampler = true;
+    let rt = video::create_render_texture(rt).unwrap();
+    let v: UniformVariable = (rt, other).into();
+    assert_eq!(v.variable_type(), UniformVariableType::RenderTexture);
+    assert_eq!(v.sampler(), Some(other));
+
+    video::delete_render_texture(rt);
+    video::delete_texture(texture);
+
+    video::delete_sampler(sampler);
+    assert_eq!(video::sampler_state(sampler), ResourceState::NotReady);
+    assert!(video::sampler(sampler).is_none());
+
+    // Deleting a invalid sampler does nothing.
+    video::delete_sampler(sampler);
+    assert_eq!(video::sampler(other), Some(SamplerParams::default()));
+
+    video::delete_sampler(other);
+    assert!(video::leaks().is_empty());
+}
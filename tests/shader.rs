@@ -0,0 +1,32 @@
+extern crate crayon;
+
+use crayon::prelude::*;
+use crayon::video::errors::Error;
+
+#[test]
+fn update() {
+    crayon::application::oneshot().unwrap();
+
+    let mut params = ShaderParams::default();
+    params.label = Some("v1".into());
+    let (vs, fs) = ("void main() {}".to_owned(), "void main() {}".to_owned());
+    let shader = video::create_shader(params.clone(), vs.clone(), fs.clone()).unwrap();
+
+    // Recompiles the shader in place, the handle is kept. The parameters are replaced once
+    // the program is recompiled while dispatching the frame.
+    params.label = Some("v2".into());
+    video::update_shader(shader, params.clone(), vs.clone(), fs.clone()).unwrap();
+    assert_eq!(video::shader_state(shader), ResourceState::Ok);
+    assert_eq!(video::shader(shader).unwrap().label, Some("v1".into()));
+
+    // Invalid sources are rejected, and the previous shader is kept.
+    params.label = Some("v3".into());
+    let rsp = video::update_shader(shader, params.clone(), vs.clone(), String::new());
+    assert!(matches!(rsp, Err(Error::ShaderInvalid(_))));
+    assert_eq!(video::shader(shader).unwrap().label, Some("v1".into()));
+
+    video::delete_shader(shader);
+    let rsp = video::update_shader(shader, params, vs, fs);
+    assert!(matches!(rsp, Err(Error::HandleInvalid(_))));
+    assert!(video::leaks().is_empty());
+}
@@ -0,0 +1,41 @@
+extern crate crayon;
+
+use crayon::prelude::*;
+use crayon::video::leaks::VideoResource;
+
+#[test]
+fn leaks() {
+    crayon::application::oneshot().unwrap();
+
+    let mut params = SurfaceParams::default();
+    params.label = Some("main".into());
+    let surface = video::create_surface(params).unwrap();
+    let line = line!() + 1;
+    let texture = video::create_texture(TextureParams::default(), None).unwrap();
+    let sampler = video::create_sampler(SamplerParams::default()).unwrap();
+
+    let leaks = video::leaks();
+    assert_eq!(leaks.len(), 3);
+
+    let leak = leaks
+        .iter()
+        .find(|v| v.resource == VideoResource::from(texture))
+        .unwrap();
+    assert_eq!(leak.site.unwrap().file(), file!());
+    assert_eq!(leak.site.unwrap().line(), line);
+    assert!(leak.label.is_none());
+
+    let leak = leaks
+        .iter()
+        .find(|v| v.resource == VideoResource::from(surface))
+        .unwrap();
+    assert_eq!(leak.label.unwrap().as_str(), "main");
+
+    video::delete_surface(surface);
+    video::delete_texture(texture);
+    assert_eq!(video::leaks().len(), 1);
+    assert_eq!(video::leaks()[0].resource, VideoResource::from(sampler));
+
+    video::delete_sampler(sampler);
+    assert!(video::leaks().is_empty());
+}
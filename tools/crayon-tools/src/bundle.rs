@@ -0,0 +1,156 @@
+//! Walks through a directory of assets, and compiles them into resources with a `Manifest`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crayon::res::header;
+use crayon::res::manifest::{self, Manifest, ManifestItem};
+use crayon::uuid::Uuid;
+
+use super::meta::Meta;
+use super::resource::Resource;
+use super::Result;
+use super::{gltf, obj, png};
+
+/// Compiles the assets under source directory into the destination directory.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    src: PathBuf,
+    dst: PathBuf,
+}
+
+impl Bundle {
+    pub fn new<P1: Into<PathBuf>, P2: Into<PathBuf>>(src: P1, dst: P2) -> Self {
+        Bundle {
+            src: src.into(),
+            dst: dst.into(),
+        }
+    }
+
+    /// Imports all the assets, and writes the resources and `MANIFEST` into the destination
+    /// directory. The `.meta` files of assets are updated if there are new resources.
+    ///
+    /// Assets are named by their paths relative to the source directory, and the resources
+    /// other than the main one are named as `path#name`.
+    pub fn build(&self) -> Result<Manifest> {
+        let mut assets = Vec::new();
+        walk(&self.src, &mut assets)?;
+        assets.sort();
+
+        fs::create_dir_all(&self.dst)?;
+
+        let mut manifest = Manifest::new();
+        for asset in &assets {
+            let resources = self
+                .import(asset)
+                .map_err(|err| format_err!("Failed to import {}. {}", asset.display(), err))?;
+
+            if let Some((mut meta, resources)) = resources {
+                let filename = self.filename(asset);
+                let base = manifest.items.len();
+
+                for v in &resources {
+                    let uuid = meta.uuid(&v.name);
+                    fs::write(self.dst.join(format!("{:X}", uuid.to_simple())), &v.bytes)?;
+
+                    let filename = if v.name.is_empty() {
+                        filename.clone()
+                    } else {
+                        format!("{}#{}", filename, v.name)
+                    };
+
+                    let dependencies: Vec<_> = v.dependencies.iter().map(|i| base + i).collect();
+                    manifest.items.push(ManifestItem {
+                        filename: manifest.buf.extend_from_str(filename),
+                        dependencies: manifest.buf.extend_from_slice(&dependencies),
+                        uuid,
+                        hash: Some(header::crc32(&v.bytes)),
+                        size: Some(v.bytes.len() as u64),
+                    });
+                }
+
+                if meta != Meta::load(asset)? {
+                    meta.save(asset)?;
+                }
+            }
+        }
+
+        check_duplicates(&manifest)?;
+
+        fs::write(self.dst.join(manifest::NAME), manifest.to_bytes()?)?;
+        Ok(manifest)
+    }
+
+    /// Imports the asset by its extension, `None` is returned if it's not supported.
+    fn import(&self, asset: &Path) -> Result<Option<(Meta, Vec<Resource>)>> {
+        let extension = asset
+            .extension()
+            .and_then(|v| v.to_str())
+            .map(|v| v.to_lowercase());
+
+        let mut meta = Meta::load(asset)?;
+        let resources = match extension.as_deref() {
+            Some("png") => {
+                let (params, data) = png::import(&fs::read(asset)?)?;
+                vec![Resource::texture("", &params, &data)?]
+            }
+            Some("obj") => {
+                let (params, data) = obj::import(&fs::read(asset)?)?;
+                vec![Resource::mesh("", &params, &data)?]
+            }
+            Some("gltf") | Some("glb") => gltf::import(asset, &fs::read(asset)?, &mut meta)?,
+            Some("ogg") => vec![Resource::audio("", &fs::read(asset)?)?],
+            _ => return Ok(None),
+        };
+
+        Ok(Some((meta, resources)))
+    }
+
+    fn filename(&self, asset: &Path) -> String {
+        let relative = asset.strip_prefix(&self.src).unwrap_or(asset);
+        let components: Vec<_> = relative
+            .components()
+            .map(|v| v.as_os_str().to_string_lossy().into_owned())
+            .collect();
+
+        components.join("/")
+    }
+}
+
+/// Collects all the files under directory recursively, except `.meta` and hidden files.
+fn walk(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|v| v.to_str()).unwrap_or("");
+
+        if name.starts_with('.') || name.ends_with(".meta") {
+            continue;
+        }
+
+        if path.is_dir() {
+            walk(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copied `.meta` files could make different resources share the same UUID.
+fn check_duplicates(manifest: &Manifest) -> Result<()> {
+    let mut uuids: Vec<(Uuid, &str)> = manifest
+        .items
+        .iter()
+        .map(|v| (v.uuid, manifest.buf.as_str(v.filename)))
+        .collect();
+
+    uuids.sort();
+    for v in uuids.windows(2) {
+        if v[0].0 == v[1].0 {
+            bail!("{} and {} have the same UUID {}.", v[0].1, v[1].1, v[0].0);
+        }
+    }
+
+    Ok(())
+}
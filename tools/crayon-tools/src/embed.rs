@@ -0,0 +1,89 @@
+//! Embeds a directory of resources into the binary for single-file distribution.
+//!
+//! `embed` generates a list of `include_bytes!` in the build script, which is loaded by
+//! `Memory::from_static` at runtime:
+//!
+//! ```rust,ignore
+//! // build.rs
+//! let out = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("resources.rs");
+//! crayon_tools::embed::embed("resources", &out).unwrap();
+//!
+//! // main.rs
+//! let memory = Memory::from_static(include!(concat!(env!("OUT_DIR"), "/resources.rs")));
+//! params.schemas.add("embedded", memory);
+//! params.dirs.push("embedded://res/".into());
+//! ```
+
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::Result;
+
+/// Generates the list of files under `dir` recursively into `out`, which could be
+/// embedded into binary with `include!` and loaded by `Memory::from_static`. It should be
+/// called from the build script, and the build script is rerun if any file is changed.
+pub fn embed<T1: AsRef<Path>, T2: AsRef<Path>>(dir: T1, out: T2) -> Result<()> {
+    let dir = fs::canonicalize(dir.as_ref())?;
+    let mut files = Vec::new();
+    walk(&dir, &mut files)?;
+    files.sort();
+
+    let mut code = String::from("&[\n");
+    for v in files {
+        let path = v.strip_prefix(&dir)?;
+        let path: Vec<_> = path.iter().map(|v| v.to_string_lossy()).collect();
+        writeln!(
+            code,
+            "    ({:?}, &include_bytes!({:?})[..]),",
+            path.join("/"),
+            v.display().to_string()
+        )?;
+
+        println!("cargo:rerun-if-changed={}", v.display());
+    }
+
+    code.push(']');
+    println!("cargo:rerun-if-changed={}", dir.display());
+    fs::write(out, code)?;
+    Ok(())
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for v in fs::read_dir(dir)? {
+        let v = v?;
+        if v.file_type()?.is_dir() {
+            println!("cargo:rerun-if-changed={}", v.path().display());
+            walk(&v.path(), files)?;
+        } else {
+            files.push(v.path());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crayon::res::vfs::user::User;
+
+    use super::*;
+
+    #[test]
+    fn embed() {
+        // The temporary directory is removed when it's dropped.
+        let root = User::temp().unwrap();
+        let dir = root.root().join("resources");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a"), b"a").unwrap();
+        fs::write(dir.join("sub").join("b"), b"b").unwrap();
+
+        let out = root.root().join("resources.rs");
+        super::embed(&dir, &out).unwrap();
+        let code = fs::read_to_string(&out).unwrap();
+
+        assert!(code.starts_with("&[\n    (\"a\", &include_bytes!("));
+        assert!(code.contains("(\"sub/b\", &include_bytes!("));
+        assert!(code.ends_with(']'));
+    }
+}
@@ -0,0 +1,494 @@
+//! Imports `glTF` 2.0 models, both the `.gltf` and binary `.glb` containers are supported.
+//!
+//! Every mesh in the model is imported as a mesh resource, whose primitives become the
+//! sub-meshes. The nodes of default scene are imported as the main resource, which is a
+//! `Prefab` that depends on all the meshes.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crayon::math::prelude::{Matrix3, Quaternion, Vector3};
+use crayon::video::assets::prelude::*;
+use crayon_world::assets::prefab::{Prefab, PrefabNode};
+use crayon_world::spatial::prelude::Transform;
+use serde_json;
+
+use super::meta::Meta;
+use super::resource::{self, Resource, Vertex};
+use super::Result;
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+
+const TRIANGLES: u32 = 4;
+
+/// Imports a `glTF` file. The `path` is used to locate the external buffers, and the
+/// UUIDs of meshes are assigned by `meta`.
+pub fn import(path: &Path, bytes: &[u8], meta: &mut Meta) -> Result<Vec<Resource>> {
+    let (doc, bin) = if bytes.starts_with(GLB_MAGIC) {
+        parse_glb(bytes)?
+    } else {
+        (serde_json::from_slice::<Document>(bytes)?, None)
+    };
+
+    let mut buffers = Vec::new();
+    for (i, v) in doc.buffers.iter().enumerate() {
+        let buf = match v.uri {
+            Some(ref uri) if uri.starts_with("data:") => {
+                let index = uri
+                    .find(";base64,")
+                    .ok_or_else(|| format_err!("[glTF] Data URI of buffer {} is invalid.", i))?;
+                base64(&uri[index + 8..])?
+            }
+            Some(ref uri) => {
+                let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                fs::read(dir.join(uri))?
+            }
+            None => bin
+                .clone()
+                .ok_or_else(|| format_err!("[glTF] BIN chunk of buffer {} is missing.", i))?,
+        };
+
+        if buf.len() < v.byte_length {
+            bail!("[glTF] Buffer {} is truncated.", i);
+        }
+
+        buffers.push(buf);
+    }
+
+    let reader = Reader {
+        doc: &doc,
+        buffers: &buffers,
+    };
+
+    let mut resources = vec![];
+    let mut prefab = Prefab {
+        nodes: Vec::new(),
+        universe_meshes: Vec::new(),
+        meshes: Vec::new(),
+    };
+
+    for (i, mesh) in doc.meshes.iter().enumerate() {
+        let name = format!("mesh{}", i);
+        let (params, data) = reader.mesh(mesh)?;
+        prefab.universe_meshes.push(meta.uuid(&name));
+        resources.push(Resource::mesh(name, &params, &data)?);
+    }
+
+    let roots = match doc
+        .scene
+        .or(if doc.scenes.is_empty() { None } else { Some(0) })
+    {
+        Some(scene) => doc
+            .scenes
+            .get(scene)
+            .ok_or_else(|| format_err!("[glTF] Scene {} is undefined.", scene))?
+            .nodes
+            .clone(),
+        None => Vec::new(),
+    };
+
+    add_siblings(&doc, &roots, &mut prefab.nodes)?;
+
+    let dependencies = (1..=resources.len()).collect();
+    resources.insert(0, Resource::prefab("", &prefab, dependencies)?);
+    Ok(resources)
+}
+
+/// Appends the nodes and their descendants into prefab, and returns the index of first one.
+fn add_siblings(
+    doc: &Document,
+    nodes: &[usize],
+    out: &mut Vec<PrefabNode>,
+) -> Result<Option<usize>> {
+    let mut prev: Option<usize> = None;
+    let mut first = None;
+
+    for &v in nodes {
+        let node = doc
+            .nodes
+            .get(v)
+            .ok_or_else(|| format_err!("[glTF] Node {} is undefined.", v))?;
+
+        let index = out.len();
+        out.push(PrefabNode {
+            name: node.name.clone().unwrap_or_else(|| format!("node{}", v)),
+            local_transform: node.transform(),
+            first_child: None,
+            next_sib: None,
+            mesh_renderer: node.mesh,
+        });
+
+        if out.len() > doc.nodes.len() {
+            bail!("[glTF] Node {} is referenced more than once.", v);
+        }
+
+        out[index].first_child = add_siblings(doc, &node.children, out)?;
+
+        match prev {
+            Some(prev) => out[prev].next_sib = Some(index),
+            None => first = Some(index),
+        }
+
+        prev = Some(index);
+    }
+
+    Ok(first)
+}
+
+fn parse_glb(bytes: &[u8]) -> Result<(Document, Option<Vec<u8>>)> {
+    let u32_at = |i: usize| -> Result<u32> {
+        let v = bytes
+            .get(i..i + 4)
+            .ok_or_else(|| format_err!("[glTF] GLB is truncated."))?;
+        Ok(u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+    };
+
+    if u32_at(4)? != 2 {
+        bail!("[glTF] Only GLB version 2 is supported.");
+    }
+
+    let mut doc = None;
+    let mut bin = None;
+    let mut cursor = 12;
+
+    while cursor + 8 <= bytes.len() {
+        let len = u32_at(cursor)? as usize;
+        let tp = u32_at(cursor + 4)?;
+        let data = bytes
+            .get(cursor + 8..cursor + 8 + len)
+            .ok_or_else(|| format_err!("[glTF] GLB chunk is truncated."))?;
+
+        match tp {
+            GLB_JSON => doc = Some(serde_json::from_slice(data)?),
+            GLB_BIN => bin = Some(data.to_vec()),
+            _ => {}
+        }
+
+        cursor += 8 + len;
+    }
+
+    let doc = doc.ok_or_else(|| format_err!("[glTF] JSON chunk of GLB is missing."))?;
+    Ok((doc, bin))
+}
+
+fn base64(text: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for c in text.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => bail!("[glTF] Invalid base64 character {:?}.", c as char),
+        };
+
+        acc = (acc << 6) | u32::from(v);
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+
+    Ok(out)
+}
+
+struct Reader<'a> {
+    doc: &'a Document,
+    buffers: &'a [Vec<u8>],
+}
+
+impl<'a> Reader<'a> {
+    fn mesh(&self, mesh: &Mesh) -> Result<(MeshParams, MeshData)> {
+        let mut verts = Vec::new();
+        let mut idxes = Vec::new();
+        let mut sub_mesh_offsets = Vec::new();
+
+        for v in &mesh.primitives {
+            if v.mode != TRIANGLES {
+                bail!("[glTF] Primitive mode {} is not supported.", v.mode);
+            }
+
+            let positions = match v.attributes.get("POSITION") {
+                Some(&accessor) => self.floats(accessor, 3)?,
+                None => bail!("[glTF] POSITION of primitive is missing."),
+            };
+
+            let num = positions.len() / 3;
+            let normals = match v.attributes.get("NORMAL") {
+                Some(&accessor) => self.floats(accessor, 3)?,
+                None => vec![0.0; num * 3],
+            };
+
+            let texcoords = match v.attributes.get("TEXCOORD_0") {
+                Some(&accessor) => self.floats(accessor, 2)?,
+                None => vec![0.0; num * 2],
+            };
+
+            if normals.len() != num * 3 || texcoords.len() != num * 2 {
+                bail!("[glTF] Attributes of primitive have different lengths.");
+            }
+
+            let base = verts.len() as u32;
+            sub_mesh_offsets.push(idxes.len());
+
+            for i in 0..num {
+                verts.push(Vertex {
+                    position: [positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]],
+                    normal: [normals[i * 3], normals[i * 3 + 1], normals[i * 3 + 2]],
+                    texcoord: [texcoords[i * 2], texcoords[i * 2 + 1]],
+                });
+            }
+
+            match v.indices {
+                Some(accessor) => {
+                    for i in self.indices(accessor)? {
+                        if i as usize >= num {
+                            bail!("[glTF] Index {} is out of bounds.", i);
+                        }
+
+                        idxes.push(base + i);
+                    }
+                }
+                None => idxes.extend((0..num as u32).map(|i| base + i)),
+            }
+
+            // Sub-meshes must not be empty, see `MeshParams::validate`.
+            if idxes.len() == *sub_mesh_offsets.last().unwrap() {
+                bail!("[glTF] Primitive of mesh is empty.");
+            }
+        }
+
+        Ok(resource::mesh(&verts, &idxes, &sub_mesh_offsets))
+    }
+
+    /// Returns the bytes of elements of accessor, and the stride between them.
+    fn elements(&self, index: usize, size: usize) -> Result<(&'a [u8], usize, &'a Accessor)> {
+        let accessor = self
+            .doc
+            .accessors
+            .get(index)
+            .ok_or_else(|| format_err!("[glTF] Accessor {} is undefined.", index))?;
+
+        let view = accessor
+            .buffer_view
+            .and_then(|v| self.doc.buffer_views.get(v))
+            .ok_or_else(|| format_err!("[glTF] BufferView of accessor {} is invalid.", index))?;
+
+        let stride = view.byte_stride.unwrap_or(size);
+        let start = view.byte_offset + accessor.byte_offset;
+        let end = start + stride * accessor.count.saturating_sub(1) + size;
+
+        let bytes = self
+            .buffers
+            .get(view.buffer)
+            .and_then(|v| v.get(start..end))
+            .filter(|_| accessor.count > 0 && end <= view.byte_offset + view.byte_length)
+            .ok_or_else(|| format_err!("[glTF] Accessor {} is out of bounds.", index))?;
+
+        Ok((bytes, stride, accessor))
+    }
+
+    fn floats(&self, index: usize, components: usize) -> Result<Vec<f32>> {
+        let accessor = self
+            .doc
+            .accessors
+            .get(index)
+            .ok_or_else(|| format_err!("[glTF] Accessor {} is undefined.", index))?;
+
+        let size = match accessor.component_type {
+            5126 => 4,
+            5123 if accessor.normalized => 2,
+            5121 if accessor.normalized => 1,
+            v => bail!(
+                "[glTF] Component type {} of accessor {} is not supported.",
+                v,
+                index
+            ),
+        };
+
+        let (bytes, stride, accessor) = self.elements(index, size * components)?;
+
+        let mut out = Vec::with_capacity(accessor.count * components);
+        for i in 0..accessor.count {
+            for c in 0..components {
+                let v = &bytes[i * stride + c * size..];
+                out.push(match size {
+                    4 => f32::from_le_bytes([v[0], v[1], v[2], v[3]]),
+                    2 => f32::from(u16::from_le_bytes([v[0], v[1]])) / 65535.0,
+                    _ => f32::from(v[0]) / 255.0,
+                });
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn indices(&self, index: usize) -> Result<Vec<u32>> {
+        let accessor = self
+            .doc
+            .accessors
+            .get(index)
+            .ok_or_else(|| format_err!("[glTF] Accessor {} is undefined.", index))?;
+
+        let size = match accessor.component_type {
+            5125 => 4,
+            5123 => 2,
+            5121 => 1,
+            v => bail!("[glTF] Index type {} is not supported.", v),
+        };
+
+        let (bytes, stride, accessor) = self.elements(index, size)?;
+
+        let mut out = Vec::with_capacity(accessor.count);
+        for i in 0..accessor.count {
+            let v = &bytes[i * stride..];
+            out.push(match size {
+                4 => u32::from_le_bytes([v[0], v[1], v[2], v[3]]),
+                2 => u32::from(u16::from_le_bytes([v[0], v[1]])),
+                _ => u32::from(v[0]),
+            });
+        }
+
+        Ok(out)
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Document {
+    scene: Option<usize>,
+    scenes: Vec<Scene>,
+    nodes: Vec<Node>,
+    meshes: Vec<Mesh>,
+    accessors: Vec<Accessor>,
+    #[serde(rename = "bufferViews")]
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Scene {
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Node {
+    name: Option<String>,
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+impl Node {
+    /// Converts into `Transform`, which only supports uniform scaling. The scaling along
+    /// x-axis is used.
+    fn transform(&self) -> Transform {
+        let mut transform = Transform::default();
+
+        if let Some(m) = self.matrix {
+            let x = Vector3::new(m[0], m[1], m[2]);
+            let y = Vector3::new(m[4], m[5], m[6]);
+            let z = Vector3::new(m[8], m[9], m[10]);
+            let scale = Vector3::new(length(x), length(y), length(z));
+
+            transform.position = Vector3::new(m[12], m[13], m[14]);
+            transform.scale = scale.x;
+
+            if scale.x > 0.0 && scale.y > 0.0 && scale.z > 0.0 {
+                let rotation = Matrix3::from_cols(x / scale.x, y / scale.y, z / scale.z);
+                transform.rotation = Quaternion::from(rotation);
+            }
+        }
+
+        if let Some(v) = self.translation {
+            transform.position = Vector3::new(v[0], v[1], v[2]);
+        }
+
+        if let Some(v) = self.rotation {
+            transform.rotation = Quaternion::new(v[3], v[0], v[1], v[2]);
+        }
+
+        if let Some(v) = self.scale {
+            transform.scale = v[0];
+        }
+
+        transform
+    }
+}
+
+fn length(v: Vector3<f32>) -> f32 {
+    (v.x * v.x + v.y * v.y + v.z * v.z).sqrt()
+}
+
+#[derive(Deserialize, Debug)]
+struct Mesh {
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    #[serde(default = "triangles")]
+    mode: u32,
+}
+
+fn triangles() -> u32 {
+    TRIANGLES
+}
+
+#[derive(Deserialize, Debug)]
+struct Accessor {
+    #[serde(rename = "bufferView")]
+    buffer_view: Option<usize>,
+    #[serde(rename = "byteOffset", default)]
+    byte_offset: usize,
+    #[serde(rename = "componentType")]
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+}
+
+#[derive(Deserialize, Debug)]
+struct BufferView {
+    buffer: usize,
+    #[serde(rename = "byteOffset", default)]
+    byte_offset: usize,
+    #[serde(rename = "byteLength")]
+    byte_length: usize,
+    #[serde(rename = "byteStride")]
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Buffer {
+    uri: Option<String>,
+    #[serde(rename = "byteLength")]
+    byte_length: usize,
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn base64() {
+        assert_eq!(super::base64("AAECAw==").unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(super::base64("aGVsbG8").unwrap(), b"hello".to_vec());
+        assert!(super::base64("a*b").is_err());
+    }
+}
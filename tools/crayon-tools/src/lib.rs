@@ -0,0 +1,44 @@
+//! Compiles assets into the resources of crayon.
+//!
+//! Assets are files that could be produced by authoring tools directly, such like `PNG`
+//! images, `OBJ`/`glTF` models and `OGG` clips. The `Bundle` walks through a directory of
+//! assets, imports them into the binary formats that are used by the loaders at runtime,
+//! and writes them with a `Manifest` into the output directory. The output directory
+//! could be mounted by `ResourceParams::dirs` directly.
+//!
+//! # UUID
+//!
+//! Every resource is identified by a UUID, which is assigned when the asset is imported
+//! first time and stored in a `.meta` file next to the asset. The `.meta` files should be
+//! committed with the assets, so that the UUIDs are stable across builds.
+//!
+//! ```rust,ignore
+//! let mut bundle = crayon_tools::Bundle::new("assets", "resources");
+//! bundle.build()?;
+//! ```
+
+extern crate crayon;
+extern crate crayon_audio;
+extern crate crayon_world;
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate serde;
+extern crate miniz_oxide;
+extern crate rand;
+extern crate serde_json;
+
+pub mod bundle;
+pub mod embed;
+pub mod gltf;
+pub mod meta;
+pub mod obj;
+pub mod png;
+pub mod resource;
+
+pub use self::bundle::Bundle;
+pub use self::embed::embed;
+pub use self::meta::Meta;
+pub use self::resource::Resource;
+
+pub type Result<T> = ::std::result::Result<T, failure::Error>;
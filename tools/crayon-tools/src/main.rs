@@ -0,0 +1,22 @@
+extern crate crayon_tools;
+
+use std::env;
+use std::process;
+
+use crayon_tools::Bundle;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: crayon-cli <ASSETS> <RESOURCES>");
+        process::exit(2);
+    }
+
+    match Bundle::new(&args[1], &args[2]).build() {
+        Ok(manifest) => println!("Compiled {} resources.", manifest.items.len()),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
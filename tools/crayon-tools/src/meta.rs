@@ -0,0 +1,77 @@
+//! The `.meta` file stores the UUIDs of resources that an asset produces.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crayon::uuid::{Builder, Uuid, Variant, Version};
+use rand::{self, Rng};
+use serde_json;
+
+use super::Result;
+
+/// The UUIDs of resources that an asset produces, which is stored in a `.meta` file
+/// next to the asset.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Meta {
+    /// The UUIDs of resources keyed by their names, the name of main resource is empty.
+    pub resources: BTreeMap<String, Uuid>,
+}
+
+impl Meta {
+    /// Returns the path of `.meta` file of asset.
+    pub fn path<P: AsRef<Path>>(asset: P) -> PathBuf {
+        let mut path = asset.as_ref().as_os_str().to_owned();
+        path.push(".meta");
+        path.into()
+    }
+
+    /// Loads the `.meta` file of asset, an empty `Meta` is returned if it does not exist.
+    pub fn load<P: AsRef<Path>>(asset: P) -> Result<Meta> {
+        let path = Meta::path(asset);
+        if !path.exists() {
+            return Ok(Meta::default());
+        }
+
+        let bytes = fs::read(&path)?;
+        serde_json::from_slice(&bytes)
+            .map_err(|err| format_err!("Failed to parse {}. {}", path.display(), err))
+    }
+
+    /// Saves the `.meta` file of asset.
+    pub fn save<P: AsRef<Path>>(&self, asset: P) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(self)?;
+        fs::write(Meta::path(asset), bytes)?;
+        Ok(())
+    }
+
+    /// Returns the UUID of resource with name, a random one is assigned if it does not
+    /// exist yet.
+    pub fn uuid(&mut self, name: &str) -> Uuid {
+        *self.resources.entry(name.to_owned()).or_insert_with(|| {
+            let mut bytes = [0; 16];
+            rand::thread_rng().fill(&mut bytes);
+            Builder::from_bytes(bytes)
+                .set_variant(Variant::RFC4122)
+                .set_version(Version::Random)
+                .build()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uuid() {
+        let mut meta = Meta::default();
+        let uuid = meta.uuid("");
+        assert_eq!(meta.uuid(""), uuid);
+        assert!(meta.uuid("mesh") != uuid);
+
+        let json = serde_json::to_string(&meta).unwrap();
+        let meta2: Meta = serde_json::from_str(&json).unwrap();
+        assert_eq!(meta, meta2);
+    }
+}
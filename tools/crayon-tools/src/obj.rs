@@ -0,0 +1,158 @@
+//! Imports Wavefront `OBJ` models. Every object, group or material in the file becomes
+//! a sub-mesh of the imported mesh, and polygons are triangulated as fans.
+
+use std::collections::HashMap;
+
+use crayon::video::assets::prelude::*;
+
+use super::resource::{self, Vertex};
+use super::Result;
+
+/// Imports a `OBJ` file as mesh.
+pub fn import(bytes: &[u8]) -> Result<(MeshParams, MeshData)> {
+    let text = std::str::from_utf8(bytes)?;
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut texcoords: Vec<[f32; 2]> = Vec::new();
+
+    let mut verts = Vec::new();
+    let mut idxes = Vec::new();
+    let mut sub_mesh_offsets = vec![0];
+    let mut lookup = HashMap::new();
+
+    for (no, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let err = || format_err!("[OBJ] Line {} is invalid: {}", no + 1, line);
+
+        match tokens.next() {
+            Some("v") => positions.push(floats(&mut tokens).ok_or_else(err)?),
+            Some("vn") => normals.push(floats(&mut tokens).ok_or_else(err)?),
+            Some("vt") => {
+                let uv: [f32; 2] = floats(&mut tokens).ok_or_else(err)?;
+                texcoords.push([uv[0], 1.0 - uv[1]]);
+            }
+
+            Some("o") | Some("g") | Some("usemtl")
+                if *sub_mesh_offsets.last().unwrap() != idxes.len() =>
+            {
+                sub_mesh_offsets.push(idxes.len());
+            }
+
+            Some("f") => {
+                let mut polygon = Vec::new();
+                for v in tokens {
+                    let key = face_vertex(v, positions.len(), texcoords.len(), normals.len())
+                        .ok_or_else(err)?;
+
+                    let index = *lookup.entry(key).or_insert_with(|| {
+                        verts.push(Vertex {
+                            position: positions[key.0],
+                            texcoord: key.1.map(|i| texcoords[i]).unwrap_or_default(),
+                            normal: key.2.map(|i| normals[i]).unwrap_or_default(),
+                        });
+
+                        verts.len() as u32 - 1
+                    });
+
+                    polygon.push(index);
+                }
+
+                if polygon.len() < 3 {
+                    return Err(err());
+                }
+
+                for i in 1..polygon.len() - 1 {
+                    idxes.extend_from_slice(&[polygon[0], polygon[i], polygon[i + 1]]);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    if idxes.is_empty() {
+        bail!("[OBJ] There are no faces.");
+    }
+
+    if *sub_mesh_offsets.last().unwrap() == idxes.len() {
+        sub_mesh_offsets.pop();
+    }
+
+    Ok(resource::mesh(&verts, &idxes, &sub_mesh_offsets))
+}
+
+fn floats<'a, I, T>(tokens: &mut I) -> Option<T>
+where
+    I: Iterator<Item = &'a str>,
+    T: Default + AsMut<[f32]>,
+{
+    let mut v = T::default();
+    for f in v.as_mut() {
+        *f = tokens.next()?.parse().ok()?;
+    }
+
+    Some(v)
+}
+
+/// Parses the `v`, `v/vt`, `v//vn` or `v/vt/vn` of face into zero-based indices.
+fn face_vertex(
+    v: &str,
+    positions: usize,
+    texcoords: usize,
+    normals: usize,
+) -> Option<(usize, Option<usize>, Option<usize>)> {
+    fn index(v: Option<&str>, len: usize) -> Option<Option<usize>> {
+        match v {
+            None | Some("") => Some(None),
+            Some(v) => {
+                let i: isize = v.parse().ok()?;
+                // Negative indices are relative to the end.
+                let i = if i < 0 { len as isize + i } else { i - 1 };
+                if i >= 0 && (i as usize) < len {
+                    Some(Some(i as usize))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    let mut iter = v.split('/');
+    let position = index(iter.next(), positions)??;
+    let texcoord = index(iter.next(), texcoords)?;
+    let normal = index(iter.next(), normals)?;
+    Some((position, texcoord, normal))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quad() {
+        let obj = "
+            # A quad with two groups.
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vn 0 0 1
+            g first
+            f 1/1/1 2/1/1 3/1/1
+            g second
+            f -4//1 -2//1 -1//1
+        ";
+
+        let (params, data) = import(obj.as_bytes()).unwrap();
+        assert_eq!(params.num_verts, 6);
+        assert_eq!(params.num_idxes, 6);
+        assert_eq!(&params.sub_mesh_offsets[..], &[0, 3]);
+        assert_eq!(data.iptr.len(), 12);
+        assert_eq!(params.aabb.max.x, 1.0);
+
+        assert!(import(b"v 0 0 0\nf 1 2 3").is_err());
+    }
+}
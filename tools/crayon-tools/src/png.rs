@@ -0,0 +1,392 @@
+//! A minimal `PNG` decoder, which supports non-interlaced images of all the color
+//! types and bit depths. Images are always decoded into RGBA8.
+
+use crayon::math::prelude::Vector2;
+use crayon::video::assets::prelude::*;
+use miniz_oxide::inflate;
+
+use super::Result;
+
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+const GRAY: u8 = 0;
+const RGB: u8 = 2;
+const PALETTE: u8 = 3;
+const GRAY_ALPHA: u8 = 4;
+const RGBA: u8 = 6;
+
+/// A decoded image.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub dimensions: Vector2<u32>,
+    /// The pixels in RGBA8, row by row from the top.
+    pub rgba: Vec<u8>,
+}
+
+/// Imports a `PNG` file as texture.
+pub fn import(bytes: &[u8]) -> Result<(TextureParams, TextureData)> {
+    let image = decode(bytes)?;
+
+    let params = TextureParams {
+        format: TextureFormat::RGBA8,
+        dimensions: image.dimensions,
+        ..Default::default()
+    };
+
+    let data = TextureData {
+        bytes: vec![image.rgba.into_boxed_slice()],
+    };
+
+    Ok((params, data))
+}
+
+/// Decodes a `PNG` file into RGBA8 pixels.
+pub fn decode(bytes: &[u8]) -> Result<Image> {
+    if bytes.len() < 8 || bytes[0..8] != SIGNATURE {
+        bail!("[PNG] Signature not match.");
+    }
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut idat = Vec::new();
+
+    let mut cursor = 8;
+    while cursor + 8 <= bytes.len() {
+        let len = be32(&bytes[cursor..]) as usize;
+        let tp = &bytes[cursor + 4..cursor + 8];
+        let start = cursor + 8;
+        let end = start + len;
+
+        if end + 4 > bytes.len() {
+            bail!(
+                "[PNG] Chunk {:?} is truncated.",
+                String::from_utf8_lossy(tp)
+            );
+        }
+
+        let data = &bytes[start..end];
+        match tp {
+            b"IHDR" => header = Some(Header::parse(data)?),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => idat.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+
+        cursor = end + 4;
+    }
+
+    let header = header.ok_or_else(|| format_err!("[PNG] IHDR chunk is missing."))?;
+    if header.color_type == PALETTE && palette.is_empty() {
+        bail!("[PNG] PLTE chunk is missing.");
+    }
+
+    let mut pixels = inflate::decompress_to_vec_zlib(&idat)
+        .map_err(|err| format_err!("[PNG] Failed to inflate image data. {:?}", err))?;
+
+    let stride = header.stride();
+    let expected = (stride + 1)
+        .checked_mul(header.height as usize)
+        .ok_or_else(|| format_err!("[PNG] Image is too large."))?;
+
+    if pixels.len() < expected {
+        bail!("[PNG] Image data is truncated.");
+    }
+
+    unfilter(&mut pixels, stride, header.bytes_per_pixel())?;
+
+    let len = (header.width as usize)
+        .checked_mul(header.height as usize)
+        .and_then(|v| v.checked_mul(4))
+        .ok_or_else(|| format_err!("[PNG] Image is too large."))?;
+
+    let mut rgba = Vec::with_capacity(len);
+    for y in 0..header.height as usize {
+        let row = &pixels[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        for x in 0..header.width as usize {
+            header.rgba(row, x, palette, transparency, &mut rgba);
+        }
+    }
+
+    Ok(Image {
+        dimensions: Vector2::new(header.width, header.height),
+        rgba,
+    })
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Result<Header> {
+        if data.len() < 13 {
+            bail!("[PNG] IHDR chunk is truncated.");
+        }
+
+        let header = Header {
+            width: be32(&data[0..]),
+            height: be32(&data[4..]),
+            depth: data[8],
+            color_type: data[9],
+        };
+
+        let depths: &[u8] = match header.color_type {
+            GRAY => &[1, 2, 4, 8, 16],
+            PALETTE => &[1, 2, 4, 8],
+            RGB | GRAY_ALPHA | RGBA => &[8, 16],
+            v => bail!("[PNG] Color type {} is invalid.", v),
+        };
+
+        if !depths.contains(&header.depth) {
+            bail!(
+                "[PNG] Bit depth {} is invalid for color type {}.",
+                header.depth,
+                header.color_type
+            );
+        }
+
+        if data[12] != 0 {
+            bail!("[PNG] Interlaced image is not supported.");
+        }
+
+        Ok(header)
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            RGB => 3,
+            GRAY_ALPHA => 2,
+            RGBA => 4,
+            _ => 1,
+        }
+    }
+
+    fn stride(&self) -> usize {
+        (self.width as usize * self.channels() * self.depth as usize).div_ceil(8)
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        (self.channels() * self.depth as usize).div_ceil(8)
+    }
+
+    /// Reads the raw value of sample.
+    fn sample(&self, row: &[u8], index: usize) -> u16 {
+        match self.depth {
+            16 => (u16::from(row[index * 2]) << 8) | u16::from(row[index * 2 + 1]),
+            8 => u16::from(row[index]),
+            depth => {
+                let bit = index * depth as usize;
+                let shift = 8 - depth as usize - bit % 8;
+                u16::from((row[bit / 8] >> shift) & ((1 << depth) - 1))
+            }
+        }
+    }
+
+    /// Scales the raw value of sample into 8 bits.
+    fn scale(&self, v: u16) -> u8 {
+        match self.depth {
+            16 => (v >> 8) as u8,
+            8 => v as u8,
+            depth => (u32::from(v) * 255 / ((1 << depth) - 1)) as u8,
+        }
+    }
+
+    fn rgba(&self, row: &[u8], x: usize, palette: &[u8], trns: &[u8], out: &mut Vec<u8>) {
+        let channels = self.channels();
+        let sample = |c: usize| self.sample(row, x * channels + c);
+
+        match self.color_type {
+            PALETTE => {
+                let index = sample(0) as usize;
+                let rgb = palette.get(index * 3..index * 3 + 3).unwrap_or(&[0, 0, 0]);
+                out.extend_from_slice(rgb);
+                out.push(trns.get(index).cloned().unwrap_or(255));
+            }
+
+            GRAY => {
+                let v = sample(0);
+                let opaque = trns.len() < 2 || v != be16(trns);
+                let v = self.scale(v);
+                out.extend_from_slice(&[v, v, v, if opaque { 255 } else { 0 }]);
+            }
+
+            GRAY_ALPHA => {
+                let v = self.scale(sample(0));
+                out.extend_from_slice(&[v, v, v, self.scale(sample(1))]);
+            }
+
+            RGB => {
+                let (r, g, b) = (sample(0), sample(1), sample(2));
+                let opaque = trns.len() < 6
+                    || (r, g, b) != (be16(&trns[0..]), be16(&trns[2..]), be16(&trns[4..]));
+                out.extend_from_slice(&[self.scale(r), self.scale(g), self.scale(b)]);
+                out.push(if opaque { 255 } else { 0 });
+            }
+
+            _ => {
+                for c in 0..4 {
+                    out.push(self.scale(sample(c)));
+                }
+            }
+        }
+    }
+}
+
+/// Reverses the filters of scanlines in place, every scanline starts with its filter type.
+fn unfilter(pixels: &mut [u8], stride: usize, bpp: usize) -> Result<()> {
+    let mut prev = vec![0; stride];
+
+    for row in pixels.chunks_mut(stride + 1) {
+        if row.len() < stride + 1 {
+            break;
+        }
+
+        let (filter, row) = row.split_at_mut(1);
+        match filter[0] {
+            0 => {}
+            1 => {
+                for i in bpp..stride {
+                    row[i] = row[i].wrapping_add(row[i - bpp]);
+                }
+            }
+            2 => {
+                for i in 0..stride {
+                    row[i] = row[i].wrapping_add(prev[i]);
+                }
+            }
+            3 => {
+                for i in 0..stride {
+                    let left = if i >= bpp { row[i - bpp] } else { 0 };
+                    let avg = (u16::from(left) + u16::from(prev[i])) / 2;
+                    row[i] = row[i].wrapping_add(avg as u8);
+                }
+            }
+            4 => {
+                for i in 0..stride {
+                    let (left, upper_left) = if i >= bpp {
+                        (row[i - bpp], prev[i - bpp])
+                    } else {
+                        (0, 0)
+                    };
+                    row[i] = row[i].wrapping_add(paeth(left, prev[i], upper_left));
+                }
+            }
+            v => bail!("[PNG] Filter type {} is invalid.", v),
+        }
+
+        prev.copy_from_slice(row);
+    }
+
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let pa = (p - i16::from(a)).abs();
+    let pb = (p - i16::from(b)).abs();
+    let pc = (p - i16::from(c)).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+    (u32::from(bytes[0]) << 24)
+        | (u32::from(bytes[1]) << 16)
+        | (u32::from(bytes[2]) << 8)
+        | u32::from(bytes[3])
+}
+
+fn be16(bytes: &[u8]) -> u16 {
+    (u16::from(bytes[0]) << 8) | u16::from(bytes[1])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use miniz_oxide::deflate;
+
+    /// Encodes a PNG file with the scanlines, which are prefixed with filter types.
+    pub fn encode(width: u32, height: u32, depth: u8, color_type: u8, lines: &[u8]) -> Vec<u8> {
+        fn chunk(out: &mut Vec<u8>, tp: &[u8], data: &[u8]) {
+            out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            out.extend_from_slice(tp);
+            out.extend_from_slice(data);
+            // The CRC is not verified by the decoder.
+            out.extend_from_slice(&[0; 4]);
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[depth, color_type, 0, 0, 0]);
+
+        let mut out = SIGNATURE.to_vec();
+        chunk(&mut out, b"IHDR", &header);
+        chunk(&mut out, b"IDAT", &deflate::compress_to_vec_zlib(lines, 6));
+        chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    #[test]
+    fn rgba() {
+        let lines = [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, //
+            2, 1, 1, 1, 1, 1, 1, 1, 1, // Up
+        ];
+
+        let image = decode(&encode(2, 2, 8, RGBA, &lines)).unwrap();
+        assert_eq!(image.dimensions, Vector2::new(2, 2));
+        assert_eq!(
+            image.rgba,
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 2, 3, 4, 5, 6, 7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn oversized() {
+        let bytes = encode(u32::MAX, u32::MAX, 16, RGBA, &[0]);
+        assert!(decode(&bytes).is_err());
+
+        let bytes = encode(0x1000_0000, 0x1000_0000, 8, RGBA, &[0]);
+        assert!(decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn filters() {
+        let lines = [
+            1, 10, 20, 30, 5, 5, 5, // Sub
+            3, 0, 0, 0, 0, 0, 0, // Average
+            4, 1, 1, 1, 1, 1, 1, // Paeth
+        ];
+
+        let image = decode(&encode(2, 3, 8, RGB, &lines)).unwrap();
+        let rgb: Vec<_> = image.rgba.chunks(4).map(|v| [v[0], v[1], v[2]]).collect();
+        assert_eq!(rgb[0], [10, 20, 30]);
+        assert_eq!(rgb[1], [15, 25, 35]);
+        assert_eq!(rgb[2], [5, 10, 15]);
+        assert_eq!(rgb[3], [10, 17, 25]);
+        assert_eq!(rgb[4], [6, 11, 16]);
+        assert_eq!(rgb[5], [11, 18, 26]);
+    }
+
+    #[test]
+    fn gray() {
+        let lines = [0, 0b1001_0000];
+        let image = decode(&encode(4, 1, 1, GRAY, &lines)).unwrap();
+        assert_eq!(&image.rgba[0..4], &[255, 255, 255, 255]);
+        assert_eq!(&image.rgba[4..8], &[0, 0, 0, 255]);
+        assert_eq!(&image.rgba[12..16], &[255, 255, 255, 255]);
+    }
+}
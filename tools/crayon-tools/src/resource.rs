@@ -0,0 +1,175 @@
+//! The resources that are produced by importing assets, and their binary formats which
+//! are read by the loaders at runtime.
+
+use crayon::bincode;
+use crayon::math::prelude::{Aabb3, Point3};
+use crayon::video::assets::mesh_loader;
+use crayon::video::assets::prelude::*;
+use crayon::video::assets::texture_loader;
+use crayon_audio::assets::clip_loader;
+use crayon_world::assets::prefab::Prefab;
+use crayon_world::assets::prefab_loader;
+
+use super::Result;
+
+/// A serialized resource.
+#[derive(Debug, Clone)]
+pub struct Resource {
+    /// The name of resource in its asset, the main resource has an empty name.
+    pub name: String,
+    /// The serialized bytes, which starts with the header of format.
+    pub bytes: Vec<u8>,
+    /// The indices of resources in the same asset that this one depends on.
+    pub dependencies: Vec<usize>,
+}
+
+impl Resource {
+    /// Serializes a texture into `VTEX` format.
+    pub fn texture<T: Into<String>>(
+        name: T,
+        params: &TextureParams,
+        data: &TextureData,
+    ) -> Result<Resource> {
+        params.validate(Some(data))?;
+
+        let mut payload = Vec::new();
+        bincode::serialize_into(&mut payload, params)?;
+        bincode::serialize_into(&mut payload, data)?;
+        Ok(Resource::new(name, texture_loader::FORMAT.write(&payload)))
+    }
+
+    /// Serializes a mesh into `VMSH` format.
+    pub fn mesh<T: Into<String>>(
+        name: T,
+        params: &MeshParams,
+        data: &MeshData,
+    ) -> Result<Resource> {
+        params.validate(Some(data))?;
+
+        let mut payload = Vec::new();
+        bincode::serialize_into(&mut payload, params)?;
+        bincode::serialize_into(&mut payload, data)?;
+        Ok(Resource::new(name, mesh_loader::FORMAT.write(&payload)))
+    }
+
+    /// Wraps a `OGG` file into `CSFX` format.
+    pub fn audio<T: Into<String>>(name: T, ogg: &[u8]) -> Result<Resource> {
+        if ogg.len() < 4 || &ogg[0..4] != b"OggS" {
+            bail!("The audio clip is not a OGG file.");
+        }
+
+        Ok(Resource::new(name, clip_loader::FORMAT.write(ogg)))
+    }
+
+    /// Serializes a prefab into `PREB` format. The meshes of prefab should be the
+    /// dependencies.
+    pub fn prefab<T: Into<String>>(
+        name: T,
+        prefab: &Prefab,
+        dependencies: Vec<usize>,
+    ) -> Result<Resource> {
+        let payload = bincode::serialize(prefab)?;
+
+        let mut resource = Resource::new(name, prefab_loader::FORMAT.write(&payload));
+        resource.dependencies = dependencies;
+        Ok(resource)
+    }
+
+    fn new<T: Into<String>>(name: T, bytes: Vec<u8>) -> Resource {
+        Resource {
+            name: name.into(),
+            bytes,
+            dependencies: Vec::new(),
+        }
+    }
+}
+
+/// The vertex of imported meshes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub texcoord: [f32; 2],
+}
+
+impl Vertex {
+    pub fn layout() -> VertexLayout {
+        VertexLayout::build()
+            .with(Attribute::Position, VertexFormat::Float, 3, false)
+            .with(Attribute::Normal, VertexFormat::Float, 3, false)
+            .with(Attribute::Texcoord0, VertexFormat::Float, 2, false)
+            .finish()
+    }
+}
+
+/// Builds a mesh of triangles from vertices and indices. The `sub_mesh_offsets` are the
+/// start indices of sub-meshes.
+pub fn mesh(verts: &[Vertex], idxes: &[u32], sub_mesh_offsets: &[usize]) -> (MeshParams, MeshData) {
+    let mut params = MeshParams {
+        layout: Vertex::layout(),
+        num_verts: verts.len(),
+        num_idxes: idxes.len(),
+        sub_mesh_offsets: sub_mesh_offsets.iter().cloned().collect(),
+        ..Default::default()
+    };
+
+    let mut vptr = Vec::with_capacity(verts.len() * params.layout.stride() as usize);
+    for v in verts {
+        for f in v.position.iter().chain(&v.normal).chain(&v.texcoord) {
+            vptr.extend_from_slice(&f.to_le_bytes());
+        }
+    }
+
+    let mut iptr = Vec::new();
+    if verts.len() <= usize::from(u16::MAX) + 1 {
+        params.index_format = IndexFormat::U16;
+        for &v in idxes {
+            iptr.extend_from_slice(&(v as u16).to_le_bytes());
+        }
+    } else {
+        params.index_format = IndexFormat::U32;
+        for &v in idxes {
+            iptr.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    if let Some(first) = verts.first() {
+        let mut aabb = Aabb3::new(Point3::from(first.position), Point3::from(first.position));
+        for v in verts {
+            aabb = aabb.grow(Point3::from(v.position));
+        }
+        params.aabb = aabb;
+    }
+
+    let data = MeshData {
+        vptr: vptr.into_boxed_slice(),
+        iptr: iptr.into_boxed_slice(),
+    };
+
+    (params, data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mesh() {
+        let verts = [Vertex::default(); 3];
+        let (params, data) = super::mesh(&verts, &[0, 1, 2], &[0]);
+        assert_eq!(params.index_format, IndexFormat::U16);
+        assert_eq!(data.vptr.len(), params.vertex_buffer_len());
+        assert_eq!(data.iptr.len(), params.index_buffer_len());
+
+        let rsp = Resource::mesh("", &params, &data).unwrap();
+        assert!(mesh_loader::FORMAT.read(&rsp.bytes).is_ok());
+    }
+
+    #[test]
+    fn audio() {
+        assert!(Resource::audio("", b"RIFF").is_err());
+
+        let rsp = Resource::audio("", b"OggS....").unwrap();
+        assert_eq!(&clip_loader::FORMAT.read(&rsp.bytes).unwrap()[..], b"OggS....");
+    }
+}
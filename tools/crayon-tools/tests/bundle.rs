@@ -0,0 +1,115 @@
+extern crate crayon;
+extern crate crayon_tools;
+
+use std::fs;
+
+use crayon::res::manifest::{self, ManfiestResolver};
+use crayon::res::vfs::user::User;
+use crayon_tools::{Bundle, Meta};
+
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let v = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | u32::from(b) << (16 - i * 8));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(v >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+fn triangle() -> String {
+    let mut bytes = Vec::new();
+    for v in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    for v in &[0u16, 1, 2, 0] {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    format!(
+        r#"{{
+            "scene": 0,
+            "scenes": [{{ "nodes": [0] }}],
+            "nodes": [
+                {{ "name": "root", "children": [1] }},
+                {{ "name": "triangle", "mesh": 0, "translation": [1, 2, 3] }}
+            ],
+            "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }}] }}],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }},
+                {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+            ],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+            ],
+            "buffers": [{{ "byteLength": 44, "uri": "data:application/octet-stream;base64,{}" }}]
+        }}"#,
+        base64(&bytes)
+    )
+}
+
+#[test]
+fn build() {
+    // The temporary directory is removed when it's dropped.
+    let root = User::temp().unwrap();
+    let src = root.root().join("assets");
+    let dst = root.root().join("resources");
+
+    fs::create_dir_all(src.join("models")).unwrap();
+    fs::write(src.join("quad.obj"), "v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\n").unwrap();
+    fs::write(src.join("models/triangle.gltf"), triangle()).unwrap();
+    fs::write(src.join("click.ogg"), b"OggS....").unwrap();
+    fs::write(src.join("README"), b"ignored").unwrap();
+
+    let manifest = Bundle::new(&src, &dst).build().unwrap();
+    assert_eq!(manifest.items.len(), 4);
+
+    let mut resolver = ManfiestResolver::new();
+    let bytes = fs::read(dst.join(manifest::NAME)).unwrap();
+    resolver.add("res:", &mut &bytes[..]).unwrap();
+
+    let prefab = resolver.find("res:/models/triangle.gltf").unwrap();
+    let mesh = resolver.find("res:/models/triangle.gltf#mesh0").unwrap();
+    assert!(resolver.find("res:/quad.obj").is_some());
+    assert!(resolver.find("res:/click.ogg").is_some());
+    assert!(resolver.find("res:/README").is_none());
+
+    let item = manifest.items.iter().find(|v| v.uuid == prefab).unwrap();
+    let dependencies = manifest.buf.as_slice(item.dependencies);
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(manifest.items[dependencies[0]].uuid, mesh);
+
+    let path = resolver.resolve(mesh).unwrap();
+    let bytes = fs::read(dst.join(path.rsplit('/').next().unwrap())).unwrap();
+    assert!(crayon::video::assets::mesh_loader::FORMAT
+        .read(&bytes)
+        .is_ok());
+
+    let item = manifest.items.iter().find(|v| v.uuid == mesh).unwrap();
+    assert_eq!(item.hash, Some(crayon::res::header::crc32(&bytes)));
+    assert_eq!(item.size, Some(bytes.len() as u64));
+
+    // UUIDs are stable across builds.
+    let meta = Meta::load(src.join("models/triangle.gltf")).unwrap();
+    assert_eq!(meta.resources[""], prefab);
+    assert_eq!(meta.resources["mesh0"], mesh);
+
+    let manifest = Bundle::new(&src, &dst).build().unwrap();
+    assert!(manifest.items.iter().any(|v| v.uuid == prefab));
+
+    fs::write(src.join("broken.obj"), "f 1 2 3\n").unwrap();
+    assert!(Bundle::new(&src, &dst).build().is_err());
+}